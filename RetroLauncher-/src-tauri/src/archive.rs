@@ -0,0 +1,683 @@
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::downloader::ProgressEvent;
+
+// Archive formats the game, emulator and core installers can unpack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Rar,
+    Tar,
+    TarGz,
+    TarXz,
+    TarBz2,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    // From the file name, falling back to the magic bytes for files without a usable extension
+    pub fn detect(path: &Path) -> Option<Self> {
+        Self::from_name(path).or_else(|| Self::from_magic(path))
+    }
+
+    pub fn from_name(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let format = if name.ends_with(".zip") {
+            ArchiveFormat::Zip
+        } else if name.ends_with(".7z") {
+            ArchiveFormat::SevenZip
+        } else if name.ends_with(".rar") {
+            ArchiveFormat::Rar
+        } else if name.ends_with(".tar") {
+            ArchiveFormat::Tar
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            ArchiveFormat::TarGz
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            ArchiveFormat::TarXz
+        } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz2") {
+            ArchiveFormat::TarBz2
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            ArchiveFormat::TarZst
+        } else {
+            return None;
+        };
+        Some(format)
+    }
+
+    fn from_magic(path: &Path) -> Option<Self> {
+        let mut magic = [0u8; 8];
+        let read = File::open(path).and_then(|mut f| f.read(&mut magic)).ok()?;
+        let magic = &magic[..read];
+        if magic.starts_with(b"PK\x03\x04") {
+            Some(ArchiveFormat::Zip)
+        } else if magic.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Some(ArchiveFormat::SevenZip)
+        } else if magic.starts_with(b"Rar!\x1a\x07") {
+            Some(ArchiveFormat::Rar)
+        } else {
+            // Compressed tarballs can't be told apart from plain gz/xz streams by magic alone
+            None
+        }
+    }
+}
+
+// Most data one extraction may write, nested archives included. Fits dual-layer DVD
+// images with room to spare while stopping decompression bombs.
+pub const DEFAULT_MAX_SIZE: u64 = 32 * 1024 * 1024 * 1024;
+
+// Windows attribute bits stored by 7z and RAR
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    // How many levels of archives found inside the archive are unpacked too (a .7z inside
+    // a .zip...). 0 leaves them as files, which is what emulator and core installs want.
+    pub nested_depth: usize,
+    // Delete nested archives once they have been unpacked
+    pub remove_nested: bool,
+    // Uncompressed bytes the extraction may write before it is aborted
+    pub max_size: u64,
+    // Set from another thread to stop the extraction; checked before every entry and chunk
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            nested_depth: 0,
+            remove_nested: true,
+            max_size: DEFAULT_MAX_SIZE,
+            cancel: None,
+        }
+    }
+}
+
+// Bytes written so far against `ExtractOptions::max_size`. Sizes declared by the archive
+// are checked up front, but only the bytes actually written are trusted. Every check
+// also stops a cancelled extraction.
+struct Budget {
+    used: u64,
+    limit: u64,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    fn check_cancelled(&self) -> io::Result<()> {
+        if self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "Extraction cancelled"));
+        }
+        Ok(())
+    }
+
+    fn check_declared(&self, declared: u64) -> io::Result<()> {
+        self.check_cancelled()?;
+        if self.used.saturating_add(declared) > self.limit {
+            return Err(self.exceeded());
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, n: u64) -> io::Result<()> {
+        self.check_cancelled()?;
+        self.used += n;
+        if self.used > self.limit {
+            return Err(self.exceeded());
+        }
+        Ok(())
+    }
+
+    fn exceeded(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Archive expands to more than {} MB, the extraction limit",
+            self.limit / (1024 * 1024)
+        ))
+    }
+}
+
+// Extraction progress, reported on "download-progress" as a percentage of the archive
+struct Progress<'a> {
+    event: &'a ProgressEvent,
+    app_handle: Option<&'a tauri::AppHandle>,
+    name: String,
+    total: u64,
+    done: u64,
+    last_emit: Instant,
+}
+
+impl<'a> Progress<'a> {
+    fn new(event: &'a ProgressEvent, app_handle: Option<&'a tauri::AppHandle>, archive: &Path, total: u64) -> Self {
+        Progress {
+            event,
+            app_handle,
+            name: archive.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            total,
+            done: 0,
+            last_emit: Instant::now() - Duration::from_secs(1),
+        }
+    }
+
+    fn set(&mut self, done: u64) {
+        self.done = done;
+        if self.last_emit.elapsed() >= Duration::from_millis(120) {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        let pct = if self.total > 0 {
+            (self.done as f64 / self.total as f64 * 100.0).min(100.0) as f32
+        } else {
+            0.0
+        };
+        let message = format!("{} ({}: {:.0}%)", self.event.message, self.name, pct);
+        self.event.emit(self.app_handle, pct, &message, self.done, self.total, 0.0);
+        self.last_emit = Instant::now();
+    }
+}
+
+// Unpack `archive` into `dest` and return the extracted files. Archives found inside are
+// unpacked next to themselves up to `options.nested_depth` levels deep. Entries that would
+// land outside `dest` fail the extraction, symlinks are skipped, and at most
+// `options.max_size` bytes are written. Blocking: call it from spawn_blocking in async code.
+pub fn extract_archive(
+    archive: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    let format = ArchiveFormat::detect(archive).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unsupported archive format: {}", archive.display()),
+    ))?;
+    fs::create_dir_all(dest)?;
+
+    let mut budget = Budget { used: 0, limit: options.max_size, cancel: options.cancel.clone() };
+    let mut files = extract_one(format, archive, dest, &mut budget, event, app_handle)?;
+
+    for _ in 0..options.nested_depth {
+        let nested: Vec<(PathBuf, ArchiveFormat)> = files.iter()
+            .filter_map(|f| ArchiveFormat::from_name(f).map(|format| (f.clone(), format)))
+            .collect();
+        if nested.is_empty() {
+            break;
+        }
+        for (path, format) in nested {
+            let parent = path.parent().unwrap_or(dest).to_path_buf();
+            let inner = extract_one(format, &path, &parent, &mut budget, event, app_handle)?;
+            if options.remove_nested {
+                fs::remove_file(&path)?;
+                files.retain(|f| f != &path);
+            }
+            files.extend(inner);
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn extract_one(
+    format: ArchiveFormat,
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive, dest, budget, event, app_handle),
+        ArchiveFormat::SevenZip => extract_7z(archive, dest, budget, event, app_handle),
+        ArchiveFormat::Rar => extract_rar(archive, dest, budget, event, app_handle),
+        _ => extract_tar(format, archive, dest, budget, event, app_handle),
+    }
+}
+
+fn invalid<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+// Where the entry `name` is written inside `dest`, or None when it names `dest` itself.
+// Absolute names and drive prefixes are made relative; names whose ".." components climb
+// out of `dest` are rejected (zip-slip).
+fn entry_path(dest: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let mut parts: Vec<&str> = Vec::new();
+    for (index, part) in name.split(['/', '\\']).enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "Archive entry '{}' points outside the extraction directory", name
+                    )));
+                }
+            }
+            // "C:" of "C:\Windows\..."
+            _ if index == 0 && part.len() == 2 && part.ends_with(':') => {}
+            _ => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(parts.iter().fold(dest.to_path_buf(), |path, part| path.join(part))))
+}
+
+fn is_symlink_mode(mode: u32) -> bool {
+    mode & 0o170000 == 0o120000
+}
+
+// Copy one entry to `out`, reporting every chunk written. A file that breaks the size
+// limit is removed again.
+fn write_entry(
+    reader: &mut dyn Read,
+    out: &Path,
+    budget: &mut Budget,
+    on_chunk: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(out)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if let Err(e) = budget.take(n as u64) {
+            drop(file);
+            let _ = fs::remove_file(out);
+            return Err(e);
+        }
+        file.write_all(&buf[..n])?;
+        on_chunk(n as u64);
+    }
+    Ok(())
+}
+
+fn extract_zip(
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?)).map_err(invalid)?;
+    let total = (0..zip.len())
+        .filter_map(|i| zip.by_index_raw(i).ok().map(|f| f.size()))
+        .sum();
+    budget.check_declared(total)?;
+    let mut progress = Progress::new(event, app_handle, archive, total);
+    let mut files = Vec::new();
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(invalid)?;
+        if entry.unix_mode().map(is_symlink_mode).unwrap_or(false) {
+            eprintln!("Skipping symlink {} in {:?}", entry.name(), archive);
+            continue;
+        }
+        let Some(out) = entry_path(dest, entry.name())? else { continue };
+        if entry.is_dir() {
+            fs::create_dir_all(&out)?;
+            continue;
+        }
+        let mut done = progress.done;
+        write_entry(&mut entry, &out, budget, &mut |n| {
+            done += n;
+            progress.set(done);
+        })?;
+        files.push(out);
+    }
+
+    progress.emit();
+    Ok(files)
+}
+
+fn extract_7z(
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())
+        .map_err(invalid)?;
+    let total = reader.archive().files.iter().map(|f| f.size()).sum();
+    budget.check_declared(total)?;
+    let mut progress = Progress::new(event, app_handle, archive, total);
+    let mut files = Vec::new();
+
+    reader.for_each_entries(|entry, data| {
+        // p7zip keeps the Unix mode in the high 16 bits of the attributes
+        let attributes = entry.windows_attributes();
+        let symlink = entry.has_windows_attributes
+            && (attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+                || (attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0 && is_symlink_mode(attributes >> 16)));
+        if symlink || entry.is_anti_item() {
+            eprintln!("Skipping {} in {:?}", entry.name(), archive);
+            return Ok(true);
+        }
+        let Some(out) = entry_path(dest, entry.name()).map_err(sevenz_rust::Error::io)? else {
+            return Ok(true);
+        };
+        if entry.is_directory() {
+            fs::create_dir_all(&out).map_err(sevenz_rust::Error::io)?;
+            return Ok(true);
+        }
+        let mut done = progress.done;
+        write_entry(data, &out, budget, &mut |n| {
+            done += n;
+            progress.set(done);
+        }).map_err(sevenz_rust::Error::io)?;
+        files.push(out);
+        Ok(true)
+    }).map_err(invalid)?;
+
+    progress.emit();
+    Ok(files)
+}
+
+fn extract_rar(
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    let archive_path = archive.to_path_buf();
+    let total = unrar::Archive::new(&archive_path)
+        .open_for_listing()
+        .map_err(invalid)?
+        .filter_map(|header| header.ok())
+        .map(|header| header.unpacked_size)
+        .sum();
+    budget.check_declared(total)?;
+    let mut progress = Progress::new(event, app_handle, archive, total);
+    let mut files = Vec::new();
+
+    // unrar extracts whole files and can't be streamed through the budget. Its unpacker
+    // stops at the entry's declared size, so that is checked before anything is written,
+    // then the file on disk is held to it. Progress moves one entry at a time.
+    let mut rar = unrar::Archive::new(&archive_path).open_for_processing().map_err(invalid)?;
+    while let Some(header) = rar.read_header().map_err(invalid)? {
+        let entry = header.entry();
+        let symlink = is_symlink_mode(entry.file_attr) || entry.file_attr & FILE_ATTRIBUTE_REPARSE_POINT != 0;
+        let out = match entry_path(dest, &entry.filename.to_string_lossy())? {
+            Some(out) if !symlink => out,
+            _ => {
+                rar = header.skip().map_err(invalid)?;
+                continue;
+            }
+        };
+        rar = if entry.is_directory() {
+            fs::create_dir_all(&out)?;
+            header.skip().map_err(invalid)?
+        } else {
+            if let Some(parent) = out.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let declared = entry.unpacked_size;
+            budget.check_declared(declared)?;
+            let next = header.extract_to(&out).map_err(invalid)?;
+            let written = fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+            let check = if written > declared {
+                Err(invalid(format!("{} is larger than its declared size", out.display())))
+            } else {
+                budget.take(written)
+            };
+            if let Err(e) = check {
+                let _ = fs::remove_file(&out);
+                return Err(e);
+            }
+            files.push(out);
+            progress.set(progress.done + written);
+            next
+        };
+    }
+
+    progress.emit();
+    Ok(files)
+}
+
+// Counts the compressed bytes consumed, which is the only progress measure a
+// compressed tar stream offers without decompressing it twice
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+fn extract_tar(
+    format: ArchiveFormat,
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    event: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> io::Result<Vec<PathBuf>> {
+    let file = File::open(archive)?;
+    let total = file.metadata()?.len();
+    let consumed = Rc::new(Cell::new(0));
+    let counted = BufReader::new(CountingReader { inner: file, count: consumed.clone() });
+    let stream: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(counted)),
+        ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new(counted)),
+        ArchiveFormat::TarBz2 => Box::new(bzip2::read::BzDecoder::new(counted)),
+        ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::new(counted)?),
+        _ => Box::new(counted),
+    };
+
+    let mut progress = Progress::new(event, app_handle, archive, total);
+    let mut files = Vec::new();
+    let mut tar = tar::Archive::new(stream);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let Some(out) = entry_path(dest, &name)? else { continue };
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => fs::create_dir_all(&out)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                write_entry(&mut entry, &out, budget, &mut |_| progress.set(consumed.get()))?;
+                files.push(out);
+            }
+            // Symlinks, hard links, devices and other special entries are never extracted
+            _ => {}
+        }
+    }
+
+    progress.emit();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+    use zip::write::{FileOptions, ZipWriter};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-archive-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn event() -> ProgressEvent {
+        ProgressEvent {
+            slug: "test".to_string(),
+            stage: "extracting".to_string(),
+            message: "Extracting".to_string(),
+            title: None,
+        }
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_7z(path: &Path, entries: Vec<(SevenZArchiveEntry, &[u8])>) {
+        let mut writer = SevenZWriter::create(path).unwrap();
+        for (entry, data) in entries {
+            writer.push_archive_entry(entry, Some(data)).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn sevenz_entry(name: &str) -> SevenZArchiveEntry {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        entry.has_stream = true;
+        entry
+    }
+
+    #[test]
+    fn rejects_zip_entries_escaping_the_destination() {
+        let dir = test_dir("zip-slip");
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, &[("game.nes", b"rom"), ("../../evil.txt", b"owned")]);
+        let dest = dir.join("a").join("b").join("out");
+
+        let result = extract_archive(&archive, &dest, &ExtractOptions::default(), &event(), None);
+
+        assert!(result.is_err());
+        assert!(!dir.join("a").join("evil.txt").exists());
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn keeps_absolute_and_dotted_zip_entries_inside_the_destination() {
+        let dir = test_dir("zip-absolute");
+        let archive = dir.join("paths.zip");
+        write_zip(&archive, &[("/tmp/abs.txt", b"1"), ("C:\\Windows\\win.ini", b"2"), ("a/../b/./c.txt", b"3")]);
+        let dest = dir.join("out");
+
+        let files = extract_archive(&archive, &dest, &ExtractOptions::default(), &event(), None).unwrap();
+
+        assert_eq!(files, vec![
+            dest.join("Windows").join("win.ini"),
+            dest.join("b").join("c.txt"),
+            dest.join("tmp").join("abs.txt"),
+        ]);
+    }
+
+    #[test]
+    fn skips_zip_symlinks() {
+        let dir = test_dir("zip-symlink");
+        let archive = dir.join("link.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        zip.add_symlink("passwd", "/etc/passwd", FileOptions::default()).unwrap();
+        zip.start_file("game.gba", FileOptions::default()).unwrap();
+        zip.write_all(b"rom").unwrap();
+        zip.finish().unwrap();
+        let dest = dir.join("out");
+
+        let files = extract_archive(&archive, &dest, &ExtractOptions::default(), &event(), None).unwrap();
+
+        assert_eq!(files, vec![dest.join("game.gba")]);
+        assert!(fs::symlink_metadata(dest.join("passwd")).is_err());
+    }
+
+    #[test]
+    fn enforces_the_maximum_uncompressed_size() {
+        let dir = test_dir("zip-bomb");
+        let archive = dir.join("bomb.zip");
+        let zeros = vec![0u8; 1024 * 1024];
+        write_zip(&archive, &[("a.bin", &zeros), ("b.bin", &zeros)]);
+        let options = ExtractOptions { max_size: 1024 * 1024 + 10, ..Default::default() };
+
+        let result = extract_archive(&archive, &dir.join("out"), &options, &event(), None);
+
+        assert!(result.is_err());
+        assert!(!dir.join("out").join("b.bin").exists());
+    }
+
+    #[test]
+    fn enforces_the_size_limit_on_streamed_tarballs() {
+        let dir = test_dir("tar-bomb");
+        let archive = dir.join("bomb.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        let zeros = vec![0u8; 256 * 1024];
+        let mut header = tar::Header::new_gnu();
+        header.set_size(zeros.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "big.bin", zeros.as_slice()).unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+        let options = ExtractOptions { max_size: 64 * 1024, ..Default::default() };
+
+        let result = extract_archive(&archive, &dir.join("out"), &options, &event(), None);
+
+        assert!(result.is_err());
+        assert!(!dir.join("out").join("big.bin").exists());
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let dir = test_dir("cancel");
+        let archive = dir.join("game.zip");
+        write_zip(&archive, &[("game.gba", b"rom")]);
+        let options = ExtractOptions { cancel: Some(Arc::new(AtomicBool::new(true))), ..Default::default() };
+
+        let error = extract_archive(&archive, &dir.join("out"), &options, &event(), None).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!dir.join("out").join("game.gba").exists());
+    }
+
+    #[test]
+    fn rejects_7z_entries_escaping_the_destination() {
+        let dir = test_dir("7z-slip");
+        let archive = dir.join("evil.7z");
+        write_7z(&archive, vec![
+            (sevenz_entry("game.iso"), b"disc"),
+            (sevenz_entry("../evil.txt"), b"owned"),
+        ]);
+        let dest = dir.join("out");
+
+        let result = extract_archive(&archive, &dest, &ExtractOptions::default(), &event(), None);
+
+        assert!(result.is_err());
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn skips_7z_symlinks() {
+        let dir = test_dir("7z-symlink");
+        let archive = dir.join("link.7z");
+        let mut link = sevenz_entry("passwd");
+        link.has_windows_attributes = true;
+        link.windows_attributes = FILE_ATTRIBUTE_UNIX_EXTENSION | (0o120777 << 16);
+        write_7z(&archive, vec![(link, b"/etc/passwd"), (sevenz_entry("game.iso"), b"disc")]);
+        let dest = dir.join("out");
+
+        let files = extract_archive(&archive, &dest, &ExtractOptions::default(), &event(), None).unwrap();
+
+        assert_eq!(files, vec![dest.join("game.iso")]);
+        assert!(fs::symlink_metadata(dest.join("passwd")).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use md5::{Digest, Md5};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::scanner;
+
+// BIOS dumps are a few MiB at most; anything larger is not worth hashing on import
+const MAX_BIOS_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BiosFile {
+    // Path inside RetroArch's system directory
+    pub filename: &'static str,
+    pub md5: &'static str,
+}
+
+// Which cores refuse to start without a BIOS
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "kind", content = "cores", rename_all = "lowercase")]
+pub enum Need {
+    // Every core for the platform
+    Always,
+    // Cores whose id contains one of these; the others emulate the BIOS
+    Cores(&'static [&'static str]),
+    // Used when present (boot logos, accuracy), never required
+    Optional,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BiosRequirement {
+    pub platform: &'static str,
+    pub description: &'static str,
+    // Any one of these satisfies the requirement, e.g. one BIOS per region
+    pub files: &'static [BiosFile],
+    pub need: Need,
+    // ROM extensions the BIOS is for; empty for every game of the platform
+    pub content: &'static [&'static str],
+}
+
+impl BiosRequirement {
+    pub fn required_by(&self, core_id: &str) -> bool {
+        match self.need {
+            Need::Always => true,
+            Need::Cores(cores) => cores.iter().any(|c| core_id.contains(c)),
+            Need::Optional => false,
+        }
+    }
+
+    fn applies_to(&self, rom_path: &Path) -> bool {
+        self.content.is_empty() || self.content.contains(&scanner::extension_of(rom_path).as_str())
+    }
+}
+
+// Known-good dumps, as listed in the libretro core documentation
+pub const REGISTRY: &[BiosRequirement] = &[
+    BiosRequirement {
+        platform: "ps1",
+        description: "PlayStation BIOS (any region)",
+        files: &[
+            BiosFile { filename: "scph5501.bin", md5: "490f666e1afb15b7362b406ed1cea246" },
+            BiosFile { filename: "scph5500.bin", md5: "8dd7d5296a650fac7319bce665a6a53c" },
+            BiosFile { filename: "scph5502.bin", md5: "32736f17079d0b2b7024407c39bd3050" },
+            BiosFile { filename: "scph1001.bin", md5: "924e392ed05558ffdb115408c263dccf" },
+        ],
+        need: Need::Cores(&["beetle_psx", "mednafen_psx", "swanstation"]),
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "segacd",
+        description: "Sega CD / Mega CD BIOS (any region)",
+        files: &[
+            BiosFile { filename: "bios_CD_U.bin", md5: "2efd74e3232ff260e371b99f84024f7f" },
+            BiosFile { filename: "bios_CD_E.bin", md5: "e66fa1dc5820d254611fdcdba0662372" },
+            BiosFile { filename: "bios_CD_J.bin", md5: "278a9397d192149e84e820ac621a8edd" },
+        ],
+        need: Need::Always,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "saturn",
+        description: "Sega Saturn BIOS (any region)",
+        files: &[
+            BiosFile { filename: "mpr-17933.bin", md5: "3240872c70984b6cbfda1586cab68dbe" },
+            BiosFile { filename: "sega_101.bin", md5: "85ec9ca47d8f6807718151cbcca8b964" },
+        ],
+        need: Need::Cores(&["mednafen_saturn", "beetle_saturn"]),
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "pce",
+        description: "PC Engine CD System Card 3.0",
+        files: &[BiosFile { filename: "syscard3.pce", md5: "38179df8f4ac870017db21ebcbf53114" }],
+        need: Need::Always,
+        content: &["cue", "ccd", "chd", "m3u"],
+    },
+    BiosRequirement {
+        platform: "nes",
+        description: "Famicom Disk System BIOS",
+        files: &[BiosFile { filename: "disksys.rom", md5: "ca30b50f880eb660a320674ed365ef7a" }],
+        need: Need::Always,
+        content: &["fds"],
+    },
+    BiosRequirement {
+        platform: "lynx",
+        description: "Atari Lynx boot ROM",
+        files: &[BiosFile { filename: "lynxboot.img", md5: "fcd403db69f54290b51035d82f835e7b" }],
+        need: Need::Always,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "gba",
+        description: "Game Boy Advance BIOS",
+        files: &[BiosFile { filename: "gba_bios.bin", md5: "a860e8c0b6d573d191e4ec7db1b1e4f6" }],
+        need: Need::Cores(&["gpsp"]),
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "gb",
+        description: "Game Boy boot ROM",
+        files: &[BiosFile { filename: "gb_bios.bin", md5: "32fbbd84168d3482956eb3c5051637f5" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "gbc",
+        description: "Game Boy Color boot ROM",
+        files: &[BiosFile { filename: "gbc_bios.bin", md5: "dbfce9db9deaa2567f6a84fde55f9680" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "nds",
+        description: "Nintendo DS ARM7 BIOS",
+        files: &[BiosFile { filename: "bios7.bin", md5: "df692a80a5b1bc90728bc3dfc76cd948" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "nds",
+        description: "Nintendo DS ARM9 BIOS",
+        files: &[BiosFile { filename: "bios9.bin", md5: "a392174eb3e572fed6447e956bde4b25" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "dreamcast",
+        description: "Dreamcast boot ROM",
+        files: &[BiosFile { filename: "dc/dc_boot.bin", md5: "e10c53c2f8b90bab96ead2d368858623" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "dreamcast",
+        description: "Dreamcast flash ROM",
+        files: &[BiosFile { filename: "dc/dc_flash.bin", md5: "0a93f7940c455905bea6e392dfde92a4" }],
+        need: Need::Optional,
+        content: &[],
+    },
+    BiosRequirement {
+        platform: "atari7800",
+        description: "Atari 7800 BIOS",
+        files: &[BiosFile { filename: "7800 BIOS (U).rom", md5: "0763f1ffb006ddbe32e52d497ee848ae" }],
+        need: Need::Optional,
+        content: &[],
+    },
+];
+
+// RetroArch looks for BIOS files in "system" next to its executable in our portable install
+pub fn system_dir(retroarch_path: &Path) -> PathBuf {
+    retroarch_path.join("system")
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BiosFileStatus {
+    pub filename: String,
+    pub expected_md5: String,
+    pub present: bool,
+    // The file's MD5 matches the known-good dump
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BiosStatus {
+    pub platform: String,
+    pub description: String,
+    pub need: Need,
+    pub content: Vec<String>,
+    pub files: Vec<BiosFileStatus>,
+    // One of the files is present; verified when it also has a known-good MD5
+    pub present: bool,
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BiosImportSummary {
+    pub files_scanned: usize,
+    // Registry file names copied into the system directory
+    pub imported: Vec<String>,
+    pub already_present: usize,
+}
+
+fn md5_of(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Check every registry entry (or those of one platform) against the system directory
+pub fn verify(system_dir: &Path, platform: Option<&str>) -> Vec<BiosStatus> {
+    verify_with(REGISTRY, system_dir, platform)
+}
+
+fn verify_with(registry: &[BiosRequirement], system_dir: &Path, platform: Option<&str>) -> Vec<BiosStatus> {
+    registry.iter()
+        .filter(|req| platform.map(|p| p == req.platform).unwrap_or(true))
+        .map(|req| {
+            let files: Vec<BiosFileStatus> = req.files.iter()
+                .map(|file| {
+                    let path = system_dir.join(file.filename);
+                    let present = path.is_file();
+                    let verified = present && md5_of(&path).map(|md5| md5 == file.md5).unwrap_or(false);
+                    BiosFileStatus {
+                        filename: file.filename.to_string(),
+                        expected_md5: file.md5.to_string(),
+                        present,
+                        verified,
+                    }
+                })
+                .collect();
+            BiosStatus {
+                platform: req.platform.to_string(),
+                description: req.description.to_string(),
+                need: req.need,
+                content: req.content.iter().map(|c| c.to_string()).collect(),
+                present: files.iter().any(|f| f.present),
+                verified: files.iter().any(|f| f.verified),
+                files,
+            }
+        })
+        .collect()
+}
+
+// Copy every known BIOS found under `folder` into the system directory under the name
+// cores expect. Files are recognized by MD5, so their names don't matter.
+pub fn import_from_folder(folder: &Path, system_dir: &Path) -> std::io::Result<BiosImportSummary> {
+    import_with(REGISTRY, folder, system_dir)
+}
+
+fn import_with(registry: &[BiosRequirement], folder: &Path, system_dir: &Path) -> std::io::Result<BiosImportSummary> {
+    if !folder.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Not a directory: {}", folder.display()),
+        ));
+    }
+
+    let mut summary = BiosImportSummary::default();
+    for path in scanner::collect_files(folder) {
+        summary.files_scanned += 1;
+        let small = std::fs::metadata(&path).map(|m| m.len() <= MAX_BIOS_SIZE).unwrap_or(false);
+        if !small {
+            continue;
+        }
+        let md5 = match md5_of(&path) {
+            Ok(md5) => md5,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let known = registry.iter().flat_map(|req| req.files.iter()).find(|file| file.md5 == md5);
+        let Some(file) = known else { continue };
+
+        let target = system_dir.join(file.filename);
+        if target.is_file() && md5_of(&target).map(|existing| existing == md5).unwrap_or(false) {
+            summary.already_present += 1;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&path, &target)?;
+        summary.imported.push(file.filename.to_string());
+    }
+    Ok(summary)
+}
+
+// Requirements a launch can't do without: needed by the core for this game and with
+// none of their files in the system directory. A file with an unknown MD5 still counts,
+// as it may be a good dump we don't list.
+pub fn missing_for_launch(platform: &str, core_id: &str, rom_path: &Path, system_dir: &Path) -> Vec<&'static BiosRequirement> {
+    missing_with(REGISTRY, platform, core_id, rom_path, system_dir)
+}
+
+fn missing_with<'a>(
+    registry: &'a [BiosRequirement],
+    platform: &str,
+    core_id: &str,
+    rom_path: &Path,
+    system_dir: &Path,
+) -> Vec<&'a BiosRequirement> {
+    registry.iter()
+        .filter(|req| req.platform == platform && req.required_by(core_id) && req.applies_to(rom_path))
+        .filter(|req| !req.files.iter().any(|file| system_dir.join(file.filename).is_file()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // Stand-ins for real dumps: MD5s of b"bios a", b"bios b", b"system card" and b"boot rom"
+    const TEST_REGISTRY: &[BiosRequirement] = &[
+        BiosRequirement {
+            platform: "ps1",
+            description: "Test PlayStation BIOS",
+            files: &[
+                BiosFile { filename: "a.bin", md5: "6e246b9b7279a0fc791dca3ab6314433" },
+                BiosFile { filename: "b.bin", md5: "e99948f9bf78b26ebcc77375fd3ac8f8" },
+            ],
+            need: Need::Cores(&["beetle_psx", "swanstation"]),
+            content: &[],
+        },
+        BiosRequirement {
+            platform: "pce",
+            description: "Test System Card",
+            files: &[BiosFile { filename: "syscard.pce", md5: "633588ac5fce5e77cb8b1428d61ea6f1" }],
+            need: Need::Always,
+            content: &["cue"],
+        },
+        BiosRequirement {
+            platform: "dreamcast",
+            description: "Test boot ROM",
+            files: &[BiosFile { filename: "dc/dc_boot.bin", md5: "da53edcded78d057253fff5a31d89761" }],
+            need: Need::Optional,
+            content: &[],
+        },
+    ];
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-bios-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn status<'a>(statuses: &'a [BiosStatus], platform: &str) -> &'a BiosStatus {
+        statuses.iter().find(|s| s.platform == platform).unwrap()
+    }
+
+    #[test]
+    fn registry_md5s_are_well_formed() {
+        for file in REGISTRY.iter().flat_map(|req| req.files.iter()) {
+            assert_eq!(file.md5.len(), 32, "{}", file.filename);
+            assert!(file.md5.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)), "{}", file.filename);
+        }
+    }
+
+    #[test]
+    fn verifies_present_files_by_md5() {
+        let system = test_dir("verify");
+
+        let statuses = verify_with(TEST_REGISTRY, &system, None);
+        assert_eq!(statuses.len(), 3);
+        let ps1 = status(&statuses, "ps1");
+        assert!(!ps1.present && !ps1.verified);
+        assert!(ps1.files.iter().all(|f| !f.present));
+
+        // A file under a known name but with the wrong contents is present, not verified
+        fs::write(system.join("a.bin"), b"bad dump").unwrap();
+        let statuses = verify_with(TEST_REGISTRY, &system, Some("ps1"));
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].present && !statuses[0].verified);
+        assert!(statuses[0].files[0].present && !statuses[0].files[0].verified);
+
+        // Any one good file verifies the requirement
+        fs::write(system.join("b.bin"), b"bios b").unwrap();
+        let ps1 = &verify_with(TEST_REGISTRY, &system, Some("ps1"))[0];
+        assert!(ps1.present && ps1.verified);
+        assert!(!ps1.files[0].verified && ps1.files[1].verified);
+    }
+
+    #[test]
+    fn imports_known_dumps_by_md5_whatever_their_name() {
+        let dir = test_dir("import");
+        let folder = dir.join("downloads");
+        let system = dir.join("system");
+        fs::create_dir_all(folder.join("nested")).unwrap();
+        fs::write(folder.join("PSX BIOS (USA).bin"), b"bios a").unwrap();
+        fs::write(folder.join("nested").join("boot.bin"), b"boot rom").unwrap();
+        fs::write(folder.join("readme.txt"), b"not a bios").unwrap();
+
+        let summary = import_with(TEST_REGISTRY, &folder, &system).unwrap();
+
+        assert_eq!(summary.files_scanned, 3);
+        let mut imported = summary.imported.clone();
+        imported.sort();
+        assert_eq!(imported, ["a.bin", "dc/dc_boot.bin"]);
+        assert_eq!(fs::read(system.join("a.bin")).unwrap(), b"bios a");
+        assert_eq!(fs::read(system.join("dc").join("dc_boot.bin")).unwrap(), b"boot rom");
+        assert!(!system.join("readme.txt").exists());
+
+        // Good copies already in place are left alone; bad ones are replaced
+        fs::write(system.join("a.bin"), b"bad dump").unwrap();
+        let again = import_with(TEST_REGISTRY, &folder, &system).unwrap();
+        assert_eq!(again.imported, ["a.bin"]);
+        assert_eq!(again.already_present, 1);
+        assert_eq!(fs::read(system.join("a.bin")).unwrap(), b"bios a");
+
+        assert!(import_with(TEST_REGISTRY, &dir.join("missing"), &system).is_err());
+    }
+
+    #[test]
+    fn reports_bios_missing_for_the_core_and_content() {
+        let system = test_dir("missing");
+        let rom = Path::new("/roms/game.cue");
+        let descriptions = |missing: Vec<&BiosRequirement>| missing.iter().map(|r| r.description).collect::<Vec<_>>();
+
+        // Need::Cores only applies to cores whose id contains one of the names
+        assert_eq!(descriptions(missing_with(TEST_REGISTRY, "ps1", "swanstation", rom, &system)), ["Test PlayStation BIOS"]);
+        assert_eq!(descriptions(missing_with(TEST_REGISTRY, "ps1", "mednafen_beetle_psx_hw", rom, &system)), ["Test PlayStation BIOS"]);
+        assert!(missing_with(TEST_REGISTRY, "ps1", "pcsx_rearmed", rom, &system).is_empty());
+
+        // Content limits which games need it; optional files never block a launch
+        assert_eq!(descriptions(missing_with(TEST_REGISTRY, "pce", "mednafen_pce", rom, &system)), ["Test System Card"]);
+        assert!(missing_with(TEST_REGISTRY, "pce", "mednafen_pce", Path::new("/roms/game.pce"), &system).is_empty());
+        assert!(missing_with(TEST_REGISTRY, "dreamcast", "flycast", rom, &system).is_empty());
+
+        // Any file counts, even one whose MD5 we don't know
+        fs::write(system.join("b.bin"), b"other region").unwrap();
+        assert!(missing_with(TEST_REGISTRY, "ps1", "swanstation", rom, &system).is_empty());
+    }
+}
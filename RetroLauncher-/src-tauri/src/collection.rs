@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::FromRow;
+
+// A user collection. Manual collections list their games explicitly; smart ones
+// (rules set) hold every game matching the saved filter.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    // The rules as stored, parsed into `rules` by `with_parsed_rules`
+    #[serde(skip)]
+    #[sqlx(rename = "rules")]
+    pub rules_json: Option<String>,
+    #[sqlx(skip)]
+    pub rules: Option<SmartRules>,
+    pub game_count: i64,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl Collection {
+    pub fn with_parsed_rules(mut self) -> Self {
+        self.rules = self.rules_json.as_deref().and_then(|json| serde_json::from_str(json).ok());
+        self
+    }
+
+    pub fn is_smart(&self) -> bool {
+        self.rules_json.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    pub game_count: i64,
+}
+
+// Saved filter of a smart collection, e.g.
+// {"match": "all", "rules": [{"field": "platform", "op": "eq", "value": "snes"},
+//                            {"field": "playtime", "op": "lt", "value": "1h"}]}
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SmartRules {
+    #[serde(default, rename = "match")]
+    pub match_mode: MatchMode,
+    #[serde(default)]
+    pub rules: Vec<SmartRule>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartRule {
+    pub field: RuleField,
+    pub op: RuleOp,
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    Title,
+    Platform,
+    Genre,
+    Developer,
+    Region,
+    ReleaseYear,
+    // Total playtime; values are seconds or durations like "1h", "90m", "1h30m"
+    Playtime,
+    PlayCount,
+    // Compared as "YYYY-MM-DD[ HH:MM:SS]" text
+    LastPlayed,
+    Favorite,
+    Verified,
+    Tag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOp {
+    Eq,
+    Ne,
+    Contains,
+    NotContains,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+// A rule value normalized for binding into a query
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleValue {
+    Int(i64),
+    Text(String),
+}
+
+impl RuleField {
+    fn is_numeric(self) -> bool {
+        matches!(self, RuleField::ReleaseYear | RuleField::Playtime | RuleField::PlayCount)
+    }
+
+    fn is_flag(self) -> bool {
+        matches!(self, RuleField::Favorite | RuleField::Verified)
+    }
+}
+
+impl RuleOp {
+    fn is_ordering(self) -> bool {
+        matches!(self, RuleOp::Lt | RuleOp::Lte | RuleOp::Gt | RuleOp::Gte)
+    }
+
+    fn is_text_match(self) -> bool {
+        matches!(self, RuleOp::Contains | RuleOp::NotContains)
+    }
+}
+
+impl SmartRules {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            rule.value()?;
+        }
+        Ok(())
+    }
+}
+
+impl SmartRule {
+    // Check the operator suits the field and normalize the value
+    pub fn value(&self) -> Result<RuleValue, String> {
+        let field = self.field;
+        let op = self.op;
+
+        if (field.is_numeric() || field.is_flag()) && op.is_text_match() {
+            return Err(format!("{:?} can't be matched with {:?}", field, op));
+        }
+        if field.is_flag() && op.is_ordering() {
+            return Err(format!("{:?} can only be compared with eq or ne", field));
+        }
+        if field == RuleField::Tag && op.is_ordering() {
+            return Err("Tags can only be matched with eq, ne, contains or not_contains".to_string());
+        }
+
+        let invalid = || format!("Invalid value for {:?}: {}", field, self.value);
+
+        match field {
+            RuleField::Favorite | RuleField::Verified => match &self.value {
+                JsonValue::Bool(flag) => Ok(RuleValue::Int(*flag as i64)),
+                JsonValue::Number(n) => n.as_i64().map(|n| RuleValue::Int((n != 0) as i64)).ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            RuleField::Playtime => match &self.value {
+                JsonValue::Number(n) => n.as_i64().map(RuleValue::Int).ok_or_else(invalid),
+                JsonValue::String(s) => parse_duration_secs(s).map(RuleValue::Int).ok_or_else(invalid),
+                _ => Err(invalid()),
+            },
+            RuleField::ReleaseYear | RuleField::PlayCount => match &self.value {
+                JsonValue::Number(n) => n.as_i64().map(RuleValue::Int).ok_or_else(invalid),
+                JsonValue::String(s) => s.trim().parse().map(RuleValue::Int).map_err(|_| invalid()),
+                _ => Err(invalid()),
+            },
+            _ => match &self.value {
+                JsonValue::String(s) if !s.trim().is_empty() => Ok(RuleValue::Text(s.trim().to_string())),
+                JsonValue::Number(n) => Ok(RuleValue::Text(n.to_string())),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+// "90" -> 90, "45s" -> 45, "30m" -> 1800, "1h30m" -> 5400, "2d" -> 172800
+pub fn parse_duration_secs(value: &str) -> Option<i64> {
+    let value = value.trim().to_lowercase();
+    if let Ok(secs) = value.parse::<i64>() {
+        return Some(secs);
+    }
+
+    let mut total = 0i64;
+    let mut number = String::new();
+    for c in value.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let secs = number.parse::<i64>().ok()?.checked_mul(unit)?;
+        total = total.checked_add(secs)?;
+        number.clear();
+    }
+
+    if number.is_empty() && !value.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(field: RuleField, op: RuleOp, value: JsonValue) -> SmartRule {
+        SmartRule { field, op, value }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration_secs("90"), Some(90));
+        assert_eq!(parse_duration_secs("45s"), Some(45));
+        assert_eq!(parse_duration_secs("30m"), Some(1_800));
+        assert_eq!(parse_duration_secs("1h30m"), Some(5_400));
+        assert_eq!(parse_duration_secs(" 1H 30M "), Some(5_400));
+        assert_eq!(parse_duration_secs("2d"), Some(172_800));
+        assert_eq!(parse_duration_secs("9223372036854775807s"), Some(i64::MAX));
+
+        assert_eq!(parse_duration_secs(""), None);
+        assert_eq!(parse_duration_secs("h"), None);
+        assert_eq!(parse_duration_secs("1h30"), None);
+        assert_eq!(parse_duration_secs("1w"), None);
+        assert_eq!(parse_duration_secs("-1h"), None);
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert_eq!(parse_duration_secs("999999999999999d"), None);
+        assert_eq!(parse_duration_secs("99999999999999999999s"), None);
+        assert_eq!(parse_duration_secs("9223372036854775807s1s"), None);
+    }
+
+    #[test]
+    fn normalizes_rule_values() {
+        let value = |field, op, value| rule(field, op, value).value();
+
+        assert_eq!(value(RuleField::Playtime, RuleOp::Lt, json!("1h")), Ok(RuleValue::Int(3_600)));
+        assert_eq!(value(RuleField::Playtime, RuleOp::Gte, json!(90)), Ok(RuleValue::Int(90)));
+        assert_eq!(value(RuleField::ReleaseYear, RuleOp::Gte, json!(" 1994 ")), Ok(RuleValue::Int(1_994)));
+        assert_eq!(value(RuleField::PlayCount, RuleOp::Eq, json!(0)), Ok(RuleValue::Int(0)));
+        assert_eq!(value(RuleField::Favorite, RuleOp::Eq, json!(true)), Ok(RuleValue::Int(1)));
+        assert_eq!(value(RuleField::Verified, RuleOp::Ne, json!(5)), Ok(RuleValue::Int(1)));
+        assert_eq!(value(RuleField::Verified, RuleOp::Eq, json!(0)), Ok(RuleValue::Int(0)));
+        assert_eq!(value(RuleField::Title, RuleOp::Contains, json!("  Mario ")), Ok(RuleValue::Text("Mario".to_string())));
+        assert_eq!(value(RuleField::Platform, RuleOp::Eq, json!(32)), Ok(RuleValue::Text("32".to_string())));
+        assert_eq!(value(RuleField::LastPlayed, RuleOp::Gt, json!("2024-01-01")), Ok(RuleValue::Text("2024-01-01".to_string())));
+    }
+
+    #[test]
+    fn rejects_invalid_rule_values() {
+        let invalid = |field, op, value| rule(field, op, value).value().is_err();
+
+        assert!(invalid(RuleField::Playtime, RuleOp::Lt, json!("soon")));
+        assert!(invalid(RuleField::Playtime, RuleOp::Lt, json!("999999999999999d")));
+        assert!(invalid(RuleField::Playtime, RuleOp::Lt, json!(1.5)));
+        assert!(invalid(RuleField::ReleaseYear, RuleOp::Eq, json!("nineties")));
+        assert!(invalid(RuleField::Favorite, RuleOp::Eq, json!("yes")));
+        assert!(invalid(RuleField::Title, RuleOp::Eq, json!("   ")));
+        assert!(invalid(RuleField::Genre, RuleOp::Eq, json!(null)));
+        assert!(invalid(RuleField::Tag, RuleOp::Eq, json!(["a", "b"])));
+    }
+
+    #[test]
+    fn rejects_operators_that_do_not_suit_the_field() {
+        let invalid = |field, op, value| rule(field, op, value).value().is_err();
+
+        assert!(invalid(RuleField::Playtime, RuleOp::Contains, json!("1h")));
+        assert!(invalid(RuleField::ReleaseYear, RuleOp::NotContains, json!(1994)));
+        assert!(invalid(RuleField::Favorite, RuleOp::Contains, json!(true)));
+        assert!(invalid(RuleField::Favorite, RuleOp::Lt, json!(true)));
+        assert!(invalid(RuleField::Tag, RuleOp::Gt, json!("rpg")));
+
+        assert!(!invalid(RuleField::Tag, RuleOp::NotContains, json!("rpg")));
+        assert!(!invalid(RuleField::Title, RuleOp::Lt, json!("M")));
+    }
+
+    #[test]
+    fn reads_saved_rules() {
+        let rules: SmartRules = serde_json::from_value(json!({
+            "rules": [
+                {"field": "platform", "op": "eq", "value": "snes"},
+                {"field": "play_count", "op": "gt", "value": "abc"}
+            ]
+        })).unwrap();
+
+        assert_eq!(rules.match_mode, MatchMode::All);
+        assert_eq!(rules.rules.len(), 2);
+        assert!(rules.validate().is_err());
+        assert!(serde_json::from_value::<SmartRules>(json!({"rules": [{"field": "size", "op": "eq", "value": 1}]})).is_err());
+
+        let collection = Collection {
+            id: 1,
+            name: "Broken".to_string(),
+            description: None,
+            rules_json: Some("{not json".to_string()),
+            rules: None,
+            game_count: 0,
+            created_at: None,
+            updated_at: None,
+        }.with_parsed_rules();
+        assert!(collection.is_smart());
+        assert!(collection.rules.is_none());
+    }
+}
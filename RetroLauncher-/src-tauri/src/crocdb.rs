@@ -1,11 +1,69 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 use tauri::Manager;
 
-const CROCDB_API_BASE: &str = "https://api.crocdb.net";
+use crate::archive::{self, ArchiveFormat, ExtractOptions};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::downloader::{self, DownloadOptions, ProgressEvent};
+use crate::multidisc;
+use crate::romid;
+use crate::scanner;
+
+const DEFAULT_API_BASE: &str = "https://api.crocdb.net";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_USER_AGENT: &str = concat!("RetroLauncher/", env!("CARGO_PKG_VERSION"));
+
+// ============================================================================
+// Client
+// ============================================================================
+
+/// HTTP client for the CrocDB API. `from_settings` reads the "crocdb_api_base",
+/// "crocdb_timeout_secs" and "crocdb_user_agent" settings; missing ones use the public API.
+#[derive(Debug, Clone)]
+pub struct CrocDbClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl CrocDbClient {
+    pub fn new(base_url: &str, timeout: Duration, user_agent: &str) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(user_agent)
+            .build()?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+        })
+    }
+
+    pub async fn from_settings(db: &Database) -> Result<Self, AppError> {
+        let text_setting = |value: Option<serde_json::Value>| {
+            value
+                .and_then(|v| v.as_str().map(|s| s.trim().to_string()))
+                .filter(|s| !s.is_empty())
+        };
+        let base_url = text_setting(db.get_setting("crocdb_api_base").await?)
+            .unwrap_or_else(|| DEFAULT_API_BASE.to_string());
+        let user_agent = text_setting(db.get_setting("crocdb_user_agent").await?)
+            .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+        let timeout = db.get_setting("crocdb_timeout_secs").await?
+            .and_then(|v| v.as_u64())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        Ok(Self::new(&base_url, Duration::from_secs(timeout), &user_agent)?)
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}{}", self.base_url, endpoint)
+    }
+}
 
 // ============================================================================
 // API Response Structures
@@ -71,6 +129,32 @@ pub struct DownloadLink {
     pub host: String,
 }
 
+// Picks one of an entry's download links. Set one field: `index` is the position in
+// `download_links`, `filename` and `format` are matched case-insensitively.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkSelector {
+    #[serde(default)]
+    pub index: Option<usize>,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+// How links are ranked when no selector is given, or when a selector matches several
+// links. Stored as the "link_preferences" setting. Earlier entries of `preferred_hosts`
+// and `preferred_formats` win; formats are compared against the link's format field and
+// filename extension, e.g. ["chd", "bin/cue", "iso"].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkPreferences {
+    #[serde(default)]
+    pub preferred_hosts: Vec<String>,
+    #[serde(default)]
+    pub preferred_formats: Vec<String>,
+    #[serde(default)]
+    pub prefer_smallest: bool,
+}
+
 // ============================================================================
 // Platform & Region Structures
 // ============================================================================
@@ -146,11 +230,9 @@ pub struct DatabaseInfo {
 // ============================================================================
 
 /// Search for games in CrocDB
-pub async fn search_games(query: &str) -> Result<Vec<CrocDBGame>, Box<dyn std::error::Error>> {
+pub async fn search_games(client: &CrocDbClient, query: &str) -> Result<Vec<CrocDBGame>, AppError> {
     println!("🔍 CrocDB: Searching for '{}'", query);
 
-    let client = reqwest::Client::new();
-
     let search_request = SearchRequest {
         search_key: Some(query.to_string()),
         platforms: None,
@@ -160,10 +242,10 @@ pub async fn search_games(query: &str) -> Result<Vec<CrocDBGame>, Box<dyn std::e
         page: Some(1),
     };
 
-    println!("📤 CrocDB: Sending request to {}/search", CROCDB_API_BASE);
+    println!("📤 CrocDB: Sending request to {}", client.url("/search"));
 
-    let response = client
-        .post(format!("{}/search", CROCDB_API_BASE))
+    let response = client.http
+        .post(client.url("/search"))
         .json(&search_request)
         .send()
         .await?;
@@ -172,9 +254,10 @@ pub async fn search_games(query: &str) -> Result<Vec<CrocDBGame>, Box<dyn std::e
     println!("📥 CrocDB: Response status: {}", status);
 
     if !status.is_success() {
+        let error = AppError::from_response(&response);
         let error_text = response.text().await.unwrap_or_default();
         eprintln!("❌ CrocDB: API error: {} - {}", status, error_text);
-        return Err(format!("API error: {}", status).into());
+        return Err(error);
     }
 
     let response_text = response.text().await?;
@@ -203,16 +286,16 @@ pub async fn search_games(query: &str) -> Result<Vec<CrocDBGame>, Box<dyn std::e
     Ok(games)
 }
 
-/// Search with advanced filters
+/// Search with advanced filters (cached)
 pub async fn search_games_advanced(
+    client: &CrocDbClient,
+    db: &Database,
     search_key: Option<String>,
     platforms: Option<Vec<String>>,
     regions: Option<Vec<String>>,
     max_results: Option<i32>,
     page: Option<i32>,
-) -> Result<SearchResultsData, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
+) -> Result<SearchResultsData, AppError> {
     let search_request = SearchRequest {
         search_key,
         platforms,
@@ -222,18 +305,9 @@ pub async fn search_games_advanced(
         page,
     };
 
-    let response = client
-        .post(format!("{}/search", CROCDB_API_BASE))
-        .json(&search_request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
-    }
-
     // Deserialize to raw structure (CrocDBEntry with "links")
-    let api_response: ApiResponse<SearchResultsDataRaw> = response.json().await?;
+    let api_response: ApiResponse<SearchResultsDataRaw> =
+        cached_request(client, db, "/search", Some(serde_json::to_value(&search_request)?)).await?;
 
     // Convert CrocDBEntry to CrocDBGame (links → download_links)
     let converted_results: Vec<CrocDBGame> = api_response.data.results
@@ -251,88 +325,50 @@ pub async fn search_games_advanced(
     })
 }
 
-/// Get a specific game entry by slug
-pub async fn get_entry(slug: &str) -> Result<CrocDBGame, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
+/// Get a specific game entry by slug (cached)
+pub async fn get_entry(client: &CrocDbClient, db: &Database, slug: &str) -> Result<CrocDBGame, AppError> {
     let request_body = serde_json::json!({ "slug": slug });
 
-    let response = client
-        .post(format!("{}/entry", CROCDB_API_BASE))
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
-    }
-
-    let api_response: ApiResponse<EntryData> = response.json().await?;
+    let api_response: ApiResponse<EntryData> = cached_request(client, db, "/entry", Some(request_body)).await?;
     Ok(entry_to_game(api_response.data.entry))
 }
 
 /// Get a random game entry
-pub async fn get_random_entry() -> Result<CrocDBGame, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(format!("{}/entry/random", CROCDB_API_BASE))
+pub async fn get_random_entry(client: &CrocDbClient) -> Result<CrocDBGame, AppError> {
+    let response = client.http
+        .get(client.url("/entry/random"))
         .send()
         .await?;
 
     if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
+        return Err(AppError::from_response(&response));
     }
 
     let api_response: ApiResponse<EntryData> = response.json().await?;
     Ok(entry_to_game(api_response.data.entry))
 }
 
-/// Get all available platforms
-pub async fn get_platforms() -> Result<HashMap<String, PlatformInfo>, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(format!("{}/platforms", CROCDB_API_BASE))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
-    }
-
-    let api_response: ApiResponse<PlatformsData> = response.json().await?;
+/// Get all available platforms (cached)
+pub async fn get_platforms(client: &CrocDbClient, db: &Database) -> Result<HashMap<String, PlatformInfo>, AppError> {
+    let api_response: ApiResponse<PlatformsData> = cached_request(client, db, "/platforms", None).await?;
     Ok(api_response.data.platforms)
 }
 
-/// Get all available regions
-pub async fn get_regions() -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(format!("{}/regions", CROCDB_API_BASE))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
-    }
-
-    let api_response: ApiResponse<RegionsData> = response.json().await?;
+/// Get all available regions (cached)
+pub async fn get_regions(client: &CrocDbClient, db: &Database) -> Result<HashMap<String, String>, AppError> {
+    let api_response: ApiResponse<RegionsData> = cached_request(client, db, "/regions", None).await?;
     Ok(api_response.data.regions)
 }
 
 /// Get database information
-pub async fn get_database_info() -> Result<DatabaseInfo, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(format!("{}/info", CROCDB_API_BASE))
+pub async fn get_database_info(client: &CrocDbClient) -> Result<DatabaseInfo, AppError> {
+    let response = client.http
+        .get(client.url("/info"))
         .send()
         .await?;
 
     if !response.status().is_success() {
-        return Err(format!("API error: {}", response.status()).into());
+        return Err(AppError::from_response(&response));
     }
 
     let api_response: ApiResponse<DatabaseInfo> = response.json().await?;
@@ -340,107 +376,155 @@ pub async fn get_database_info() -> Result<DatabaseInfo, Box<dyn std::error::Err
 }
 
 // ============================================================================
-// Download Functions
+// Response Cache
 // ============================================================================
 
-/// Stream download with progress events (bytes, total, speed)
-async fn stream_download_with_progress(
-    url: &str,
-    destination: &str,
-    slug: &str,
-    stage: &str,
-    app_handle: Option<&tauri::AppHandle>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    use futures_util::StreamExt;
-    use std::time::{Instant, Duration};
+/// A CrocDB response stored in the database, with the validators the API sent
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CachedResponse {
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix timestamp of the last time the API confirmed the body
+    pub fetched_at: i64,
+}
 
-    let client = reqwest::Client::new();
-    let mut response = client.get(url).send().await?;
-    if !response.status().is_success() {
-        return Err(format!("Download failed: {}", response.status()).into());
+/// How long a cached response is used without asking the API. Platforms and regions
+/// rarely change; search results do as the database grows.
+fn cache_ttl(endpoint: &str) -> i64 {
+    match endpoint {
+        "/platforms" | "/regions" => 7 * 24 * 60 * 60,
+        "/entry" => 24 * 60 * 60,
+        _ => 15 * 60,
+    }
+}
+
+/// Most responses an endpoint keeps. Every search body is cached under its own key, so
+/// searches are capped to keep the cache from growing with each query typed.
+fn cache_limit(endpoint: &str) -> Option<i64> {
+    match endpoint {
+        "/search" => Some(200),
+        _ => None,
     }
+}
 
-    // Ensure parent directory exists
-    if let Some(parent) = Path::new(destination).parent() {
-        std::fs::create_dir_all(parent)?;
+/// Keyed by full URL so switching API servers doesn't mix their responses. POST
+/// endpoints are keyed by their request body too.
+fn cache_key(url: &str, body: Option<&serde_json::Value>) -> String {
+    match body {
+        Some(body) => format!("POST {} {}", url, body),
+        None => format!("GET {}", url),
     }
+}
 
-    let total = response
-        .content_length()
-        .unwrap_or(0);
-    let mut file = File::create(destination)?;
-    let mut downloaded: u64 = 0;
-    let mut last_emit = Instant::now();
-    let mut window_downloaded: u64 = 0;
-
-    let mut stream = response.bytes_stream();
-    let start = Instant::now();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        file.write_all(&chunk)?;
-        downloaded += chunk.len() as u64;
-        window_downloaded += chunk.len() as u64;
-
-        // Emit every ~120ms to avoid flooding
-        if last_emit.elapsed() >= Duration::from_millis(120) {
-            let elapsed = start.elapsed().as_secs_f64().max(0.001);
-            let speed_bps = (downloaded as f64 / elapsed) as f64;
-            let pct = if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 };
-            if let Some(handle) = app_handle {
-                let _ = handle.emit_all("download-progress", serde_json::json!({
-                    "slug": slug,
-                    "stage": stage,
-                    "progress": pct,
-                    "message": format!("Downloading..."),
-                    "bytesReceived": downloaded,
-                    "totalBytes": total,
-                    "speedBps": speed_bps,
-                }));
+/// Fetch an endpoint through the response cache. Fresh entries are served without a
+/// request; expired ones are revalidated with If-None-Match / If-Modified-Since. When the
+/// API can't be reached or fails, the last stored response is served however old it is.
+async fn cached_request<T: DeserializeOwned>(
+    client: &CrocDbClient,
+    db: &Database,
+    endpoint: &str,
+    body: Option<serde_json::Value>,
+) -> Result<T, AppError> {
+    let url = client.url(endpoint);
+    let key = cache_key(&url, body.as_ref());
+    let now = chrono::Utc::now().timestamp();
+
+    // Rows that no longer parse (e.g. after an API change) are refetched
+    let cached = db.get_cached_response(&key).await?
+        .and_then(|c| serde_json::from_str::<T>(&c.body).ok().map(|parsed| (c, parsed)));
+
+    let mut cached = match cached {
+        Some((entry, parsed)) if now - entry.fetched_at < cache_ttl(endpoint) => return Ok(parsed),
+        other => other,
+    };
+
+    let mut request = match &body {
+        Some(body) => client.http.post(&url).json(body),
+        None => client.http.get(&url),
+    };
+    if let Some((entry, _)) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => match cached.take() {
+            Some((_, parsed)) => {
+                eprintln!("CrocDB unreachable ({}), serving cached {}", e, endpoint);
+                return Ok(parsed);
             }
-            window_downloaded = 0;
-            last_emit = Instant::now();
+            None => return Err(e.into()),
+        },
+    };
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some((_, parsed)) = cached.take() {
+            db.touch_cached_response(&key, now).await?;
+            return Ok(parsed);
+        }
+    }
+    if status.is_server_error() {
+        if let Some((_, parsed)) = cached.take() {
+            eprintln!("CrocDB API error {}, serving cached {}", status, endpoint);
+            return Ok(parsed);
         }
     }
+    if !status.is_success() {
+        return Err(AppError::from_response(&response));
+    }
 
-    // Final emit 100%
-    if let Some(handle) = app_handle {
-        let _ = handle.emit_all("download-progress", serde_json::json!({
-            "slug": slug,
-            "stage": stage,
-            "progress": 100.0,
-            "message": "Download complete",
-            "bytesReceived": downloaded,
-            "totalBytes": total,
-            "speedBps": 0.0,
-        }));
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+    };
+    let etag = header(reqwest::header::ETAG);
+    let last_modified = header(reqwest::header::LAST_MODIFIED);
+    let text = response.text().await?;
+
+    // Only bodies that parse are stored
+    let parsed = serde_json::from_str::<T>(&text)?;
+    db.put_cached_response(&key, endpoint, &CachedResponse {
+        body: text,
+        etag,
+        last_modified,
+        fetched_at: now,
+    }).await?;
+    if let Some(limit) = cache_limit(endpoint) {
+        db.prune_cached_responses(endpoint, limit).await?;
     }
 
-    Ok(destination.to_string())
+    Ok(parsed)
 }
 
-/// Download a game from CrocDB by slug (downloads the first available link)
+// ============================================================================
+// Download Functions
+// ============================================================================
+
+/// Download a game from CrocDB by slug (the selected link, or the best ranked one)
 pub async fn download_game(
+    client: &CrocDbClient,
+    db: &Database,
     slug: &str,
     destination_dir: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let game = get_entry(slug).await?;
-
-    if game.download_links.is_empty() {
-        return Err("No download links available for this game".into());
-    }
-
-    // Use the first download link
-    let link = &game.download_links[0];
-    let destination_path = Path::new(destination_dir)
-        .join(&game.platform)
-        .join(sanitize_filename(&game.title));
+    selector: Option<&LinkSelector>,
+    prefs: &LinkPreferences,
+) -> Result<String, AppError> {
+    let game = get_entry(client, db, slug).await?;
+    let link = select_link(&game.download_links, selector, prefs)?;
+    let destination_path = game_install_dir(Path::new(destination_dir), &game.platform, &game.title);
     let destination = destination_path.to_string_lossy().to_string();
 
-    stream_download_with_progress(
+    downloader::download_file(
         &link.url,
-        &destination,
-        slug,
-        "downloading",
+        &destination_path,
+        &DownloadOptions::with_expected_size(link.size),
+        &download_progress(slug, "downloading", "Downloading..."),
         None,
     ).await?;
 
@@ -451,6 +535,72 @@ pub async fn download_game(
 // Helper Functions
 // ============================================================================
 
+/// Choose the link to download: the selector narrows the candidates, the preferences
+/// order them. Without preferences the first matching link wins, as before.
+pub fn select_link<'a>(
+    links: &'a [DownloadLink],
+    selector: Option<&LinkSelector>,
+    prefs: &LinkPreferences,
+) -> Result<&'a DownloadLink, AppError> {
+    if links.is_empty() {
+        return Err(AppError::NotFound("No download links available for this game".to_string()));
+    }
+
+    let mut candidates: Vec<(usize, &DownloadLink)> = links.iter().enumerate().collect();
+    if let Some(selector) = selector {
+        if let Some(index) = selector.index {
+            let link = links.get(index).ok_or_else(|| AppError::NotFound(format!(
+                "Download link {} does not exist ({} available)", index, links.len()
+            )))?;
+            return Ok(link);
+        }
+        if let Some(filename) = &selector.filename {
+            candidates.retain(|(_, link)| link.filename.eq_ignore_ascii_case(filename));
+            if candidates.is_empty() {
+                return Err(AppError::NotFound(format!("No download link named '{}'", filename)));
+            }
+        }
+        if let Some(format) = &selector.format {
+            candidates.retain(|(_, link)| format_rank(link, std::slice::from_ref(format)).is_some());
+            if candidates.is_empty() {
+                return Err(AppError::NotFound(format!("No download link in format '{}'", format)));
+            }
+        }
+    }
+
+    let host_rank = |link: &DownloadLink| {
+        prefs.preferred_hosts.iter()
+            .position(|host| link.host.eq_ignore_ascii_case(host))
+            .unwrap_or(usize::MAX)
+    };
+    candidates.sort_by_key(|(index, link)| (
+        host_rank(link),
+        format_rank(link, &prefs.preferred_formats).unwrap_or(usize::MAX),
+        if prefs.prefer_smallest && link.size > 0 { link.size } else { i64::MAX },
+        *index,
+    ));
+
+    Ok(candidates[0].1)
+}
+
+// Position of the first preferred format the link is in. "bin/cue" style entries match
+// any of their parts.
+fn format_rank(link: &DownloadLink, formats: &[String]) -> Option<usize> {
+    let parts = |s: &str| -> Vec<String> {
+        s.split(['/', ',', '+', ' '])
+            .map(|part| part.trim().trim_start_matches('.').to_lowercase())
+            .filter(|part| !part.is_empty())
+            .collect()
+    };
+    let mut link_formats = parts(&link.format);
+    if let Some(ext) = Path::new(&link.filename).extension() {
+        link_formats.push(ext.to_string_lossy().to_lowercase());
+    }
+    formats.iter().position(|format| {
+        parts(format).iter().any(|part| link_formats.contains(part))
+    })
+}
+
 /// Convert CrocDBEntry to CrocDBGame
 fn entry_to_game(entry: CrocDBEntry) -> CrocDBGame {
     let download_links: Vec<DownloadLink> = entry.links
@@ -479,37 +629,6 @@ fn entry_to_game(entry: CrocDBEntry) -> CrocDBGame {
     }
 }
 
-/// Helper to extract ROM from compressed archives (ZIP)
-pub async fn extract_rom(
-    archive_path: &str,
-    destination: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    use std::fs::File;
-    use std::io::BufReader;
-    use zip::ZipArchive;
-
-    let file = File::open(archive_path)?;
-    let reader = BufReader::new(file);
-    let mut archive = ZipArchive::new(reader)?;
-
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let outpath = Path::new(destination).join(file.name());
-
-        if file.name().ends_with('/') {
-            std::fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                std::fs::create_dir_all(p)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            std::io::copy(&mut file, &mut outfile)?;
-        }
-    }
-
-    Ok(destination.to_string())
-}
-
 /// Install a game completely: download, extract, and prepare for library
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallResult {
@@ -520,12 +639,19 @@ pub struct InstallResult {
     pub slug: String,
 }
 
+// Setting `cancel` stops an extraction already running on the blocking pool, which
+// aborting the future can't reach
+#[allow(clippy::too_many_arguments)]
 pub async fn install_game_complete(
+    client: &CrocDbClient,
+    db: &Database,
     slug: &str,
-    _emulator_id: &str,
     install_dir: &str,
+    selector: Option<&LinkSelector>,
+    prefs: &LinkPreferences,
+    cancel: Option<Arc<AtomicBool>>,
     app_handle: Option<&tauri::AppHandle>,
-) -> Result<InstallResult, Box<dyn std::error::Error>> {
+) -> Result<InstallResult, AppError> {
     // Emit progress event helper
     let emit_progress = |stage: &str, progress: f32, message: &str| {
         if let Some(handle) = app_handle {
@@ -541,89 +667,71 @@ pub async fn install_game_complete(
     emit_progress("starting", 0.0, "Starting download...");
 
     // 1. Get game entry
-    let game = get_entry(slug).await?;
-
-    if game.download_links.is_empty() {
-        return Err("No download links available".into());
-    }
+    let game = get_entry(client, db, slug).await?;
+    let link = select_link(&game.download_links, selector, prefs)?;
 
     emit_progress("preparing", 5.0, "Preparing installation...");
 
     // 2. Create installation directory
-    let game_dir = Path::new(install_dir)
-        .join(&game.platform)
-        .join(sanitize_filename(&game.title));
+    let game_dir = game_install_dir(Path::new(install_dir), &game.platform, &game.title);
     std::fs::create_dir_all(&game_dir)?;
 
     emit_progress("downloading", 10.0, "Downloading game...");
 
-    // 3. Download the game (use first link)
-    let link = &game.download_links[0];
-    let download_path = game_dir.join(&link.filename);
-
-    stream_download_with_progress(
-        &link.url,
-        &download_path.to_string_lossy().to_string(),
-        slug,
-        "downloading",
-        app_handle,
-    ).await?;
+    // 3. Download the game. Entries listing one link per disc get every disc of the set.
+    let links = multidisc::disc_links(&game.download_links, link);
+    let mut downloads = Vec::new();
+    for (index, link) in links.iter().enumerate() {
+        let message = if links.len() > 1 {
+            format!("Downloading disc {} of {}...", index + 1, links.len())
+        } else {
+            "Downloading...".to_string()
+        };
+        let download_path = game_dir.join(&link.filename);
+        downloader::download_file(
+            &link.url,
+            &download_path,
+            &DownloadOptions::with_expected_size(link.size),
+            &download_progress(slug, "downloading", &message),
+            app_handle,
+        ).await?;
+        downloads.push(download_path);
+    }
 
     emit_progress("extracting", 50.0, "Extracting files...");
 
-    // 4. Extract if it's a ZIP file
-    let rom_path = if link.filename.ends_with(".zip") {
-        let extract_dir = game_dir.join("extracted");
-        extract_rom(
-            &download_path.to_string_lossy().to_string(),
-            &extract_dir.to_string_lossy().to_string()
-        ).await?;
-
-        emit_progress("finding_rom", 65.0, "Finding ROM file...");
-
-        // Find the ROM file (common extensions)
-        let rom_extensions = vec!["nes", "snes", "sfc", "n64", "z64", "gba", "gb", "gbc",
-                                   "nds", "iso", "cue", "bin", "md", "gen", "smd", "gg",
-                                   "sms", "pce", "ngp", "ngc", "ws", "wsc"];
-
-        let mut found_rom = None;
-        for entry in std::fs::read_dir(&extract_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some(ext) = path.extension() {
-                if rom_extensions.contains(&ext.to_string_lossy().to_lowercase().as_str()) {
-                    found_rom = Some(path);
-                    break;
-                }
-            }
+    // 4. Extract archives (zip, 7z, rar, tar.*), including archives inside them
+    let extract_dir = game_dir.join("extracted");
+    let mut files = Vec::new();
+    for download_path in downloads {
+        if ArchiveFormat::detect(&download_path).is_none() {
+            files.push(download_path);
+            continue;
         }
+        let dest = extract_dir.clone();
+        let event = download_progress(slug, "extracting", "Extracting files...");
+        let handle = app_handle.cloned();
+        let cancel = cancel.clone();
+        let extracted = tokio::task::spawn_blocking(move || {
+            let options = ExtractOptions { nested_depth: 2, cancel, ..Default::default() };
+            archive::extract_archive(&download_path, &dest, &options, &event, handle.as_ref())
+        }).await??;
+        files.extend(extracted);
+    }
 
-        emit_progress("rom_found", 70.0, "ROM file found!");
+    emit_progress("finding_rom", 65.0, "Finding ROM file...");
 
-        // If we found a ROM, use it; otherwise use the first extracted file
-        if let Some(rom) = found_rom {
-            rom.to_string_lossy().to_string()
-        } else {
-            // Fallback: use the first non-directory file
-            let mut first_file = None;
-            for entry in std::fs::read_dir(&extract_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_file() {
-                    first_file = Some(path);
-                    break;
-                }
-            }
-            first_file
-                .ok_or("No ROM file found in archive")?
-                .to_string_lossy()
-                .to_string()
+    // Multi-disc games get an .m3u playlist so RetroArch can swap discs
+    let rom_path = match multidisc::disc_images(&files) {
+        Some(discs) => {
+            println!("Found {} discs, writing playlist", discs.len());
+            multidisc::write_m3u(&discs)?
         }
-    } else {
-        emit_progress("preparing_rom", 60.0, "Preparing ROM file...");
-        // Not a ZIP, use the downloaded file directly
-        download_path.to_string_lossy().to_string()
+        None => find_rom(&files).ok_or_else(|| AppError::Install("No ROM file found in archive".to_string()))?,
     };
+    let rom_path = rom_path.to_string_lossy().to_string();
+
+    emit_progress("rom_found", 70.0, "ROM file found!");
 
     println!("✅ ROM prepared: {}", rom_path);
     emit_progress("downloading_cover", 75.0, "Downloading cover art...");
@@ -633,11 +741,11 @@ pub async fn install_game_complete(
         let cover_filename = format!("{}.png", sanitize_filename(&game.title));
         let cover_dest = game_dir.join(&cover_filename);
 
-        match stream_download_with_progress(
+        match downloader::download_file(
             boxart_url,
-            &cover_dest.to_string_lossy().to_string(),
-            slug,
-            "downloading_cover",
+            &cover_dest,
+            &DownloadOptions::default(),
+            &download_progress(slug, "downloading_cover", "Downloading cover art..."),
             app_handle,
         ).await {
             Ok(_) => Some(cover_dest.to_string_lossy().to_string()),
@@ -658,6 +766,36 @@ pub async fn install_game_complete(
     })
 }
 
+/// Pick the file to launch: the one identified with the most confidence, sheets and
+/// playlists over the tracks they list. The largest file is the last resort.
+fn find_rom(files: &[PathBuf]) -> Option<PathBuf> {
+    let referenced = scanner::referenced_by_sheets(files);
+    files.iter()
+        .filter(|path| !referenced.contains(*path))
+        .filter_map(|path| romid::identify(path).map(|identity| (identity.confidence, path)))
+        // max_by_key keeps the last of equals; reverse so the first file wins ties
+        .rev()
+        .max_by_key(|(confidence, _)| *confidence)
+        .map(|(_, path)| path)
+        .or_else(|| files.iter().max_by_key(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)))
+        .cloned()
+}
+
+/// Directory a game is downloaded and extracted into
+pub fn game_install_dir(install_dir: &Path, platform: &str, title: &str) -> PathBuf {
+    install_dir.join(platform).join(sanitize_filename(title))
+}
+
+/// Progress event fields for a CrocDB download
+fn download_progress(slug: &str, stage: &str, message: &str) -> ProgressEvent {
+    ProgressEvent {
+        slug: slug.to_string(),
+        stage: stage.to_string(),
+        message: message.to_string(),
+        title: None,
+    }
+}
+
 /// Sanitize filename for filesystem
 fn sanitize_filename(name: &str) -> String {
     name.chars()
@@ -667,3 +805,153 @@ fn sanitize_filename(name: &str) -> String {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{rom_zip, start_crocdb_stub, StubServer, PLATFORMS_ETAG};
+    use std::sync::atomic::Ordering;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-crocdb-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn test_db(dir: &Path) -> Database {
+        Database::new(&dir.join("retrolauncher.db")).await.unwrap()
+    }
+
+    fn test_client(server: &StubServer) -> CrocDbClient {
+        CrocDbClient::new(&server.base_url, Duration::from_secs(5), "RetroLauncher-Test").unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_is_configured_from_settings() {
+        let server = start_crocdb_stub(Vec::new());
+        let db = test_db(&test_dir("settings")).await;
+        db.update_settings(serde_json::json!({
+            "crocdb_api_base": format!("{}/", server.base_url),
+            "crocdb_user_agent": "RetroLauncher-Settings",
+            "crocdb_timeout_secs": 5,
+        })).await.unwrap();
+
+        let client = CrocDbClient::from_settings(&db).await.unwrap();
+        let platforms = get_platforms(&client, &db).await.unwrap();
+
+        assert_eq!(platforms["gba"].name, "Game Boy Advance");
+        let requests = server.requests_to("/platforms");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].headers["user-agent"], "RetroLauncher-Settings");
+    }
+
+    #[tokio::test]
+    async fn search_converts_links_and_caches_per_request() {
+        let server = start_crocdb_stub(rom_zip());
+        let db = test_db(&test_dir("search")).await;
+        let client = test_client(&server);
+
+        let search = |page| search_games_advanced(&client, &db, Some("Stub Game".to_string()), Some(vec!["gba".to_string()]), None, Some(20), Some(page));
+        let first = search(1).await.unwrap();
+        search(1).await.unwrap();
+        let second_page = search(2).await.unwrap();
+
+        assert_eq!(first.results.len(), 1);
+        assert_eq!(first.results[0].slug, "stub-game");
+        assert_eq!(first.results[0].download_links[0].filename, "stub-game.zip");
+        assert_eq!(second_page.current_page, 2);
+        let requests = server.requests_to("/search");
+        assert_eq!(requests.len(), 2);
+        let sent: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(sent["platforms"], serde_json::json!(["gba"]));
+        assert!(sent.get("regions").is_none());
+    }
+
+    #[tokio::test]
+    async fn entry_is_fetched_by_slug() {
+        let server = start_crocdb_stub(rom_zip());
+        let db = test_db(&test_dir("entry")).await;
+        let client = test_client(&server);
+
+        let game = get_entry(&client, &db, "stub-game").await.unwrap();
+
+        assert_eq!(game.title, "Stub Game");
+        assert_eq!(game.platform, "gba");
+        assert_eq!(game.download_links.len(), 1);
+        let requests = server.requests_to("/entry");
+        assert_eq!(requests[0].headers["user-agent"], "RetroLauncher-Test");
+        assert_eq!(requests[0].body, r#"{"slug":"stub-game"}"#);
+    }
+
+    #[tokio::test]
+    async fn expired_responses_are_revalidated_with_etag() {
+        let server = start_crocdb_stub(Vec::new());
+        let db = test_db(&test_dir("revalidate")).await;
+        let client = test_client(&server);
+
+        get_platforms(&client, &db).await.unwrap();
+        db.touch_cached_response(&cache_key(&client.url("/platforms"), None), 0).await.unwrap();
+        let platforms = get_platforms(&client, &db).await.unwrap();
+
+        assert_eq!(platforms["gba"].brand, "Nintendo");
+        let requests = server.requests_to("/platforms");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].headers["if-none-match"], PLATFORMS_ETAG);
+
+        // The 304 renewed the entry, so the next call stays local
+        get_platforms(&client, &db).await.unwrap();
+        assert_eq!(server.requests_to("/platforms").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_responses_are_served_while_the_api_is_down() {
+        let server = start_crocdb_stub(Vec::new());
+        let db = test_db(&test_dir("offline")).await;
+        let client = test_client(&server);
+
+        get_regions(&client, &db).await.unwrap();
+        db.touch_cached_response(&cache_key(&client.url("/regions"), None), 0).await.unwrap();
+        server.down.store(true, Ordering::SeqCst);
+
+        let regions = get_regions(&client, &db).await.unwrap();
+        assert_eq!(regions["eu"], "Europe");
+        assert_eq!(server.requests_to("/regions").len(), 2);
+
+        // Nothing cached to fall back on
+        assert!(get_entry(&client, &db, "stub-game").await.is_err());
+
+        // Unreachable server, same base URL
+        let unreachable = CrocDbClient::new("http://127.0.0.1:1", Duration::from_secs(5), "RetroLauncher-Test").unwrap();
+        assert!(get_regions(&unreachable, &db).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn install_downloads_and_extracts_the_rom() {
+        let archive = rom_zip();
+        let server = start_crocdb_stub(archive);
+        let dir = test_dir("install");
+        let db = test_db(&dir).await;
+        let client = test_client(&server);
+        let install_dir = dir.join("games");
+
+        let result = install_game_complete(
+            &client,
+            &db,
+            "stub-game",
+            &install_dir.to_string_lossy(),
+            None,
+            &LinkPreferences::default(),
+            None,
+            None,
+        ).await.unwrap();
+
+        let rom_path = PathBuf::from(&result.rom_path);
+        assert_eq!(rom_path.file_name().unwrap(), "Stub Game.gba");
+        assert!(rom_path.starts_with(game_install_dir(&install_dir, "gba", "Stub Game")));
+        assert_eq!(std::fs::metadata(&rom_path).unwrap().len(), 4096);
+        // The cover URL 404s, which doesn't fail the install
+        assert!(result.cover_path.is_none());
+        assert_eq!(server.requests_to("/files/stub-game.zip").len(), 1);
+    }
+}
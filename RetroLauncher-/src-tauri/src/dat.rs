@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use md5::{Digest, Md5};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::database::Database;
+use crate::romid::{self, BigEndianReader, N64ByteOrder};
+use crate::scanner;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatHeader {
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+}
+
+// One <rom> of a Logiqx <game>/<machine> entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatEntry {
+    pub game_name: String,
+    pub rom_name: String,
+    pub size: Option<i64>,
+    pub crc32: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub region: Option<String>,
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DatFile {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub entry_count: i64,
+    pub imported_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RomHashes {
+    pub size: i64,
+    pub crc32: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DatMatch {
+    pub dat_name: String,
+    pub game_name: String,
+    pub rom_name: String,
+    pub region: Option<String>,
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub game_id: String,
+    pub verified: bool,
+    pub hashes: RomHashes,
+    pub matched: Option<DatMatch>,
+}
+
+// Parse a Logiqx XML datafile (the format used by No-Intro and Redump)
+pub fn parse_logiqx(xml: &str) -> Result<(DatHeader, Vec<DatEntry>), Box<dyn std::error::Error>> {
+    let doc = roxmltree::Document::parse_with_options(xml, roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    })?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "datafile" {
+        return Err("Not a Logiqx DAT file (missing <datafile> root)".into());
+    }
+
+    let child_text = |node: roxmltree::Node, tag: &str| {
+        node.children()
+            .find(|c| c.has_tag_name(tag))
+            .and_then(|c| c.text())
+            .map(|t| t.trim().to_string())
+    };
+
+    let mut header = DatHeader::default();
+    if let Some(node) = root.children().find(|c| c.has_tag_name("header")) {
+        header.name = child_text(node, "name").unwrap_or_default();
+        header.description = child_text(node, "description");
+        header.version = child_text(node, "version");
+    }
+
+    let mut entries = Vec::new();
+    for game in root.children().filter(|c| c.has_tag_name("game") || c.has_tag_name("machine")) {
+        let game_name = match game.attribute("name") {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let region = parse_region(&game_name);
+        let revision = parse_revision(&game_name);
+
+        for rom in game.children().filter(|c| c.has_tag_name("rom")) {
+            // Listed for completeness, no dump exists
+            if rom.attribute("status") == Some("nodump") {
+                continue;
+            }
+            let hash = |attr: &str| rom.attribute(attr).map(|h| h.to_lowercase());
+            entries.push(DatEntry {
+                game_name: game_name.clone(),
+                rom_name: rom.attribute("name").unwrap_or_default().to_string(),
+                size: rom.attribute("size").and_then(|s| s.parse().ok()),
+                crc32: hash("crc"),
+                md5: hash("md5"),
+                sha1: hash("sha1"),
+                region: region.clone(),
+                revision: revision.clone(),
+            });
+        }
+    }
+
+    if header.name.is_empty() {
+        header.name = header.description.clone().unwrap_or_else(|| "Unnamed DAT".to_string());
+    }
+
+    Ok((header, entries))
+}
+
+// Parenthesised groups of a No-Intro / Redump name, e.g. "Game (USA, Europe) (Rev 1)"
+fn name_tags(name: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut rest = name;
+    while let Some(start) = rest.find('(') {
+        match rest[start..].find(')') {
+            Some(end) => {
+                tags.push(&rest[start + 1..start + end]);
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    tags
+}
+
+const REGIONS: &[&str] = &[
+    "World", "USA", "Europe", "Japan", "Asia", "Australia", "Brazil", "Canada", "China",
+    "France", "Germany", "Hong Kong", "Italy", "Korea", "Netherlands", "Russia", "Spain",
+    "Sweden", "Taiwan", "UK", "Scandinavia", "Latin America",
+];
+
+fn parse_region(name: &str) -> Option<String> {
+    name_tags(name).into_iter()
+        .find(|tag| tag.split(", ").all(|part| REGIONS.contains(&part)))
+        .map(|tag| tag.to_string())
+}
+
+fn parse_revision(name: &str) -> Option<String> {
+    name_tags(name).into_iter()
+        .find(|tag| {
+            tag.starts_with("Rev ")
+                || (tag.starts_with('v') && tag[1..].starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .map(|tag| tag.to_string())
+}
+
+// CRC32/MD5/SHA1 of everything the reader yields
+fn hash_reader<R: Read>(mut reader: R) -> std::io::Result<RomHashes> {
+    let mut crc = crc32fast::Hasher::new();
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut size = 0i64;
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        crc.update(&buf[..n]);
+        md5.update(&buf[..n]);
+        sha1.update(&buf[..n]);
+        size += n as i64;
+    }
+    Ok(RomHashes {
+        size,
+        crc32: format!("{:08x}", crc.finalize()),
+        md5: format!("{:x}", md5.finalize()),
+        sha1: format!("{:x}", sha1.finalize()),
+    })
+}
+
+fn hash_file_from(path: &Path, skip: u64) -> std::io::Result<RomHashes> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(skip))?;
+    hash_reader(file)
+}
+
+// Bytes prepended by dumpers that No-Intro DATs don't include (iNES header, SNES copier header)
+pub fn header_skip(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    if &magic == b"NES\x1a" {
+        return Some(16);
+    }
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    if (ext == "sfc" || ext == "smc") && size % 1024 == 512 {
+        return Some(512);
+    }
+    None
+}
+
+// The file DATs describe for a library entry: the first track of a .cue, the first
+// disc of an .m3u playlist, or the ROM itself. Sheets and playlists that lead nowhere
+// (or back to themselves) are hashed as they are.
+fn hash_target(rom_path: &Path) -> PathBuf {
+    scanner::first_image(rom_path).unwrap_or_else(|| rom_path.to_path_buf())
+}
+
+// Hash a ROM the way DATs list it. Returns the raw-file hashes, followed by
+// headerless hashes when the file carries a dumper header. Zips are hashed by their
+// largest entry. N64 ROMs dumped byte-swapped or little-endian are hashed as .z64
+// first, the order DATs use, so that is the hash recorded even without a match.
+pub fn hash_rom(rom_path: &Path) -> std::io::Result<Vec<RomHashes>> {
+    let target = hash_target(rom_path);
+
+    let is_zip = target.extension().map(|e| e.eq_ignore_ascii_case("zip")).unwrap_or(false);
+    if is_zip {
+        let mut archive = zip::ZipArchive::new(File::open(&target)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let largest = (0..archive.len())
+            .filter_map(|i| archive.by_index(i).ok().map(|f| (i, f.size())))
+            .max_by_key(|(_, size)| *size)
+            .map(|(i, _)| i);
+        if let Some(index) = largest {
+            let mut word = Vec::with_capacity(4);
+            zip_entry(&mut archive, index)?.take(4).read_to_end(&mut word)?;
+            let mut hashes = Vec::new();
+            if let Some(order) = romid::n64_byte_order(&word).filter(|o| *o != N64ByteOrder::BigEndian) {
+                hashes.push(hash_reader(BigEndianReader::new(zip_entry(&mut archive, index)?, order))?);
+            }
+            hashes.push(hash_reader(zip_entry(&mut archive, index)?)?);
+            return Ok(hashes);
+        }
+    }
+
+    let mut hashes = Vec::new();
+    if let Some(order) = romid::n64_file_byte_order(&target).filter(|o| *o != N64ByteOrder::BigEndian) {
+        hashes.push(hash_reader(BigEndianReader::new(File::open(&target)?, order))?);
+    }
+    hashes.push(hash_file_from(&target, 0)?);
+    if let Some(skip) = header_skip(&target) {
+        hashes.push(hash_file_from(&target, skip)?);
+    }
+    Ok(hashes)
+}
+
+fn zip_entry<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, index: usize) -> std::io::Result<zip::read::ZipFile<'_>> {
+    archive.by_index(index).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+// Import a DAT file into the local match table. Re-importing a DAT with the same
+// name replaces its previous entries.
+pub async fn import_dat_file(db: &Database, path: &Path) -> Result<DatFile, Box<dyn std::error::Error>> {
+    // Redump DATs run to tens of megabytes; read and parse them off the async runtime
+    let dat_path = path.to_path_buf();
+    let (header, entries) = tokio::task::spawn_blocking(move || -> Result<_, String> {
+        let xml = std::fs::read_to_string(&dat_path).map_err(|e| e.to_string())?;
+        parse_logiqx(&xml).map_err(|e| e.to_string())
+    }).await??;
+    if entries.is_empty() {
+        return Err(format!("No ROM entries found in {}", path.display()).into());
+    }
+    let dat = db.import_dat(&header, &entries).await?;
+    Ok(dat)
+}
+
+// Hash a library game, look it up in the imported DATs and store the result.
+// A match replaces the title with the canonical one and records region and revision.
+pub async fn verify_game(db: &Database, game_id: &str) -> Result<Verification, Box<dyn std::error::Error>> {
+    let game = db.get_game(game_id).await?;
+    let rom_path = PathBuf::from(&game.rom_path);
+
+    // Hashing multi-gigabyte disc images must not stall the async runtime
+    let candidates = tokio::task::spawn_blocking(move || hash_rom(&rom_path)).await??;
+
+    let mut matched = None;
+    for hashes in &candidates {
+        if let Some(m) = db.find_dat_match(hashes).await? {
+            matched = Some((hashes.clone(), m));
+            break;
+        }
+    }
+
+    let verification = match matched {
+        Some((hashes, m)) => Verification {
+            game_id: game.id.clone(),
+            verified: true,
+            hashes,
+            matched: Some(m),
+        },
+        None => Verification {
+            game_id: game.id.clone(),
+            verified: false,
+            hashes: candidates.into_iter().next().unwrap_or_default(),
+            matched: None,
+        },
+    };
+
+    // Patched copies keep the title naming their patch
+    let canonical_title = verification.matched.as_ref()
+        .filter(|_| game.base_game_id.is_none())
+        .map(|m| scanner::clean_title(&m.game_name));
+    db.set_game_verification(&verification, canonical_title.as_deref()).await?;
+
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-dat-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn payload() -> Vec<u8> {
+        (0..2048u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn parses_logiqx_games_and_machines() {
+        let xml = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/dtds/datafile.dtd">
+<datafile>
+    <header>
+        <description>Nintendo - Super Nintendo Entertainment System</description>
+        <version>20240101-000000</version>
+    </header>
+    <game name="Chrono Trigger (USA)">
+        <rom name="Chrono Trigger (USA).sfc" size="4194304" crc="2D206BF7" md5="A2BC447961E52FD2227BAED164F729DC"/>
+    </game>
+    <game name="Lost Game (Japan) (Rev 1)">
+        <rom name="Lost Game (Japan) (Rev 1).sfc" status="nodump"/>
+    </game>
+    <machine name="Arcade Game (World) (v1.1)">
+        <rom name="a.bin" size="1024" crc="00000001"/>
+        <rom name="b.bin" size="2048" sha1="ABCDEF"/>
+    </machine>
+    <game>
+        <rom name="nameless.bin" crc="00000002"/>
+    </game>
+</datafile>"#;
+        let (header, entries) = parse_logiqx(xml).unwrap();
+
+        // Without a <name>, the description names the DAT
+        assert_eq!(header.name, "Nintendo - Super Nintendo Entertainment System");
+        assert_eq!(header.version.as_deref(), Some("20240101-000000"));
+
+        let roms: Vec<&str> = entries.iter().map(|e| e.rom_name.as_str()).collect();
+        assert_eq!(roms, ["Chrono Trigger (USA).sfc", "a.bin", "b.bin"]);
+        assert_eq!(entries[0].size, Some(4194304));
+        assert_eq!(entries[0].crc32.as_deref(), Some("2d206bf7"));
+        assert_eq!(entries[0].md5.as_deref(), Some("a2bc447961e52fd2227baed164f729dc"));
+        assert_eq!(entries[0].region.as_deref(), Some("USA"));
+        assert_eq!(entries[2].game_name, "Arcade Game (World) (v1.1)");
+        assert_eq!(entries[2].sha1.as_deref(), Some("abcdef"));
+        assert_eq!(entries[2].crc32, None);
+        assert_eq!(entries[2].revision.as_deref(), Some("v1.1"));
+
+        assert!(parse_logiqx("<softwarelist/>").is_err());
+        let (header, _) = parse_logiqx("<datafile><header/></datafile>").unwrap();
+        assert_eq!(header.name, "Unnamed DAT");
+    }
+
+    #[tokio::test]
+    async fn imports_dat_files_from_disk() {
+        let dir = test_dir("import");
+        let db = Database::new(&dir.join("retrolauncher.db")).await.unwrap();
+        let path = dir.join("snes.dat");
+        std::fs::write(&path, r#"<datafile><header><name>SNES</name></header>
+            <game name="Game (USA)"><rom name="Game (USA).sfc" size="4" crc="0000000A"/></game>
+        </datafile>"#).unwrap();
+        let dat = import_dat_file(&db, &path).await.unwrap();
+        assert_eq!((dat.name.as_str(), dat.entry_count), ("SNES", 1));
+
+        std::fs::write(&path, "<datafile><header><name>Empty</name></header></datafile>").unwrap();
+        assert!(import_dat_file(&db, &path).await.is_err());
+        assert!(import_dat_file(&db, &dir.join("missing.dat")).await.is_err());
+    }
+
+    #[test]
+    fn parses_regions_and_revisions_from_names() {
+        assert_eq!(parse_region("Game (USA, Europe) (Rev 1)").as_deref(), Some("USA, Europe"));
+        assert_eq!(parse_region("Game (En,Fr,De) (Japan)").as_deref(), Some("Japan"));
+        assert_eq!(parse_region("Game (Hong Kong)").as_deref(), Some("Hong Kong"));
+        assert_eq!(parse_region("Game (Proto) (Beta)"), None);
+        assert_eq!(parse_region("Game (USA, Proto)"), None);
+        assert_eq!(parse_region("Game (USA"), None);
+
+        assert_eq!(parse_revision("Game (USA) (Rev 2)").as_deref(), Some("Rev 2"));
+        assert_eq!(parse_revision("Game (Europe) (v1.02)").as_deref(), Some("v1.02"));
+        assert_eq!(parse_revision("Game (Europe) (Virtual Console)"), None);
+        assert_eq!(parse_revision("Game (USA)"), None);
+    }
+
+    #[test]
+    fn hashes_without_dumper_headers() {
+        let dir = test_dir("headers");
+        let bare = hash_reader(&payload()[..]).unwrap();
+        assert_eq!(hash_reader(&b"123456789"[..]).unwrap().crc32, "cbf43926");
+
+        let mut nes = b"NES\x1a".to_vec();
+        nes.extend_from_slice(&[0u8; 12]);
+        nes.extend(payload());
+        std::fs::write(dir.join("game.nes"), &nes).unwrap();
+        let hashes = hash_rom(&dir.join("game.nes")).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[0].size, nes.len() as i64);
+        assert_eq!(hashes[1].crc32, bare.crc32);
+        assert_eq!(hashes[1].sha1, bare.sha1);
+
+        let mut snes = vec![0u8; 512];
+        snes.extend(payload());
+        std::fs::write(dir.join("game.smc"), &snes).unwrap();
+        let hashes = hash_rom(&dir.join("game.smc")).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[1].md5, bare.md5);
+
+        // Only SNES extensions get the copier header check
+        std::fs::write(dir.join("game.bin"), &snes).unwrap();
+        assert_eq!(hash_rom(&dir.join("game.bin")).unwrap().len(), 1);
+
+        std::fs::write(dir.join("clean.sfc"), payload()).unwrap();
+        let hashes = hash_rom(&dir.join("clean.sfc")).unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes[0].crc32, bare.crc32);
+    }
+}
@@ -1,10 +1,16 @@
-use sqlx::{SqlitePool, sqlite::{SqlitePoolOptions, SqliteConnectOptions}};
+use sqlx::{Sqlite, SqlitePool, query::QueryAs, sqlite::{SqliteArguments, SqlitePoolOptions, SqliteConnectOptions}};
 use std::path::Path;
 use serde_json::Value as JsonValue;
 
-use crate::game::{Game, GameStats};
+use crate::collection::{Collection, MatchMode, RuleField, RuleOp, RuleValue, SmartRules, Tag};
+use crate::game::{Game, GamePlaytime, GameQuery, GameStats, LibraryPage, LibraryPlatform, LibrarySearch, LibrarySort, NewPlaySession, PeriodPlaytime, PlatformPlaytime, PlaySession};
 use crate::emulator::EmulatorConfig;
+use crate::crocdb::{CachedResponse, LinkPreferences};
+use crate::dat::{DatEntry, DatFile, DatHeader, DatMatch, RomHashes, Verification};
+use crate::download_queue::DownloadItem;
+use crate::migrations;
 
+#[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
 }
@@ -14,8 +20,7 @@ impl Database {
         // Ensure the parent directory exists with proper error handling
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent).map_err(|e| {
-                sqlx::Error::Io(std::io::Error::new(
-                    std::io::ErrorKind::Other,
+                sqlx::Error::Io(std::io::Error::other(
                     format!("Failed to create database directory: {}", e)
                 ))
             })?;
@@ -26,77 +31,21 @@ impl Database {
             .filename(db_path)
             .create_if_missing(true);
 
+        // Create or upgrade tables through a single connection, closed before the app's
+        // pool opens: sqlx can read rows with stale column lists on connections that were
+        // already open when ALTER TABLE ran on another one
+        let migration_pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(connect_options.clone())
+            .await?;
+        migrations::run(&migration_pool, db_path).await?;
+        migration_pool.close().await;
+
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
             .connect_with(connect_options)
             .await?;
 
-        // Create tables
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS games (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                platform TEXT NOT NULL,
-                rom_path TEXT NOT NULL,
-                cover_path TEXT,
-                emulator_id TEXT NOT NULL,
-                description TEXT,
-                release_year INTEGER,
-                genre TEXT,
-                developer TEXT,
-                is_favorite INTEGER DEFAULT 0,
-                play_count INTEGER DEFAULT 0,
-                total_playtime INTEGER DEFAULT 0,
-                last_played TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )
-            "#
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS emulators (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                platform TEXT NOT NULL,
-                executable_path TEXT NOT NULL,
-                arguments TEXT,
-                icon_path TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )
-            "#
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )
-            "#
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS screenshots (
-                id TEXT PRIMARY KEY,
-                game_id TEXT NOT NULL,
-                file_path TEXT NOT NULL,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (game_id) REFERENCES games(id)
-            )
-            "#
-        )
-        .execute(&pool)
-        .await?;
-
         Ok(Database { pool })
     }
 
@@ -105,8 +54,9 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO games (id, title, platform, rom_path, cover_path, emulator_id,
-                              description, release_year, genre, developer)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                              description, release_year, genre, developer, core_override,
+                              region, revision, base_game_id, soft_patch)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(&game.id)
@@ -119,6 +69,11 @@ impl Database {
         .bind(game.release_year)
         .bind(&game.genre)
         .bind(&game.developer)
+        .bind(&game.core_override)
+        .bind(&game.region)
+        .bind(&game.revision)
+        .bind(&game.base_game_id)
+        .bind(&game.soft_patch)
         .execute(&self.pool)
         .await?;
 
@@ -146,35 +101,76 @@ impl Database {
         Ok(games)
     }
 
+    pub async fn rom_path_exists(&self, rom_path: &str) -> Result<bool, sqlx::Error> {
+        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM games WHERE rom_path = ?")
+            .bind(rom_path)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.0 > 0)
+    }
+
     pub async fn delete_game(&self, id: &str) -> Result<(), sqlx::Error> {
+        // Soft-patched copies run off this game's ROM and go with it
+        let soft_patched: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM games WHERE base_game_id = ? AND soft_patch IS NOT NULL"
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        for (copy_id,) in soft_patched {
+            self.delete_game_entry(&copy_id).await?;
+        }
+        self.delete_game_entry(id).await?;
+
+        // Patched ROMs are standalone files and stay in the library
+        sqlx::query("UPDATE games SET base_game_id = NULL WHERE base_game_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_game_entry(&self, id: &str) -> Result<(), sqlx::Error> {
         // First, get the game to retrieve file paths
         let game = self.get_game(id).await?;
 
         // Delete the entire game directory (which contains .zip, extracted/, cover, etc.)
         // The ROM path is typically: .../games/PLATFORM/GAME_NAME/extracted/rom_file
         // We want to delete: .../games/PLATFORM/GAME_NAME/
-        let rom_path = std::path::Path::new(&game.rom_path);
+        // A soft-patched game owns only its patch; the ROM belongs to the base game.
+        let owned_file = game.soft_patch.as_ref().unwrap_or(&game.rom_path);
+        let rom_path = std::path::Path::new(owned_file);
 
         // Navigate up from ROM to find the game directory
-        // Structure: games/platform/game_name/extracted/rom_file
-        // We need to go up to game_name directory
-        if let Some(game_dir) = rom_path.parent()  // extracted/
-            .and_then(|p| p.parent())                // game_name/
-        {
+        // Structure: games/platform/game_name/[extracted/]rom_file
+        // Games imported from the user's own folders have no such directory and keep their files
+        let is_dir_named = |dir: Option<&std::path::Path>, name: &str| {
+            dir.and_then(|d| d.file_name()).map(|n| n == name).unwrap_or(false)
+        };
+        let install_dir = rom_path.ancestors().skip(1).find(|dir| {
+            let platform_dir = dir.parent();
+            is_dir_named(platform_dir, &game.platform)
+                && is_dir_named(platform_dir.and_then(|p| p.parent()), "games")
+        });
+
+        if let Some(game_dir) = install_dir.filter(|_| !game.id.starts_with("local_")) {
             if game_dir.exists() {
                 if let Err(e) = std::fs::remove_dir_all(game_dir) {
                     eprintln!("Warning: Failed to delete game directory {}: {}", game_dir.display(), e);
                 }
             }
-        } else {
+        } else if !game.id.starts_with("local_") {
             // Fallback: delete individual files if we can't find the game directory
-            if std::path::Path::new(&game.rom_path).exists() {
-                if let Err(e) = std::fs::remove_file(&game.rom_path) {
-                    eprintln!("Warning: Failed to delete ROM file {}: {}", game.rom_path, e);
+            if rom_path.exists() {
+                if let Err(e) = std::fs::remove_file(rom_path) {
+                    eprintln!("Warning: Failed to delete ROM file {}: {}", rom_path.display(), e);
                 }
             }
 
-            if let Some(cover_path) = &game.cover_path {
+            // Patched copies share the base game's cover
+            if let Some(cover_path) = game.cover_path.as_ref().filter(|_| game.base_game_id.is_none()) {
                 if std::path::Path::new(cover_path).exists() {
                     if let Err(e) = std::fs::remove_file(cover_path) {
                         eprintln!("Warning: Failed to delete cover file {}: {}", cover_path, e);
@@ -183,7 +179,22 @@ impl Database {
             }
         }
 
-        // Delete the database entry
+        // Delete the database entry (and its play history, collection entries and tags)
+        sqlx::query("DELETE FROM play_sessions WHERE game_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("DELETE FROM collection_games WHERE game_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("DELETE FROM game_tags WHERE game_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
         sqlx::query("DELETE FROM games WHERE id = ?")
             .bind(id)
             .execute(&self.pool)
@@ -208,6 +219,63 @@ impl Database {
         Ok(())
     }
 
+    // Record a finished play session and add its length (in seconds) to the game's total playtime
+    pub async fn record_play_session(&self, session: &NewPlaySession) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO play_sessions (game_id, started_at, ended_at, duration, core, exit_status, exit_code)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&session.game_id)
+        .bind(&session.started_at)
+        .bind(&session.ended_at)
+        .bind(session.duration)
+        .bind(&session.core)
+        .bind(&session.exit_status)
+        .bind(session.exit_code)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE games
+            SET total_playtime = COALESCE(total_playtime, 0) + ?
+            WHERE id = ?
+            "#
+        )
+        .bind(session.duration)
+        .bind(&session.game_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_core_override(&self, game_id: &str, core_id: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE games SET core_override = ? WHERE id = ?")
+            .bind(core_id)
+            .bind(game_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_game_emulator(&self, game_id: &str, emulator_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE games SET emulator_id = ? WHERE id = ?")
+            .bind(emulator_id)
+            .bind(game_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn toggle_favorite(&self, game_id: &str) -> Result<bool, sqlx::Error> {
         let result: (i64,) = sqlx::query_as(
             "SELECT is_favorite FROM games WHERE id = ?"
@@ -238,6 +306,441 @@ impl Database {
         Ok(stats)
     }
 
+    // Play history operations
+    // Date range bounds are optional and accept anything SQLite's datetime() understands
+    // (e.g. "2024-05-01" or "2024-05-01 18:30:00"); `to` is exclusive.
+    pub async fn get_play_sessions(
+        &self,
+        game_id: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<PlaySession>, sqlx::Error> {
+        let sessions = sqlx::query_as::<_, PlaySession>(
+            r#"
+            SELECT * FROM play_sessions
+            WHERE (? IS NULL OR game_id = ?)
+              AND (? IS NULL OR started_at >= datetime(?))
+              AND (? IS NULL OR started_at < datetime(?))
+            ORDER BY started_at DESC
+            LIMIT ?
+            "#
+        )
+        .bind(game_id).bind(game_id)
+        .bind(from).bind(from)
+        .bind(to).bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    // Playtime grouped by day ("2024-05-01"), month ("2024-05") or ISO-8601 week
+    // ("2024-W18"). ISO weeks run Monday to Sunday and belong to the year holding their
+    // Thursday, so 2024-12-30 falls in "2025-W01" and 2021-01-03 in "2020-W53".
+    pub async fn get_playtime_by_period(
+        &self,
+        period: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<PeriodPlaytime>, sqlx::Error> {
+        // The bundled SQLite predates strftime's %G/%V, so the ISO week is worked out from
+        // the Thursday of the session's week
+        let bucket = match period {
+            "week" => r#"printf('%s-W%02d',
+                              strftime('%Y', date(started_at, '-3 days', 'weekday 4')),
+                              (CAST(strftime('%j', date(started_at, '-3 days', 'weekday 4')) AS INTEGER) - 1) / 7 + 1)"#,
+            "month" => "strftime('%Y-%m', started_at)",
+            _ => "strftime('%Y-%m-%d', started_at)",
+        };
+
+        let rows = sqlx::query_as::<_, PeriodPlaytime>(&format!(
+            r#"
+            SELECT {} AS period,
+                   COALESCE(SUM(duration), 0) AS total_seconds,
+                   COUNT(*) AS sessions
+            FROM play_sessions
+            WHERE (? IS NULL OR started_at >= datetime(?))
+              AND (? IS NULL OR started_at < datetime(?))
+            GROUP BY period
+            ORDER BY period ASC
+            "#,
+            bucket
+        ))
+        .bind(from).bind(from)
+        .bind(to).bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn get_playtime_by_platform(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<PlatformPlaytime>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, PlatformPlaytime>(
+            r#"
+            SELECT g.platform AS platform,
+                   COALESCE(SUM(s.duration), 0) AS total_seconds,
+                   COUNT(*) AS sessions
+            FROM play_sessions s
+            JOIN games g ON g.id = s.game_id
+            WHERE (? IS NULL OR s.started_at >= datetime(?))
+              AND (? IS NULL OR s.started_at < datetime(?))
+            GROUP BY g.platform
+            ORDER BY total_seconds DESC
+            "#
+        )
+        .bind(from).bind(from)
+        .bind(to).bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn get_top_games(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<GamePlaytime>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, GamePlaytime>(
+            r#"
+            SELECT g.id AS game_id,
+                   g.title AS title,
+                   g.platform AS platform,
+                   COALESCE(SUM(s.duration), 0) AS total_seconds,
+                   COUNT(*) AS sessions
+            FROM play_sessions s
+            JOIN games g ON g.id = s.game_id
+            WHERE (? IS NULL OR s.started_at >= datetime(?))
+              AND (? IS NULL OR s.started_at < datetime(?))
+            GROUP BY g.id
+            ORDER BY total_seconds DESC
+            LIMIT ?
+            "#
+        )
+        .bind(from).bind(from)
+        .bind(to).bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // Collection operations
+    pub async fn get_collections(&self) -> Result<Vec<Collection>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, Collection>(
+            r#"
+            SELECT c.id, c.name, c.description, c.rules, c.created_at, c.updated_at,
+                   (SELECT COUNT(*) FROM collection_games cg WHERE cg.collection_id = c.id) AS game_count
+            FROM collections c
+            ORDER BY c.name COLLATE NOCASE ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut collections = Vec::with_capacity(rows.len());
+        for row in rows {
+            collections.push(self.finish_collection(row).await?);
+        }
+
+        Ok(collections)
+    }
+
+    pub async fn get_collection(&self, id: i64) -> Result<Collection, sqlx::Error> {
+        let row = sqlx::query_as::<_, Collection>(
+            r#"
+            SELECT c.id, c.name, c.description, c.rules, c.created_at, c.updated_at,
+                   (SELECT COUNT(*) FROM collection_games cg WHERE cg.collection_id = c.id) AS game_count
+            FROM collections c
+            WHERE c.id = ?
+            "#
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.finish_collection(row).await
+    }
+
+    // Smart collections are counted by running their rules
+    async fn finish_collection(&self, row: Collection) -> Result<Collection, sqlx::Error> {
+        let mut collection = row.with_parsed_rules();
+        if collection.is_smart() {
+            let (clause, values) = collection_clause(&collection);
+            let sql = format!("SELECT COUNT(*) FROM games WHERE {}", clause);
+            let row: (i64,) = bind_values(sqlx::query_as(&sql), values)
+                .fetch_one(&self.pool)
+                .await?;
+            collection.game_count = row.0;
+        }
+
+        Ok(collection)
+    }
+
+    // `rules` is the JSON of a smart collection's SmartRules, None for a manual collection
+    pub async fn create_collection(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        rules: Option<&str>,
+    ) -> Result<Collection, sqlx::Error> {
+        let id = sqlx::query("INSERT INTO collections (name, description, rules) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(description)
+            .bind(rules)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid();
+
+        self.get_collection(id).await
+    }
+
+    pub async fn update_collection(
+        &self,
+        id: i64,
+        name: &str,
+        description: Option<&str>,
+        rules: Option<&str>,
+    ) -> Result<Collection, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE collections
+            SET name = ?, description = ?, rules = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(name)
+        .bind(description)
+        .bind(rules)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        self.get_collection(id).await
+    }
+
+    pub async fn delete_collection(&self, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM collection_games WHERE collection_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM collections WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn add_game_to_collection(&self, collection_id: i64, game_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT OR IGNORE INTO collection_games (collection_id, game_id) VALUES (?, ?)")
+            .bind(collection_id)
+            .bind(game_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_game_from_collection(&self, collection_id: i64, game_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM collection_games WHERE collection_id = ? AND game_id = ?")
+            .bind(collection_id)
+            .bind(game_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_collection_games(&self, collection: &Collection) -> Result<Vec<Game>, sqlx::Error> {
+        if !collection.is_smart() {
+            let games = sqlx::query_as::<_, Game>(
+                r#"
+                SELECT g.* FROM games g
+                JOIN collection_games cg ON cg.game_id = g.id
+                WHERE cg.collection_id = ?
+                ORDER BY g.title ASC
+                "#
+            )
+            .bind(collection.id)
+            .fetch_all(&self.pool)
+            .await?;
+
+            return Ok(games);
+        }
+
+        let (clause, values) = collection_clause(collection);
+        let sql = format!("SELECT * FROM games WHERE {} ORDER BY title ASC", clause);
+        bind_values(sqlx::query_as::<_, Game>(&sql), values)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    // Library paging
+    pub async fn query_games(&self, query: &GameQuery) -> Result<LibraryPage, sqlx::Error> {
+        self.search_library(&query.into()).await
+    }
+
+    pub async fn get_library_platforms(&self) -> Result<Vec<LibraryPlatform>, sqlx::Error> {
+        let platforms = sqlx::query_as::<_, LibraryPlatform>(
+            r#"
+            SELECT platform, COUNT(*) AS game_count
+            FROM games
+            GROUP BY platform
+            ORDER BY platform COLLATE NOCASE ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(platforms)
+    }
+
+    pub async fn search_library(&self, search: &LibrarySearch) -> Result<LibraryPage, sqlx::Error> {
+        let (mut clause, mut values) = smart_rules_clause(&search.filter_rules());
+        let fts_query = search.text().and_then(fts_match_query);
+        let searching = fts_query.is_some();
+
+        let from = match fts_query {
+            Some(fts_query) => {
+                clause = format!("games_fts MATCH ? AND ({})", clause);
+                values.insert(0, RuleValue::Text(fts_query));
+                "FROM games JOIN games_fts ON games_fts.game_id = games.id"
+            }
+            None => "FROM games",
+        };
+
+        let direction = if search.descending() { "DESC" } else { "ASC" };
+        let order = match search.sort() {
+            // Weights follow the column order: game_id, title, description, developer, genre, tags
+            LibrarySort::Relevance if searching => "bm25(games_fts, 0.0, 10.0, 1.0, 2.0, 2.0, 5.0) ASC".to_string(),
+            LibrarySort::Relevance | LibrarySort::Title => format!("games.title COLLATE NOCASE {}", direction),
+            LibrarySort::Platform => format!("games.platform COLLATE NOCASE {}", direction),
+            LibrarySort::LastPlayed => format!("games.last_played {}", direction),
+            LibrarySort::Playtime => format!("games.total_playtime {}", direction),
+            LibrarySort::PlayCount => format!("games.play_count {}", direction),
+            LibrarySort::Added => format!("games.created_at {}", direction),
+        };
+
+        let count_sql = format!("SELECT COUNT(*) {} WHERE {}", from, clause);
+        let total: (i64,) = bind_values(sqlx::query_as(&count_sql), values.clone())
+            .fetch_one(&self.pool)
+            .await?;
+
+        let sql = format!(
+            "SELECT games.* {} WHERE {} ORDER BY {}, games.title COLLATE NOCASE ASC, games.id ASC LIMIT ? OFFSET ?",
+            from, clause, order
+        );
+        let games = bind_values(sqlx::query_as::<_, Game>(&sql), values)
+            .bind(search.limit())
+            .bind(search.offset())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(LibraryPage {
+            games,
+            total: total.0,
+            offset: search.offset(),
+            limit: search.limit(),
+        })
+    }
+
+    // Tag operations
+    pub async fn get_tags(&self) -> Result<Vec<Tag>, sqlx::Error> {
+        let tags = sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT t.id, t.name, t.color,
+                   (SELECT COUNT(*) FROM game_tags gt WHERE gt.tag_id = t.id) AS game_count
+            FROM tags t
+            ORDER BY t.name ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tags)
+    }
+
+    pub async fn get_game_tags(&self, game_id: &str) -> Result<Vec<Tag>, sqlx::Error> {
+        let tags = sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT t.id, t.name, t.color,
+                   (SELECT COUNT(*) FROM game_tags c WHERE c.tag_id = t.id) AS game_count
+            FROM tags t
+            JOIN game_tags gt ON gt.tag_id = t.id
+            WHERE gt.game_id = ?
+            ORDER BY t.name ASC
+            "#
+        )
+        .bind(game_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tags)
+    }
+
+    // Replace a game's tags; tags are matched by name ignoring case and created as needed
+    pub async fn set_game_tags(&self, game_id: &str, names: &[String]) -> Result<Vec<Tag>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM game_tags WHERE game_id = ?")
+            .bind(game_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT OR IGNORE INTO game_tags (game_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+                .bind(game_id)
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        self.get_game_tags(game_id).await
+    }
+
+    pub async fn update_tag(&self, tag_id: i64, name: &str, color: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE tags SET name = ?, color = ? WHERE id = ?")
+            .bind(name)
+            .bind(color)
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_tag(&self, tag_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM game_tags WHERE tag_id = ?")
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     // Emulator operations
     pub async fn add_emulator(&self, emulator: EmulatorConfig) -> Result<(), sqlx::Error> {
         sqlx::query(
@@ -279,6 +782,272 @@ impl Database {
         Ok(emulators)
     }
 
+    // DAT operations
+    pub async fn import_dat(&self, header: &DatHeader, entries: &[DatEntry]) -> Result<DatFile, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Replace an older import of the same DAT
+        sqlx::query("DELETE FROM dat_entries WHERE dat_id IN (SELECT id FROM dat_files WHERE name = ?)")
+            .bind(&header.name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM dat_files WHERE name = ?")
+            .bind(&header.name)
+            .execute(&mut *tx)
+            .await?;
+
+        let dat_id = sqlx::query("INSERT INTO dat_files (name, description, version) VALUES (?, ?, ?)")
+            .bind(&header.name)
+            .bind(&header.description)
+            .bind(&header.version)
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO dat_entries (dat_id, game_name, rom_name, size, crc32, md5, sha1, region, revision)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(dat_id)
+            .bind(&entry.game_name)
+            .bind(&entry.rom_name)
+            .bind(entry.size)
+            .bind(&entry.crc32)
+            .bind(&entry.md5)
+            .bind(&entry.sha1)
+            .bind(&entry.region)
+            .bind(&entry.revision)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        let dat = sqlx::query_as::<_, DatFile>(
+            r#"
+            SELECT d.id, d.name, d.description, d.version, d.imported_at,
+                   (SELECT COUNT(*) FROM dat_entries e WHERE e.dat_id = d.id) as entry_count
+            FROM dat_files d
+            WHERE d.id = ?
+            "#
+        )
+        .bind(dat_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(dat)
+    }
+
+    pub async fn get_dat_files(&self) -> Result<Vec<DatFile>, sqlx::Error> {
+        let dats = sqlx::query_as::<_, DatFile>(
+            r#"
+            SELECT d.id, d.name, d.description, d.version, d.imported_at,
+                   (SELECT COUNT(*) FROM dat_entries e WHERE e.dat_id = d.id) as entry_count
+            FROM dat_files d
+            ORDER BY d.name ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(dats)
+    }
+
+    // Without an imported DAT, hashing a ROM can't match anything
+    pub async fn has_dat_files(&self) -> Result<bool, sqlx::Error> {
+        let row: (i64,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM dat_files)")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.0 != 0)
+    }
+
+    pub async fn delete_dat_file(&self, dat_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM dat_entries WHERE dat_id = ?")
+            .bind(dat_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM dat_files WHERE id = ?")
+            .bind(dat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Strongest hash first; CRC32 alone only counts when the size matches too
+    pub async fn find_dat_match(&self, hashes: &RomHashes) -> Result<Option<DatMatch>, sqlx::Error> {
+        let found = sqlx::query_as::<_, DatMatch>(
+            r#"
+            SELECT d.name as dat_name, e.game_name, e.rom_name, e.region, e.revision
+            FROM dat_entries e
+            JOIN dat_files d ON d.id = e.dat_id
+            WHERE e.sha1 = ?
+               OR e.md5 = ?
+               OR (e.crc32 = ? AND e.size = ?)
+            ORDER BY CASE
+                WHEN e.sha1 = ? THEN 0
+                WHEN e.md5 = ? THEN 1
+                ELSE 2
+            END
+            LIMIT 1
+            "#
+        )
+        .bind(&hashes.sha1)
+        .bind(&hashes.md5)
+        .bind(&hashes.crc32)
+        .bind(hashes.size)
+        .bind(&hashes.sha1)
+        .bind(&hashes.md5)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(found)
+    }
+
+    pub async fn set_game_verification(
+        &self,
+        verification: &Verification,
+        canonical_title: Option<&str>
+    ) -> Result<(), sqlx::Error> {
+        let matched = verification.matched.as_ref();
+        sqlx::query(
+            r#"
+            UPDATE games
+            SET crc32 = ?, md5 = ?, sha1 = ?, verified = ?,
+                region = CASE WHEN ? = 1 THEN ? ELSE region END,
+                revision = CASE WHEN ? = 1 THEN ? ELSE revision END,
+                title = COALESCE(?, title)
+            WHERE id = ?
+            "#
+        )
+        .bind(&verification.hashes.crc32)
+        .bind(&verification.hashes.md5)
+        .bind(&verification.hashes.sha1)
+        .bind(verification.verified as i64)
+        .bind(verification.verified as i64)
+        .bind(matched.and_then(|m| m.region.as_deref()))
+        .bind(verification.verified as i64)
+        .bind(matched.and_then(|m| m.revision.as_deref()))
+        .bind(canonical_title)
+        .bind(&verification.game_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Download queue operations
+    // Queue a download, or re-queue one that previously failed or was cancelled
+    pub async fn enqueue_download(&self, slug: &str, title: &str, platform: &str, link_selector: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO downloads (slug, title, platform, status, link_selector)
+            VALUES (?, ?, ?, 'queued', ?)
+            ON CONFLICT(slug) DO UPDATE SET
+                title = excluded.title,
+                platform = excluded.platform,
+                link_selector = excluded.link_selector,
+                status = 'queued',
+                error = NULL,
+                updated_at = CURRENT_TIMESTAMP
+            "#
+        )
+        .bind(slug)
+        .bind(title)
+        .bind(platform)
+        .bind(link_selector)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_download(&self, slug: &str) -> Result<Option<DownloadItem>, sqlx::Error> {
+        let item = sqlx::query_as::<_, DownloadItem>(
+            "SELECT * FROM downloads WHERE slug = ?"
+        )
+        .bind(slug)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn get_downloads(&self, status: Option<&str>) -> Result<Vec<DownloadItem>, sqlx::Error> {
+        let items = sqlx::query_as::<_, DownloadItem>(
+            r#"
+            SELECT * FROM downloads
+            WHERE (? IS NULL OR status = ?)
+            ORDER BY created_at ASC, rowid ASC
+            "#
+        )
+        .bind(status)
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn set_download_status(&self, slug: &str, status: &str, error: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE downloads SET status = ?, error = ?, updated_at = CURRENT_TIMESTAMP WHERE slug = ?"
+        )
+        .bind(status)
+        .bind(error)
+        .bind(slug)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_download_progress(
+        &self,
+        slug: &str,
+        progress: f64,
+        bytes_received: i64,
+        total_bytes: i64
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE downloads
+            SET progress = ?, bytes_received = ?, total_bytes = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE slug = ?
+            "#
+        )
+        .bind(progress)
+        .bind(bytes_received)
+        .bind(total_bytes)
+        .bind(slug)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_download(&self, slug: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM downloads WHERE slug = ?")
+            .bind(slug)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Downloads that were running when the app quit go back to the queue
+    pub async fn requeue_active_downloads(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE downloads SET status = 'queued' WHERE status = 'active'")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     // Settings operations
     pub async fn get_settings(&self) -> Result<JsonValue, sqlx::Error> {
         let rows: Vec<(String, String)> = sqlx::query_as(
@@ -297,6 +1066,55 @@ impl Database {
         Ok(JsonValue::Object(settings))
     }
 
+    pub async fn get_setting(&self, key: &str) -> Result<Option<JsonValue>, sqlx::Error> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM settings WHERE key = ?"
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|(value,)| serde_json::from_str(&value).ok()))
+    }
+
+    // Per-platform default cores are stored as one settings entry: {"snes": "bsnes", ...}
+    pub async fn get_platform_default_core(&self, platform: &str) -> Result<Option<String>, sqlx::Error> {
+        let defaults = self.get_setting("platform_default_cores").await?;
+        Ok(defaults
+            .as_ref()
+            .and_then(|d| d.get(platform))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()))
+    }
+
+    // Whether installs and scans convert byte-swapped and little-endian N64 ROMs to .z64
+    pub async fn get_normalize_n64(&self) -> Result<bool, sqlx::Error> {
+        Ok(self.get_setting("normalize_n64_roms").await?
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false))
+    }
+
+    // Link ranking for CrocDB installs; missing or malformed settings rank nothing
+    pub async fn get_link_preferences(&self) -> Result<LinkPreferences, sqlx::Error> {
+        let prefs = self.get_setting("link_preferences").await?;
+        Ok(prefs
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default())
+    }
+
+    pub async fn set_platform_default_core(&self, platform: &str, core_id: Option<&str>) -> Result<(), sqlx::Error> {
+        let mut defaults = match self.get_setting("platform_default_cores").await? {
+            Some(JsonValue::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        match core_id {
+            Some(core_id) => { defaults.insert(platform.to_string(), JsonValue::String(core_id.to_string())); }
+            None => { defaults.remove(platform); }
+        }
+
+        self.update_settings(serde_json::json!({ "platform_default_cores": defaults })).await
+    }
+
     pub async fn update_settings(&self, settings: JsonValue) -> Result<(), sqlx::Error> {
         if let Some(obj) = settings.as_object() {
             for (key, value) in obj {
@@ -316,4 +1134,496 @@ impl Database {
 
         Ok(())
     }
+
+    // CrocDB response cache
+    pub async fn get_cached_response(&self, key: &str) -> Result<Option<CachedResponse>, sqlx::Error> {
+        sqlx::query_as::<_, CachedResponse>(
+            "SELECT body, etag, last_modified, fetched_at FROM crocdb_cache WHERE key = ?"
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn put_cached_response(&self, key: &str, endpoint: &str, response: &CachedResponse) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO crocdb_cache (key, endpoint, body, etag, last_modified, fetched_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                body = excluded.body,
+                etag = excluded.etag,
+                last_modified = excluded.last_modified,
+                fetched_at = excluded.fetched_at
+            "#
+        )
+        .bind(key)
+        .bind(endpoint)
+        .bind(&response.body)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(response.fetched_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // A 304 from the API: the cached body is good for another TTL
+    pub async fn touch_cached_response(&self, key: &str, fetched_at: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE crocdb_cache SET fetched_at = ? WHERE key = ?")
+            .bind(fetched_at)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Keep only the `keep` most recently fetched responses of an endpoint. Returns the
+    // number of responses removed.
+    pub async fn prune_cached_responses(&self, endpoint: &str, keep: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM crocdb_cache
+            WHERE endpoint = ?
+              AND key NOT IN (
+                  SELECT key FROM crocdb_cache
+                  WHERE endpoint = ?
+                  ORDER BY fetched_at DESC
+                  LIMIT ?
+              )
+            "#
+        )
+        .bind(endpoint)
+        .bind(endpoint)
+        .bind(keep)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Returns the number of responses removed
+    pub async fn clear_response_cache(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM crocdb_cache")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn bind_values<'q, O>(
+    mut query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    values: Vec<RuleValue>,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    for value in values {
+        query = match value {
+            RuleValue::Int(n) => query.bind(n),
+            RuleValue::Text(s) => query.bind(s),
+        };
+    }
+    query
+}
+
+// "zelda link" -> "zelda"* "link"*: every word has to match, as a prefix so results follow typing
+fn fts_match_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+// WHERE clause of a smart collection. Rules that no longer parse match nothing rather
+// than the whole library.
+fn collection_clause(collection: &Collection) -> (String, Vec<RuleValue>) {
+    match &collection.rules {
+        Some(rules) => smart_rules_clause(rules),
+        None => ("0 = 1".to_string(), Vec::new()),
+    }
+}
+
+// WHERE clause over `games` for a smart collection's rules, with the values to bind in order.
+// A rule that doesn't validate matches nothing, so it can't widen the result; no rules
+// match every game.
+fn smart_rules_clause(rules: &SmartRules) -> (String, Vec<RuleValue>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+
+    for rule in &rules.rules {
+        let Ok(value) = rule.value() else {
+            conditions.push("(0 = 1)".to_string());
+            continue;
+        };
+
+        let condition = if rule.field == RuleField::Tag {
+            let matches = match rule.op {
+                RuleOp::Contains | RuleOp::NotContains => "INSTR(LOWER(t.name), LOWER(?)) > 0",
+                _ => "t.name = ?",
+            };
+            let negate = matches!(rule.op, RuleOp::Ne | RuleOp::NotContains);
+            format!(
+                "{}EXISTS (SELECT 1 FROM game_tags gt JOIN tags t ON t.id = gt.tag_id WHERE gt.game_id = games.id AND {})",
+                if negate { "NOT " } else { "" },
+                matches
+            )
+        } else {
+            let column = match rule.field {
+                RuleField::Title => "COALESCE(games.title, '')",
+                RuleField::Platform => "COALESCE(games.platform, '')",
+                RuleField::Genre => "COALESCE(games.genre, '')",
+                RuleField::Developer => "COALESCE(games.developer, '')",
+                RuleField::Region => "COALESCE(games.region, '')",
+                RuleField::LastPlayed => "games.last_played",
+                RuleField::ReleaseYear => "games.release_year",
+                RuleField::Playtime => "COALESCE(games.total_playtime, 0)",
+                RuleField::PlayCount => "COALESCE(games.play_count, 0)",
+                RuleField::Favorite => "COALESCE(games.is_favorite, 0)",
+                RuleField::Verified => "COALESCE(games.verified, 0)",
+                RuleField::Tag => unreachable!(),
+            };
+            // Text compares ignore case
+            let (column, placeholder) = match value {
+                RuleValue::Text(_) if rule.field != RuleField::LastPlayed => (format!("LOWER({})", column), "LOWER(?)"),
+                _ => (column.to_string(), "?"),
+            };
+            match rule.op {
+                RuleOp::Eq => format!("{} = {}", column, placeholder),
+                RuleOp::Ne => format!("{} <> {}", column, placeholder),
+                RuleOp::Contains => format!("INSTR({}, {}) > 0", column, placeholder),
+                RuleOp::NotContains => format!("INSTR({}, {}) = 0", column, placeholder),
+                RuleOp::Lt => format!("{} < {}", column, placeholder),
+                RuleOp::Lte => format!("{} <= {}", column, placeholder),
+                RuleOp::Gt => format!("{} > {}", column, placeholder),
+                RuleOp::Gte => format!("{} >= {}", column, placeholder),
+            }
+        };
+
+        conditions.push(format!("({})", condition));
+        values.push(value);
+    }
+
+    if conditions.is_empty() {
+        return ("1 = 1".to_string(), values);
+    }
+
+    let joiner = if rules.match_mode == MatchMode::Any { " OR " } else { " AND " };
+    (conditions.join(joiner), values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{NewPlaySession, DEFAULT_PAGE_SIZE};
+
+    async fn test_db(name: &str) -> Database {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-database-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Database::new(&dir.join("retrolauncher.db")).await.unwrap()
+    }
+
+    fn game(id: &str, title: &str, platform: &str) -> Game {
+        Game {
+            id: id.to_string(),
+            title: title.to_string(),
+            platform: platform.to_string(),
+            rom_path: format!("/roms/{}.rom", id),
+            cover_path: None,
+            emulator_id: "retroarch".to_string(),
+            description: None,
+            release_year: None,
+            genre: None,
+            developer: None,
+            is_favorite: 0,
+            play_count: 0,
+            total_playtime: 0,
+            last_played: None,
+            core_override: None,
+            crc32: None,
+            md5: None,
+            sha1: None,
+            verified: 0,
+            region: None,
+            revision: None,
+            base_game_id: None,
+            soft_patch: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn buckets_playtime_by_iso_week() {
+        let db = test_db("iso-weeks").await;
+        db.add_game(game("g1", "Game", "snes")).await.unwrap();
+        for started_at in [
+            "2020-12-31 10:00:00",
+            "2021-01-03 23:00:00",
+            "2021-01-04 08:00:00",
+            "2024-12-29 12:00:00",
+            "2024-12-30 12:00:00",
+            "2025-01-05 12:00:00",
+        ] {
+            db.record_play_session(&NewPlaySession {
+                game_id: "g1".to_string(),
+                started_at: started_at.to_string(),
+                ended_at: started_at.to_string(),
+                duration: 60,
+                core: None,
+                exit_status: "exited".to_string(),
+                exit_code: Some(0),
+            }).await.unwrap();
+        }
+
+        let weeks: Vec<(String, i64)> = db.get_playtime_by_period("week", None, None).await.unwrap()
+            .into_iter()
+            .map(|p| (p.period, p.sessions))
+            .collect();
+
+        assert_eq!(weeks, [
+            ("2020-W53".to_string(), 2),
+            ("2021-W01".to_string(), 1),
+            ("2024-W52".to_string(), 1),
+            ("2025-W01".to_string(), 2),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn prunes_the_oldest_responses_of_an_endpoint() {
+        let db = test_db("cache-prune").await;
+        let response = |fetched_at| CachedResponse {
+            body: "{}".to_string(),
+            etag: None,
+            last_modified: None,
+            fetched_at,
+        };
+        for i in 0..5 {
+            db.put_cached_response(&format!("POST /search {}", i), "/search", &response(100 + i)).await.unwrap();
+        }
+        db.put_cached_response("GET /platforms", "/platforms", &response(0)).await.unwrap();
+
+        assert_eq!(db.prune_cached_responses("/search", 3).await.unwrap(), 2);
+
+        for i in 0..5 {
+            let kept = db.get_cached_response(&format!("POST /search {}", i)).await.unwrap().is_some();
+            assert_eq!(kept, i >= 2, "search {}", i);
+        }
+        assert!(db.get_cached_response("GET /platforms").await.unwrap().is_some());
+    }
+
+    fn rules(value: JsonValue) -> SmartRules {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn smart_collection(rules: Option<SmartRules>) -> Collection {
+        Collection {
+            id: 1,
+            name: "Smart".to_string(),
+            description: None,
+            rules_json: Some("{}".to_string()),
+            rules,
+            game_count: 0,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn builds_smart_rule_sql() {
+        assert_eq!(smart_rules_clause(&SmartRules::default()), ("1 = 1".to_string(), Vec::new()));
+
+        let (clause, values) = smart_rules_clause(&rules(serde_json::json!({
+            "rules": [
+                {"field": "platform", "op": "eq", "value": "SNES"},
+                {"field": "playtime", "op": "lt", "value": "1h"},
+                {"field": "last_played", "op": "gte", "value": "2024-01-01"}
+            ]
+        })));
+        assert_eq!(
+            clause,
+            "(LOWER(COALESCE(games.platform, '')) = LOWER(?)) AND (COALESCE(games.total_playtime, 0) < ?) AND (games.last_played >= ?)"
+        );
+        assert_eq!(values, [
+            RuleValue::Text("SNES".to_string()),
+            RuleValue::Int(3_600),
+            RuleValue::Text("2024-01-01".to_string()),
+        ]);
+
+        let (clause, values) = smart_rules_clause(&rules(serde_json::json!({
+            "match": "any",
+            "rules": [
+                {"field": "title", "op": "not_contains", "value": "demo"},
+                {"field": "tag", "op": "ne", "value": "Beaten"},
+                {"field": "tag", "op": "contains", "value": "rpg"}
+            ]
+        })));
+        assert_eq!(
+            clause,
+            "(INSTR(LOWER(COALESCE(games.title, '')), LOWER(?)) = 0) OR \
+             (NOT EXISTS (SELECT 1 FROM game_tags gt JOIN tags t ON t.id = gt.tag_id WHERE gt.game_id = games.id AND t.name = ?)) OR \
+             (EXISTS (SELECT 1 FROM game_tags gt JOIN tags t ON t.id = gt.tag_id WHERE gt.game_id = games.id AND INSTR(LOWER(t.name), LOWER(?)) > 0))"
+        );
+        assert_eq!(values.len(), 3);
+    }
+
+    #[test]
+    fn invalid_rules_match_nothing() {
+        let (clause, values) = smart_rules_clause(&rules(serde_json::json!({
+            "rules": [
+                {"field": "platform", "op": "eq", "value": "snes"},
+                {"field": "playtime", "op": "lt", "value": "999999999999999d"}
+            ]
+        })));
+        assert_eq!(clause, "(LOWER(COALESCE(games.platform, '')) = LOWER(?)) AND (0 = 1)");
+        assert_eq!(values, [RuleValue::Text("snes".to_string())]);
+
+        let (clause, values) = smart_rules_clause(&rules(serde_json::json!({
+            "match": "any",
+            "rules": [{"field": "favorite", "op": "lt", "value": true}]
+        })));
+        assert_eq!(clause, "(0 = 1)");
+        assert!(values.is_empty());
+
+        assert_eq!(collection_clause(&smart_collection(None)), ("0 = 1".to_string(), Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn smart_collections_select_matching_games() {
+        let db = test_db("smart-rules").await;
+        db.add_game(game("g1", "Super Mario World", "snes")).await.unwrap();
+        db.add_game(game("g2", "Chrono Trigger", "snes")).await.unwrap();
+        db.add_game(game("g3", "Sonic", "genesis")).await.unwrap();
+        let titles = |games: Vec<Game>| games.into_iter().map(|g| g.title).collect::<Vec<_>>();
+
+        let snes = smart_collection(Some(rules(serde_json::json!({
+            "rules": [
+                {"field": "platform", "op": "eq", "value": "SNES"},
+                {"field": "title", "op": "contains", "value": "mario"}
+            ]
+        }))));
+        assert_eq!(titles(db.get_collection_games(&snes).await.unwrap()), ["Super Mario World"]);
+
+        let broken = smart_collection(Some(rules(serde_json::json!({
+            "match": "any",
+            "rules": [{"field": "release_year", "op": "eq", "value": "soon"}]
+        }))));
+        assert!(db.get_collection_games(&broken).await.unwrap().is_empty());
+        assert!(db.get_collection_games(&smart_collection(None)).await.unwrap().is_empty());
+
+        // A blank library filter is no filter at all
+        let search = LibrarySearch { platform: Some(" ".to_string()), ..Default::default() };
+        assert_eq!(db.search_library(&search).await.unwrap().total, 3);
+    }
+
+    #[tokio::test]
+    async fn query_games_filters_sorts_and_pages() {
+        let db = test_db("query-games").await;
+        db.add_game(game("a", "Alpha", "snes")).await.unwrap();
+        db.add_game(game("b", "bravo", "nes")).await.unwrap();
+        db.add_game(game("c", "Charlie", "snes")).await.unwrap();
+        db.add_game(game("d", "Delta", "genesis")).await.unwrap();
+        db.toggle_favorite("b").await.unwrap();
+        db.toggle_favorite("c").await.unwrap();
+        for id in ["c", "c", "a"] {
+            db.update_game_stats(id).await.unwrap();
+        }
+        db.record_play_session(&NewPlaySession {
+            game_id: "d".to_string(),
+            started_at: "2024-01-01 10:00:00".to_string(),
+            ended_at: "2024-01-01 10:10:00".to_string(),
+            duration: 600,
+            core: None,
+            exit_status: "exited".to_string(),
+            exit_code: Some(0),
+        }).await.unwrap();
+        let ids = |page: LibraryPage| page.games.into_iter().map(|g| g.id).collect::<Vec<_>>();
+
+        // Titles sort ascending ignoring case unless told otherwise
+        let page = db.query_games(&GameQuery::default()).await.unwrap();
+        assert_eq!((page.total, page.offset, page.limit), (4, 0, DEFAULT_PAGE_SIZE));
+        assert_eq!(ids(page), ["a", "b", "c", "d"]);
+        let query = GameQuery { descending: Some(true), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["d", "c", "b", "a"]);
+
+        let query = GameQuery { platform: Some("SNES".to_string()), ..Default::default() };
+        let page = db.query_games(&query).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(ids(page), ["a", "c"]);
+        let query = GameQuery { favorite: Some(true), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["b", "c"]);
+        let query = GameQuery { favorite: Some(false), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["a", "d"]);
+
+        // Stat sorts default to descending; ties fall back to the title
+        let query = GameQuery { sort: Some(LibrarySort::PlayCount), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["c", "a", "b", "d"]);
+        let query = GameQuery { sort: Some(LibrarySort::PlayCount), descending: Some(false), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["b", "d", "a", "c"]);
+        let query = GameQuery { sort: Some(LibrarySort::Playtime), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["d", "a", "b", "c"]);
+        let query = GameQuery { sort: Some(LibrarySort::Platform), ..Default::default() };
+        assert_eq!(ids(db.query_games(&query).await.unwrap()), ["d", "b", "a", "c"]);
+
+        let query = GameQuery { offset: 1, limit: Some(2), ..Default::default() };
+        let page = db.query_games(&query).await.unwrap();
+        assert_eq!((page.total, page.offset, page.limit), (4, 1, 2));
+        assert_eq!(ids(page), ["b", "c"]);
+        let query = GameQuery { offset: 3, limit: Some(0), ..Default::default() };
+        let page = db.query_games(&query).await.unwrap();
+        assert_eq!((page.total, page.limit), (4, 1));
+        assert_eq!(ids(page), ["d"]);
+        let query = GameQuery { offset: 10, ..Default::default() };
+        let page = db.query_games(&query).await.unwrap();
+        assert_eq!(page.total, 4);
+        assert!(page.games.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_follows_game_and_tag_changes() {
+        let db = test_db("fts-sync").await;
+        db.add_game(game("g1", "The Legend of Zelda", "nes")).await.unwrap();
+        db.add_game(Game {
+            description: Some("An adventure set in the world of Zelda".to_string()),
+            ..game("g2", "Link's Awakening", "gb")
+        }).await.unwrap();
+        db.add_game(game("g3", "Metroid", "nes")).await.unwrap();
+        let search = |query: &str| LibrarySearch { query: Some(query.to_string()), ..Default::default() };
+        let ids = |page: LibraryPage| page.games.into_iter().map(|g| g.id).collect::<Vec<_>>();
+
+        // A title hit outranks a description hit, and words match as prefixes
+        let page = db.search_library(&search("zel")).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(ids(page), ["g1", "g2"]);
+
+        let second = LibrarySearch { offset: 1, limit: Some(1), ..search("zelda") };
+        let page = db.search_library(&second).await.unwrap();
+        assert_eq!((page.total, page.offset, page.limit), (2, 1, 1));
+        assert_eq!(ids(page), ["g2"]);
+
+        sqlx::query("UPDATE games SET title = 'Zelda II' WHERE id = 'g3'").execute(&db.pool).await.unwrap();
+        assert!(db.search_library(&search("metroid")).await.unwrap().games.is_empty());
+        assert_eq!(db.search_library(&search("zelda")).await.unwrap().total, 3);
+
+        db.delete_game("g1").await.unwrap();
+        assert_eq!(ids(db.search_library(&search("zelda")).await.unwrap()), ["g3", "g2"]);
+        assert!(db.search_library(&search("legend")).await.unwrap().games.is_empty());
+
+        let tags = db.set_game_tags("g2", &["Speedrun".to_string()]).await.unwrap();
+        assert_eq!(ids(db.search_library(&search("speedrun")).await.unwrap()), ["g2"]);
+
+        db.update_tag(tags[0].id, "Marathon", None).await.unwrap();
+        assert!(db.search_library(&search("speedrun")).await.unwrap().games.is_empty());
+        assert_eq!(ids(db.search_library(&search("marathon")).await.unwrap()), ["g2"]);
+
+        db.set_game_tags("g2", &[]).await.unwrap();
+        assert!(db.search_library(&search("marathon")).await.unwrap().games.is_empty());
+        assert_eq!(db.search_library(&search("adventure")).await.unwrap().total, 1);
+    }
 }
@@ -0,0 +1,594 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use tokio::sync::Notify;
+
+use crate::crocdb;
+use crate::dat;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::game::Game;
+use crate::romid;
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_ACTIVE: &str = "active";
+pub const STATUS_PAUSED: &str = "paused";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_COMPLETED: &str = "completed";
+
+const DEFAULT_MAX_CONCURRENT: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DownloadItem {
+    pub slug: String,
+    pub title: String,
+    pub platform: String,
+    pub status: String,
+    pub progress: f64,
+    pub bytes_received: i64,
+    pub total_bytes: i64,
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    // JSON-encoded crocdb::LinkSelector chosen at enqueue time
+    #[sqlx(default)]
+    pub link_selector: Option<String>,
+}
+
+// Last progress seen on "download-progress" for a running download
+#[derive(Debug, Clone, Default)]
+struct LiveProgress {
+    progress: f64,
+    bytes_received: i64,
+    total_bytes: i64,
+}
+
+struct ActiveDownload {
+    task: tauri::async_runtime::JoinHandle<()>,
+    // Aborting the task can't stop an extraction already running on the blocking pool
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    active: HashMap<String, ActiveDownload>,
+    live: HashMap<String, LiveProgress>,
+}
+
+// CrocDB installs run through this queue instead of inside the Tauri command. Every
+// download is a row in the `downloads` table, so the queue survives restarts; at most
+// `max_concurrent_downloads` (setting) installs run at a time. Without an app handle no
+// events are emitted, which is how the tests run it.
+#[derive(Clone)]
+pub struct DownloadQueue {
+    db: Database,
+    games_dir: PathBuf,
+    app_handle: Option<tauri::AppHandle>,
+    state: Arc<Mutex<QueueState>>,
+    wake: Arc<Notify>,
+}
+
+impl DownloadQueue {
+    pub fn new(db: Database, games_dir: PathBuf, app_handle: Option<tauri::AppHandle>) -> Self {
+        DownloadQueue {
+            db,
+            games_dir,
+            app_handle,
+            state: Arc::default(),
+            wake: Arc::default(),
+        }
+    }
+
+    // Start the scheduler. Downloads left active by the previous run are queued again.
+    pub fn start(&self) {
+        if let Some(app_handle) = &self.app_handle {
+            let state = self.state.clone();
+            app_handle.listen_global("download-progress", move |event| {
+                let payload: serde_json::Value = match event.payload().and_then(|p| serde_json::from_str(p).ok()) {
+                    Some(payload) => payload,
+                    None => return,
+                };
+                let slug = payload["slug"].as_str().unwrap_or_default();
+                let mut state = state.lock().unwrap();
+                if !state.active.contains_key(slug) {
+                    return;
+                }
+                let live = state.live.entry(slug.to_string()).or_default();
+                if let Some(progress) = payload["progress"].as_f64() {
+                    live.progress = progress;
+                }
+                if let Some(bytes) = payload["bytesReceived"].as_i64() {
+                    live.bytes_received = bytes;
+                }
+                if let Some(total) = payload["totalBytes"].as_i64().filter(|&t| t > 0) {
+                    live.total_bytes = total;
+                }
+            });
+        }
+
+        let queue = self.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = queue.db.requeue_active_downloads().await {
+                eprintln!("Failed to restore download queue: {}", e);
+            }
+            loop {
+                if let Err(e) = queue.fill_slots().await {
+                    eprintln!("Download queue error: {}", e);
+                }
+                queue.wake.notified().await;
+            }
+        });
+    }
+
+    // Start queued downloads until the concurrency limit is reached
+    async fn fill_slots(&self) -> Result<(), sqlx::Error> {
+        let limit = max_concurrent_downloads(&self.db).await;
+
+        for item in self.db.get_downloads(Some(STATUS_QUEUED)).await? {
+            if self.state.lock().unwrap().active.len() >= limit {
+                break;
+            }
+            self.db.set_download_status(&item.slug, STATUS_ACTIVE, None).await?;
+
+            // Hold the lock while spawning so the task can't finish before it is registered
+            let mut state = self.state.lock().unwrap();
+            let cancel = Arc::new(AtomicBool::new(false));
+            let task = tauri::async_runtime::spawn(run_download(self.clone(), item.slug.clone(), cancel.clone()));
+            state.active.insert(item.slug, ActiveDownload { task, cancel });
+        }
+
+        Ok(())
+    }
+
+    // Add a CrocDB entry to the queue
+    pub async fn enqueue(&self, slug: &str, selector: Option<crocdb::LinkSelector>) -> Result<DownloadItem, AppError> {
+        let db = &self.db;
+        if let Some(existing) = db.get_download(slug).await? {
+            if existing.status == STATUS_QUEUED || existing.status == STATUS_ACTIVE || existing.status == STATUS_PAUSED {
+                return Err(format!("'{}' is already in the download queue", existing.title).into());
+            }
+        }
+        // Installing again would download everything only to fail on the duplicate id
+        match db.get_game(&game_id(slug)).await {
+            Ok(game) => return Err(AppError::Install(format!("'{}' is already in the library", game.title))),
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // Reject selectors that match nothing now rather than when the download starts
+        let client = crocdb::CrocDbClient::from_settings(db).await?;
+        let entry = crocdb::get_entry(&client, db, slug).await?;
+        let prefs = db.get_link_preferences().await?;
+        crocdb::select_link(&entry.download_links, selector.as_ref(), &prefs)?;
+
+        let selector_json = selector
+            .map(|s| serde_json::to_string(&s))
+            .transpose()?;
+        db.enqueue_download(slug, &entry.title, &entry.platform, selector_json.as_deref()).await?;
+        let item = db.get_download(slug).await?
+            .ok_or("Failed to queue download")?;
+
+        self.emit_status(&item, "Waiting for a free download slot...");
+        self.wake.notify_one();
+        Ok(item)
+    }
+
+    pub async fn pause(&self, slug: &str) -> Result<(), AppError> {
+        let db = &self.db;
+        let item = db.get_download(slug).await?
+            .ok_or_else(|| AppError::NotFound(format!("No download for '{}'", slug)))?;
+        if item.status != STATUS_QUEUED && item.status != STATUS_ACTIVE {
+            return Err(format!("'{}' is {} and can't be paused", item.title, item.status).into());
+        }
+
+        // The partial file stays on disk and is resumed from where it stopped
+        let live = self.stop_task(slug);
+        if let Some(live) = live {
+            db.set_download_progress(slug, live.progress, live.bytes_received, live.total_bytes).await?;
+        }
+        db.set_download_status(slug, STATUS_PAUSED, None).await?;
+
+        if let Some(item) = db.get_download(slug).await? {
+            self.emit_status(&item, "Paused");
+        }
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    pub async fn resume(&self, slug: &str) -> Result<(), AppError> {
+        let db = &self.db;
+        let item = db.get_download(slug).await?
+            .ok_or_else(|| AppError::NotFound(format!("No download for '{}'", slug)))?;
+        if item.status != STATUS_PAUSED && item.status != STATUS_FAILED {
+            return Err(format!("'{}' is {} and can't be resumed", item.title, item.status).into());
+        }
+
+        db.set_download_status(slug, STATUS_QUEUED, None).await?;
+        if let Some(item) = db.get_download(slug).await? {
+            self.emit_status(&item, "Waiting for a free download slot...");
+        }
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    // Stop the download, forget it and delete its partial files
+    pub async fn cancel(&self, slug: &str) -> Result<(), AppError> {
+        let item = self.db.get_download(slug).await?
+            .ok_or_else(|| AppError::NotFound(format!("No download for '{}'", slug)))?;
+
+        self.stop_task(slug);
+        self.db.delete_download(slug).await?;
+
+        if item.status != STATUS_COMPLETED {
+            let game_dir = crocdb::game_install_dir(&self.games_dir, &item.platform, &item.title);
+            remove_partial_files(&game_dir);
+        }
+
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    // All downloads, with live progress for the running ones
+    pub async fn list(&self) -> Result<Vec<DownloadItem>, AppError> {
+        let mut items = self.db.get_downloads(None).await?;
+        let state = self.state.lock().unwrap();
+        for item in items.iter_mut() {
+            if let Some(live) = state.live.get(&item.slug) {
+                item.progress = live.progress;
+                item.bytes_received = live.bytes_received;
+                item.total_bytes = live.total_bytes;
+            }
+        }
+        Ok(items)
+    }
+
+    // Re-read the concurrency limit, e.g. after settings changed
+    pub fn reschedule(&self) {
+        self.wake.notify_one();
+    }
+
+    fn stop_task(&self, slug: &str) -> Option<LiveProgress> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(active) = state.active.remove(slug) {
+            active.cancel.store(true, Ordering::Relaxed);
+            active.task.abort();
+        }
+        state.live.remove(slug)
+    }
+
+    // Queue state changes use the same event as progress so the DownloadManager cards update
+    fn emit_status(&self, item: &DownloadItem, message: &str) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit_all("download-progress", serde_json::json!({
+                "slug": item.slug,
+                "stage": item.status,
+                "progress": item.progress,
+                "message": message,
+                "bytesReceived": item.bytes_received,
+                "totalBytes": item.total_bytes,
+                "title": item.title,
+            }));
+        }
+    }
+
+    // A failed install also carries the error itself, kind and all
+    fn emit_failure(&self, item: &DownloadItem, error: &AppError) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit_all("download-progress", serde_json::json!({
+                "slug": item.slug,
+                "stage": item.status,
+                "progress": item.progress,
+                "message": format!("Download failed: {}", error),
+                "bytesReceived": item.bytes_received,
+                "totalBytes": item.total_bytes,
+                "title": item.title,
+                "error": error,
+            }));
+        }
+    }
+}
+
+async fn run_download(queue: DownloadQueue, slug: String, cancel: Arc<AtomicBool>) {
+    let result = install_from_crocdb(&queue, &slug, cancel).await;
+
+    let live = {
+        let mut state = queue.state.lock().unwrap();
+        state.active.remove(&slug);
+        state.live.remove(&slug).unwrap_or_default()
+    };
+
+    let db = &queue.db;
+    let saved = match &result {
+        Ok(_) => {
+            let total = live.total_bytes.max(live.bytes_received);
+            let _ = db.set_download_progress(&slug, 100.0, total, total).await;
+            db.set_download_status(&slug, STATUS_COMPLETED, None).await
+        }
+        Err(e) => {
+            eprintln!("Download of {} failed: {}", slug, e);
+            let _ = db.set_download_progress(&slug, live.progress, live.bytes_received, live.total_bytes).await;
+            db.set_download_status(&slug, STATUS_FAILED, Some(&e.to_string())).await
+        }
+    };
+    if let Err(e) = saved {
+        eprintln!("Failed to update download {}: {}", slug, e);
+    }
+
+    if let (Err(e), Ok(Some(item))) = (&result, db.get_download(&slug).await) {
+        queue.emit_failure(&item, e);
+    }
+
+    queue.wake.notify_one();
+}
+
+// Library id of a game installed from CrocDB
+fn game_id(slug: &str) -> String {
+    format!("crocdb_{}", slug.replace("-", "_"))
+}
+
+// Download, extract and add a CrocDB entry to the library
+async fn install_from_crocdb(queue: &DownloadQueue, slug: &str, cancel: Arc<AtomicBool>) -> Result<String, AppError> {
+    let install_dir = &queue.games_dir;
+    std::fs::create_dir_all(install_dir)?;
+
+    let db = &queue.db;
+    let selector: Option<crocdb::LinkSelector> = db.get_download(slug).await?
+        .and_then(|item| item.link_selector)
+        .and_then(|json| serde_json::from_str(&json).ok());
+    let prefs = db.get_link_preferences().await?;
+    let client = crocdb::CrocDbClient::from_settings(db).await?;
+
+    // Install the game (download + extract) with progress events
+    let install_result = crocdb::install_game_complete(
+        &client,
+        db,
+        slug,
+        &install_dir.to_string_lossy(),
+        selector.as_ref(),
+        &prefs,
+        Some(cancel),
+        queue.app_handle.as_ref(),
+    ).await?;
+
+    let mut rom_path = install_result.rom_path;
+    if db.get_normalize_n64().await? {
+        rom_path = romid::normalize_n64_rom(PathBuf::from(&rom_path), false).await
+            .to_string_lossy()
+            .to_string();
+    }
+
+    let game_id = game_id(slug);
+
+    // Add to database
+    let game = Game {
+        id: game_id.clone(),
+        title: install_result.title.clone(),
+        platform: install_result.platform.clone(),
+        rom_path,
+        cover_path: install_result.cover_path,
+        emulator_id: "retroarch".to_string(), // Launch through RetroArch unless reassigned
+        description: Some(format!("Downloaded from CrocDB: {}", slug)),
+        release_year: None,
+        genre: None,
+        developer: None,
+        is_favorite: 0,
+        play_count: 0,
+        total_playtime: 0,
+        last_played: None,
+        core_override: None,
+        crc32: None,
+        md5: None,
+        sha1: None,
+        verified: 0,
+        region: None,
+        revision: None,
+        base_game_id: None,
+        soft_patch: None,
+    };
+
+    db.add_game(game).await?;
+
+    // Record hashes and the DAT match; the install itself already succeeded
+    if db.has_dat_files().await? {
+        if let Err(e) = dat::verify_game(db, &game_id).await {
+            eprintln!("Failed to verify {}: {}", game_id, e);
+        }
+    }
+
+    Ok(format!("Game '{}' installed successfully!", install_result.title))
+}
+
+pub fn games_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_dir = app_handle.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to get app data directory")?;
+    Ok(app_dir.join("games"))
+}
+
+async fn max_concurrent_downloads(db: &Database) -> usize {
+    db.get_setting("max_concurrent_downloads").await
+        .ok()
+        .flatten()
+        .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+        .map(|n| n.max(1) as usize)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT)
+}
+
+// Delete leftover .part files (and their .part.info), and the game directory if nothing
+// else is in it
+fn remove_partial_files(game_dir: &std::path::Path) {
+    if let Ok(entries) = std::fs::read_dir(game_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.ends_with(".part") || name.ends_with(".part.info") {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+    let _ = std::fs::remove_dir(game_dir);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{rom_zip, start_crocdb_stub, StubServer};
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-queue-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn test_queue(dir: &Path, server: &StubServer, max_concurrent: u64) -> DownloadQueue {
+        let db = Database::new(&dir.join("retrolauncher.db")).await.unwrap();
+        db.update_settings(serde_json::json!({
+            "crocdb_api_base": server.base_url,
+            "max_concurrent_downloads": max_concurrent,
+        })).await.unwrap();
+        DownloadQueue::new(db, dir.join("games"), None)
+    }
+
+    // Poll until `check` holds, failing after 10 seconds
+    async fn wait_until<F: std::future::Future<Output = bool>>(what: &str, mut check: impl FnMut() -> F) {
+        for _ in 0..500 {
+            if check().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("timed out waiting for {}", what);
+    }
+
+    async fn wait_for_status(queue: &DownloadQueue, slug: &str, status: &str) -> DownloadItem {
+        wait_until(&format!("{} to be {}", slug, status), || async {
+            queue.db.get_download(slug).await.unwrap().is_some_and(|item| item.status == status)
+        }).await;
+        queue.db.get_download(slug).await.unwrap().unwrap()
+    }
+
+    fn part_file(queue: &DownloadQueue, slug: &str, title: &str) -> PathBuf {
+        crocdb::game_install_dir(&queue.games_dir, "gba", title).join(format!("{}.zip.part", slug))
+    }
+
+    // Wait for the stalled stub to have delivered the first bytes of `slug`
+    async fn wait_for_partial(queue: &DownloadQueue, slug: &str, title: &str) -> PathBuf {
+        let part = part_file(queue, slug, title);
+        wait_until("a partial file", || async {
+            std::fs::metadata(&part).map(|m| m.len() > 0).unwrap_or(false)
+        }).await;
+        part
+    }
+
+    fn active_count(queue: &DownloadQueue) -> usize {
+        queue.state.lock().unwrap().active.len()
+    }
+
+    #[tokio::test]
+    async fn installs_queued_games_into_the_library() {
+        let server = start_crocdb_stub(rom_zip());
+        let queue = test_queue(&test_dir("install"), &server, 2).await;
+        queue.start();
+
+        let item = queue.enqueue("stub-game", None).await.unwrap();
+        assert_eq!(item.status, STATUS_QUEUED);
+        let item = wait_for_status(&queue, "stub-game", STATUS_COMPLETED).await;
+
+        assert_eq!(item.progress, 100.0);
+        let game = queue.db.get_game("crocdb_stub_game").await.unwrap();
+        assert!(game.rom_path.ends_with("Stub Game.gba"));
+        assert_eq!(active_count(&queue), 0);
+
+        // Already installed: nothing is downloaded again
+        let error = queue.enqueue("stub-game", None).await.unwrap_err();
+        assert!(matches!(error, AppError::Install(_)), "{:?}", error);
+        assert_eq!(server.requests_to("/files/stub-game.zip").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn downloads_left_active_are_resumed_on_start() {
+        let server = start_crocdb_stub(rom_zip());
+        let queue = test_queue(&test_dir("restore"), &server, 2).await;
+        // As left behind by an app that quit mid-download
+        queue.db.enqueue_download("stub-game", "Stub Game", "gba", None).await.unwrap();
+        queue.db.set_download_status("stub-game", STATUS_ACTIVE, None).await.unwrap();
+
+        queue.start();
+
+        wait_for_status(&queue, "stub-game", STATUS_COMPLETED).await;
+        assert!(queue.db.get_game("crocdb_stub_game").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn runs_at_most_max_concurrent_downloads() {
+        let server = start_crocdb_stub(rom_zip());
+        server.stall.store(true, Ordering::SeqCst);
+        let queue = test_queue(&test_dir("limit"), &server, 1).await;
+        queue.start();
+
+        queue.enqueue("first-game", None).await.unwrap();
+        queue.enqueue("second-game", None).await.unwrap();
+        wait_for_partial(&queue, "first-game", "First Game").await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(queue.db.get_download("second-game").await.unwrap().unwrap().status, STATUS_QUEUED);
+        assert!(server.requests_to("/files/second-game.zip").is_empty());
+        assert_eq!(active_count(&queue), 1);
+
+        server.stall.store(false, Ordering::SeqCst);
+        wait_for_status(&queue, "first-game", STATUS_COMPLETED).await;
+        wait_for_status(&queue, "second-game", STATUS_COMPLETED).await;
+    }
+
+    #[tokio::test]
+    async fn pause_keeps_the_partial_file_until_resumed() {
+        let server = start_crocdb_stub(rom_zip());
+        server.stall.store(true, Ordering::SeqCst);
+        let queue = test_queue(&test_dir("pause"), &server, 2).await;
+        queue.start();
+
+        queue.enqueue("stub-game", None).await.unwrap();
+        let part = wait_for_partial(&queue, "stub-game", "Stub Game").await;
+        queue.pause("stub-game").await.unwrap();
+
+        assert_eq!(queue.db.get_download("stub-game").await.unwrap().unwrap().status, STATUS_PAUSED);
+        assert_eq!(active_count(&queue), 0);
+        assert!(part.exists());
+        // A paused download is still pending
+        assert!(queue.enqueue("stub-game", None).await.is_err());
+        assert!(queue.pause("stub-game").await.is_err());
+
+        server.stall.store(false, Ordering::SeqCst);
+        queue.resume("stub-game").await.unwrap();
+        wait_for_status(&queue, "stub-game", STATUS_COMPLETED).await;
+        assert!(!part.exists());
+        assert!(queue.db.get_game("crocdb_stub_game").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn cancel_forgets_the_download_and_its_files() {
+        let server = start_crocdb_stub(rom_zip());
+        server.stall.store(true, Ordering::SeqCst);
+        let queue = test_queue(&test_dir("cancel"), &server, 2).await;
+        queue.start();
+
+        queue.enqueue("stub-game", None).await.unwrap();
+        let part = wait_for_partial(&queue, "stub-game", "Stub Game").await;
+        queue.cancel("stub-game").await.unwrap();
+        server.stall.store(false, Ordering::SeqCst);
+
+        assert!(queue.db.get_download("stub-game").await.unwrap().is_none());
+        assert_eq!(active_count(&queue), 0);
+        assert!(!part.exists());
+        assert!(!part.parent().unwrap().exists());
+        assert!(queue.db.get_game("crocdb_stub_game").await.is_err());
+    }
+}
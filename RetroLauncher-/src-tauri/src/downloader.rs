@@ -0,0 +1,655 @@
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::error::AppError;
+
+// Shared by every download in the app (games, covers, RetroArch, cores).
+// Data is written to `<destination>.part` and only renamed once complete, so an
+// interrupted download is resumed with an HTTP Range request instead of starting over.
+// The server's validator and total size are kept in `<destination>.part.info`; a resume
+// sends them back with If-Range, so a file that changed upstream is downloaded again
+// instead of being spliced onto the old bytes.
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    // Size advertised by the source (e.g. CrocDBLink.size), checked against the final file
+    pub expected_size: Option<u64>,
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // Give up on a connection that delivers nothing for this long
+    pub stall_timeout: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            expected_size: None,
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            stall_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl DownloadOptions {
+    // CrocDB reports 0 when it doesn't know the size
+    pub fn with_expected_size(size: i64) -> Self {
+        DownloadOptions {
+            expected_size: u64::try_from(size).ok().filter(|&s| s > 0),
+            ..Default::default()
+        }
+    }
+}
+
+// Fields of the "download-progress" events emitted while downloading
+#[derive(Debug, Clone)]
+pub struct ProgressEvent {
+    pub slug: String,
+    pub stage: String,
+    pub message: String,
+    pub title: Option<String>,
+}
+
+impl ProgressEvent {
+    pub fn emit(&self, app_handle: Option<&tauri::AppHandle>, progress: f32, message: &str, received: u64, total: u64, speed_bps: f64) {
+        if let Some(handle) = app_handle {
+            let mut payload = serde_json::json!({
+                "slug": self.slug,
+                "stage": self.stage,
+                "progress": progress,
+                "message": message,
+                "bytesReceived": received,
+                "totalBytes": total,
+                "speedBps": speed_bps,
+            });
+            if let Some(title) = &self.title {
+                payload["title"] = serde_json::json!(title);
+            }
+            let _ = handle.emit_all("download-progress", payload);
+        }
+    }
+}
+
+enum AttemptError {
+    // Worth another try: dropped connection, timeout, 5xx...
+    Retry(AppError),
+    Fatal(AppError),
+}
+
+pub fn part_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    destination.with_file_name(name)
+}
+
+// What the server said about the file the .part belongs to
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PartInfo {
+    // Strong ETag, or Last-Modified when there is none
+    validator: Option<String>,
+    // 0 when the server didn't send a length
+    total: u64,
+}
+
+fn info_path(part: &Path) -> PathBuf {
+    let mut name = part.file_name().unwrap_or_default().to_os_string();
+    name.push(".info");
+    part.with_file_name(name)
+}
+
+fn read_part_info(part: &Path) -> Option<PartInfo> {
+    serde_json::from_slice(&fs::read(info_path(part)).ok()?).ok()
+}
+
+fn write_part_info(part: &Path, info: &PartInfo) -> std::io::Result<()> {
+    fs::write(info_path(part), serde_json::to_vec(info)?)
+}
+
+fn discard_part(part: &Path) {
+    let _ = fs::remove_file(part);
+    let _ = fs::remove_file(info_path(part));
+}
+
+// If-Range only accepts strong ETags
+fn response_validator(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok());
+    header(reqwest::header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(reqwest::header::LAST_MODIFIED))
+        .map(str::to_string)
+}
+
+// Download `url` to `destination`, resuming and retrying with exponential backoff
+pub async fn download_file(
+    url: &str,
+    destination: &Path,
+    options: &DownloadOptions,
+    progress: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<PathBuf, AppError> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(15))
+        .build()?;
+    let part = part_path(destination);
+
+    let mut attempt = 0;
+    let mut backoff = options.initial_backoff;
+    loop {
+        let before = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+        let error = match download_attempt(&client, url, &part, options, progress, app_handle).await {
+            Ok(total) => {
+                if let Some(expected) = options.expected_size {
+                    if total != expected {
+                        discard_part(&part);
+                        return Err(AppError::Parse(format!(
+                            "Downloaded size ({} bytes) does not match the expected size ({} bytes)",
+                            total, expected
+                        )));
+                    }
+                }
+                fs::rename(&part, destination)?;
+                let _ = fs::remove_file(info_path(&part));
+                progress.emit(app_handle, 100.0, "Download complete", total, total, 0.0);
+                return Ok(destination.to_path_buf());
+            }
+            Err(AttemptError::Fatal(error)) => return Err(error),
+            Err(AttemptError::Retry(error)) => error,
+        };
+
+        // A connection that made progress before dropping doesn't count against the limit
+        let after = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        if after > before {
+            attempt = 0;
+            backoff = options.initial_backoff;
+        }
+        attempt += 1;
+        if attempt > options.max_retries {
+            // The last error keeps its kind, so a 429 still reads as a rate limit
+            eprintln!("Download failed after {} retries: {}", options.max_retries, error);
+            return Err(error);
+        }
+
+        println!("Download interrupted ({}), retrying in {:?} (attempt {}/{})", error, backoff, attempt, options.max_retries);
+        progress.emit(
+            app_handle,
+            0.0,
+            &format!("Connection lost, retrying in {}s...", backoff.as_secs().max(1)),
+            after,
+            0,
+            0.0,
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(options.max_backoff);
+    }
+}
+
+// One request, appending to the .part file. Returns the total size once the file is complete.
+async fn download_attempt(
+    client: &reqwest::Client,
+    url: &str,
+    part: &Path,
+    options: &DownloadOptions,
+    progress: &ProgressEvent,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<u64, AttemptError> {
+    // A .part without its info can't be checked against the remote file: start over
+    let info = read_part_info(part);
+    let mut offset = match info {
+        Some(_) => fs::metadata(part).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = info.as_ref().and_then(|i| i.validator.as_deref()) {
+            request = request.header(reqwest::header::IF_RANGE, validator);
+        }
+    }
+    let response = request.send().await.map_err(|e| AttemptError::Retry(e.into()))?;
+    let status = response.status();
+
+    let total = match status {
+        reqwest::StatusCode::PARTIAL_CONTENT => {
+            let content_range = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_content_range);
+            let known_total = info.as_ref().map_or(0, |i| i.total);
+            match content_range {
+                // A different total means the file changed since the .part was started
+                Some((_, total)) if (known_total > 0 && total != known_total)
+                    || options.expected_size.is_some_and(|expected| total != expected) => {
+                    discard_part(part);
+                    return Err(AttemptError::Retry(AppError::Network("Remote file changed since the download started".to_string())));
+                }
+                Some((start, total)) if start == offset => total,
+                // Resumed at a different offset than asked for, the .part can't be trusted
+                _ => {
+                    discard_part(part);
+                    return Err(AttemptError::Retry(AppError::Network("Server resumed at an unexpected offset".to_string())));
+                }
+            }
+        }
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+            // Either the .part is already complete or it is bigger than the remote file
+            let total = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit('/').next())
+                .and_then(|v| v.parse::<u64>().ok());
+            if total == Some(offset) && info.as_ref().is_some_and(|i| i.total == offset) {
+                return Ok(offset);
+            }
+            discard_part(part);
+            return Err(AttemptError::Retry(AppError::Network("Partial download does not match the remote file".to_string())));
+        }
+        s if s.is_success() => {
+            // Fresh download, or the server ignored the Range header / the If-Range
+            // validator no longer matches: start over
+            offset = 0;
+            let total = response.content_length().unwrap_or(0);
+            let info = PartInfo { validator: response_validator(response.headers()), total };
+            write_part_info(part, &info).map_err(|e| AttemptError::Fatal(e.into()))?;
+            total
+        }
+        s if s.is_server_error()
+            || s == reqwest::StatusCode::REQUEST_TIMEOUT
+            || s == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            return Err(AttemptError::Retry(AppError::from_response(&response)));
+        }
+        _ => return Err(AttemptError::Fatal(AppError::from_response(&response))),
+    };
+
+    if let (Some(expected), true) = (options.expected_size, total > 0) {
+        if total != expected {
+            discard_part(part);
+            return Err(AttemptError::Fatal(AppError::Parse(format!(
+                "Server reports {} bytes but {} bytes were expected",
+                total, expected
+            ))));
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(part)
+        .map_err(|e| AttemptError::Fatal(e.into()))?;
+
+    let mut downloaded = offset;
+    let mut session_bytes: u64 = 0;
+    let start = Instant::now();
+    let mut last_emit = Instant::now();
+    let mut stream = response.bytes_stream();
+    loop {
+        let chunk = match tokio::time::timeout(options.stall_timeout, stream.next()).await {
+            Ok(Some(chunk)) => chunk.map_err(|e| AttemptError::Retry(e.into()))?,
+            Ok(None) => break,
+            Err(_) => return Err(AttemptError::Retry(AppError::Network("Connection stalled".to_string()))),
+        };
+        file.write_all(&chunk).map_err(|e| AttemptError::Fatal(e.into()))?;
+        downloaded += chunk.len() as u64;
+        session_bytes += chunk.len() as u64;
+
+        // Emit every ~120ms to avoid flooding
+        if last_emit.elapsed() >= Duration::from_millis(120) {
+            let speed_bps = session_bytes as f64 / start.elapsed().as_secs_f64().max(0.001);
+            let pct = if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 };
+            progress.emit(app_handle, pct, &progress.message, downloaded, total, speed_bps);
+            last_emit = Instant::now();
+        }
+    }
+    file.flush().map_err(|e| AttemptError::Fatal(e.into()))?;
+
+    if total > 0 {
+        if downloaded < total {
+            return Err(AttemptError::Retry(AppError::Network(format!("Connection closed at {} of {} bytes", downloaded, total))));
+        }
+        if downloaded > total {
+            discard_part(part);
+            return Err(AttemptError::Fatal(AppError::Parse(format!(
+                "Received {} bytes but Content-Length was {}",
+                downloaded, total
+            ))));
+        }
+    }
+
+    Ok(downloaded)
+}
+
+// "bytes 100-199/1000" -> (100, 1000)
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let range = value.strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    // Minimal HTTP/1.1 file server. The first `drops` responses are cut off after
+    // `chunk` bytes by closing the socket; `honor_range` = false ignores Range headers.
+    // With an `etag`, a Range whose If-Range doesn't match it gets the whole file.
+    struct FlakyServer {
+        url: String,
+        ranges: Arc<Mutex<Vec<Option<u64>>>>,
+        if_ranges: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    fn start_server(body: Vec<u8>, drops: usize, chunk: usize, honor_range: bool) -> FlakyServer {
+        start_tagged_server(body, None, drops, chunk, honor_range)
+    }
+
+    fn start_tagged_server(body: Vec<u8>, etag: Option<&'static str>, drops: usize, chunk: usize, honor_range: bool) -> FlakyServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/rom.bin", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let if_ranges = Arc::new(Mutex::new(Vec::new()));
+        let (seen, seen_if) = (ranges.clone(), if_ranges.clone());
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let Ok(stream) = stream else { continue };
+                let (range, if_range) = read_request(&stream);
+                seen.lock().unwrap().push(range);
+                seen_if.lock().unwrap().push(if_range.clone());
+                let current = if_range.is_none() || if_range.as_deref() == etag;
+                serve(stream, &body, etag, range.filter(|_| honor_range && current), index < drops, chunk);
+            }
+        });
+        FlakyServer { url, ranges, if_ranges }
+    }
+
+    // Range start and If-Range of a request
+    fn read_request(stream: &TcpStream) -> (Option<u64>, Option<String>) {
+        let mut reader = BufReader::new(stream);
+        let (mut range, mut if_range) = (None, None);
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                range = value.trim().trim_end_matches('-').parse().ok();
+            }
+            if line.to_lowercase().starts_with("if-range:") {
+                if_range = Some(line["if-range:".len()..].trim().to_string());
+            }
+        }
+        (range, if_range)
+    }
+
+    fn serve(mut stream: TcpStream, body: &[u8], etag: Option<&str>, range: Option<u64>, drop_early: bool, chunk: usize) {
+        let start = range.unwrap_or(0) as usize;
+        let etag = etag.map(|e| format!("ETag: {}\r\n", e)).unwrap_or_default();
+        let header = if range.is_some() {
+            format!(
+                "HTTP/1.1 206 Partial Content\r\n{}Content-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                etag, body.len() - start, start, body.len() - 1, body.len()
+            )
+        } else {
+            format!("HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n", etag, body.len())
+        };
+        let _ = stream.write_all(header.as_bytes());
+        let end = if drop_early { (start + chunk).min(body.len()) } else { body.len() };
+        let _ = stream.write_all(&body[start..end]);
+        let _ = stream.flush();
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    // Answers every request with `status` and an empty body
+    fn start_status_server(status: &'static str, headers: &'static str) -> FlakyServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/rom.bin", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                seen.lock().unwrap().push(read_request(&stream).0);
+                let response = format!("HTTP/1.1 {}\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n", status, headers);
+                let _ = stream.write_all(response.as_bytes());
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+            }
+        });
+        FlakyServer { url, ranges, if_ranges: Arc::default() }
+    }
+
+    fn test_body() -> Vec<u8> {
+        (0..256 * 1024u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn fast_retries() -> DownloadOptions {
+        DownloadOptions {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            stall_timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+    }
+
+    fn progress() -> ProgressEvent {
+        ProgressEvent {
+            slug: "test".to_string(),
+            stage: "downloading".to_string(),
+            message: "Downloading...".to_string(),
+            title: None,
+        }
+    }
+
+    fn temp_destination(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-dl-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("rom.bin")
+    }
+
+    #[tokio::test]
+    async fn resumes_after_dropped_connections() {
+        let body = test_body();
+        let server = start_server(body.clone(), 3, 40 * 1024, true);
+        let destination = temp_destination("resume");
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert!(!part_path(&destination).exists());
+        let ranges = server.ranges.lock().unwrap().clone();
+        assert_eq!(ranges, vec![None, Some(40 * 1024), Some(80 * 1024), Some(120 * 1024)]);
+    }
+
+    #[tokio::test]
+    async fn resume_sends_the_saved_etag() {
+        let body = test_body();
+        let server = start_tagged_server(body.clone(), Some("\"v1\""), 1, 40 * 1024, true);
+        let destination = temp_destination("etag");
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert_eq!(*server.if_ranges.lock().unwrap(), vec![None, Some("\"v1\"".to_string())]);
+        assert!(!info_path(&part_path(&destination)).exists());
+    }
+
+    // Seeds a .part holding the first `len` bytes of `old`, as left by an earlier run
+    fn seed_part(destination: &Path, old: &[u8], len: usize, validator: Option<&str>) {
+        let part = part_path(destination);
+        fs::create_dir_all(part.parent().unwrap()).unwrap();
+        fs::write(&part, &old[..len]).unwrap();
+        let info = PartInfo { validator: validator.map(str::to_string), total: old.len() as u64 };
+        write_part_info(&part, &info).unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_etag_downloads_the_new_file() {
+        let old: Vec<u8> = test_body().iter().map(|b| b ^ 0xFF).collect();
+        let body = test_body();
+        let server = start_tagged_server(body.clone(), Some("\"v2\""), 0, 0, true);
+        let destination = temp_destination("etag-changed");
+        seed_part(&destination, &old, 40 * 1024, Some("\"v1\""));
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert_eq!(*server.if_ranges.lock().unwrap(), vec![Some("\"v1\"".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn changed_total_restarts_the_download() {
+        let old: Vec<u8> = (0..100 * 1024u32).map(|i| (i % 7) as u8).collect();
+        let body = test_body();
+        let server = start_server(body.clone(), 0, 0, true);
+        let destination = temp_destination("total-changed");
+        seed_part(&destination, &old, 40 * 1024, None);
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert_eq!(*server.ranges.lock().unwrap(), vec![Some(40 * 1024), None]);
+    }
+
+    #[tokio::test]
+    async fn part_without_info_is_not_resumed() {
+        let body = test_body();
+        let server = start_server(body.clone(), 0, 0, true);
+        let destination = temp_destination("no-info");
+        fs::create_dir_all(destination.parent().unwrap()).unwrap();
+        fs::write(part_path(&destination), vec![0u8; 40 * 1024]).unwrap();
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert_eq!(*server.ranges.lock().unwrap(), vec![None]);
+    }
+
+    #[tokio::test]
+    async fn restarts_when_server_ignores_range() {
+        let body = test_body();
+        let server = start_server(body.clone(), 1, 64 * 1024, false);
+        let destination = temp_destination("norange");
+
+        let path = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn rejects_size_mismatch() {
+        let body = test_body();
+        let server = start_server(body.clone(), 0, 0, true);
+        let destination = temp_destination("size");
+        let options = DownloadOptions {
+            expected_size: Some(body.len() as u64 + 1),
+            ..fast_retries()
+        };
+
+        let result = download_file(&server.url, &destination, &options, &progress(), None).await;
+
+        assert!(result.is_err());
+        assert!(!destination.exists());
+        assert!(!part_path(&destination).exists());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let server = start_server(test_body(), usize::MAX, 0, true);
+        let destination = temp_destination("retries");
+        let options = DownloadOptions { max_retries: 2, ..fast_retries() };
+
+        let result = download_file(&server.url, &destination, &options, &progress(), None).await;
+
+        assert!(result.is_err());
+        assert_eq!(server.ranges.lock().unwrap().len(), 3);
+        assert!(!destination.exists());
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_not_found_without_retrying() {
+        let server = start_status_server("404 Not Found", "");
+        let destination = temp_destination("404");
+
+        let error = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, AppError::NotFound(_)), "{:?}", error);
+        assert!(!error.retryable());
+        assert_eq!(server.ranges.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_survives_the_retries() {
+        let server = start_status_server("429 Too Many Requests", "Retry-After: 120\r\n");
+        let destination = temp_destination("429");
+        let options = DownloadOptions { max_retries: 1, ..fast_retries() };
+
+        let error = download_file(&server.url, &destination, &options, &progress(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, AppError::RateLimited { retry_after: Some(120) }), "{:?}", error);
+        assert!(error.retryable());
+        assert_eq!(server.ranges.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn server_errors_keep_their_status() {
+        let server = start_status_server("503 Service Unavailable", "");
+        let destination = temp_destination("503");
+        let options = DownloadOptions { max_retries: 0, ..fast_retries() };
+
+        let error = download_file(&server.url, &destination, &options, &progress(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, AppError::Http { status: 503, .. }), "{:?}", error);
+        assert!(error.retryable());
+    }
+
+    // /dev/full fails every write with ENOSPC
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn full_disk_is_reported_as_disk_full() {
+        let server = start_server(test_body(), 0, 0, true);
+        let destination = temp_destination("enospc");
+        fs::create_dir_all(destination.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink("/dev/full", part_path(&destination)).unwrap();
+
+        let error = download_file(&server.url, &destination, &fast_retries(), &progress(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, AppError::DiskFull(_)), "{:?}", error);
+        assert!(!destination.exists());
+    }
+}
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
-use sqlx;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Used when an emulator has no arguments template configured
+pub const DEFAULT_ARGUMENTS: &str = "{rom}";
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct EmulatorConfig {
@@ -12,6 +16,7 @@ pub struct EmulatorConfig {
 }
 
 impl EmulatorConfig {
+    #[allow(dead_code)]
     pub fn new(
         id: String,
         name: String,
@@ -28,15 +33,164 @@ impl EmulatorConfig {
         }
     }
 
+    #[allow(dead_code)]
     pub fn with_arguments(mut self, arguments: String) -> Self {
         self.arguments = Some(arguments);
         self
     }
 
+    #[allow(dead_code)]
     pub fn with_icon(mut self, icon_path: String) -> Self {
         self.icon_path = Some(icon_path);
         self
     }
+
+    // Build the argv (without the executable) for launching a ROM
+    pub fn launch_arguments(&self, rom_path: &Path, fullscreen: bool) -> Vec<String> {
+        let template = self.arguments.as_deref()
+            .filter(|a| !a.trim().is_empty())
+            .unwrap_or(DEFAULT_ARGUMENTS);
+        expand_arguments_template(template, rom_path, fullscreen)
+    }
+
+    // Build the launch command as a plain argv; no shell is involved, so ROM paths
+    // containing quotes, `$()` or backticks reach the emulator untouched
+    pub fn launch_command(&self, rom_path: &Path, fullscreen: bool) -> Command {
+        let executable = resolve_executable(&self.executable_path);
+        let mut cmd = Command::new(&executable);
+        cmd.args(self.launch_arguments(rom_path, fullscreen));
+        // Portable emulators often look for their config next to the executable
+        if let Some(dir) = executable.parent().filter(|d| !d.as_os_str().is_empty()) {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+// Expand an arguments template into argv. The template is split into words first
+// (single/double quotes group words), then placeholders are substituted inside each word,
+// so a ROM path containing spaces always stays a single argument.
+//
+// Placeholders:
+//   {rom}               full ROM path
+//   {rom_dir}           directory containing the ROM
+//   {rom_stem}          ROM file name without extension
+//   {fullscreen}        "--fullscreen" when fullscreen is enabled, dropped otherwise
+//   {fullscreen:FLAG}   FLAG when fullscreen is enabled (e.g. {fullscreen:-f}), dropped otherwise
+//
+// If the template never references the ROM, its path is appended as the last argument.
+pub fn expand_arguments_template(template: &str, rom_path: &Path, fullscreen: bool) -> Vec<String> {
+    let rom = rom_path.to_string_lossy().to_string();
+    let rom_dir = rom_path.parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let rom_stem = rom_path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut uses_rom = false;
+    let mut args = Vec::new();
+    for word in split_arguments(template) {
+        if word.contains("{rom}") || word.contains("{rom_dir}") || word.contains("{rom_stem}") {
+            uses_rom = true;
+        }
+
+        // One pass over the template word, so braces in the ROM path are never expanded
+        let mut expanded = String::new();
+        let mut rest = word.as_str();
+        while let Some(start) = rest.find('{') {
+            expanded.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            let Some(end) = tail.find('}') else {
+                rest = tail;
+                break;
+            };
+            let value = match &tail[1..end] {
+                "rom" => Some(rom.as_str()),
+                "rom_dir" => Some(rom_dir.as_str()),
+                "rom_stem" => Some(rom_stem.as_str()),
+                "fullscreen" => Some(if fullscreen { "--fullscreen" } else { "" }),
+                name => name.strip_prefix("fullscreen:").map(|flag| if fullscreen { flag } else { "" }),
+            };
+            match value {
+                Some(value) => {
+                    expanded.push_str(value);
+                    rest = &tail[end + 1..];
+                }
+                None => {
+                    expanded.push('{');
+                    rest = &tail[1..];
+                }
+            }
+        }
+        expanded.push_str(rest);
+
+        // A word made only of a disabled {fullscreen} disappears instead of becoming ""
+        if !expanded.is_empty() || !word.starts_with("{fullscreen") {
+            args.push(expanded);
+        }
+    }
+
+    if !uses_rom {
+        args.push(rom);
+    }
+
+    args
+}
+
+// Split a command line into words, honoring single and double quotes (no shell expansion)
+pub fn split_arguments(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+
+    for c in input.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        words.push(current);
+    }
+
+    words
+}
+
+// macOS emulators are registered as .app bundles; run the binary inside the bundle
+pub fn resolve_executable(executable_path: &str) -> PathBuf {
+    let path = PathBuf::from(executable_path);
+    if path.is_dir() && path.extension().map(|e| e == "app").unwrap_or(false) {
+        let macos_dir = path.join("Contents").join("MacOS");
+        if let Some(stem) = path.file_stem() {
+            let candidate = macos_dir.join(stem);
+            if candidate.is_file() {
+                return candidate;
+            }
+        }
+        if let Ok(entries) = std::fs::read_dir(&macos_dir) {
+            if let Some(first) = entries.flatten().map(|e| e.path()).find(|p| p.is_file()) {
+                return first;
+            }
+        }
+    }
+    path
 }
 
 // Recommended emulators with download information
@@ -58,6 +212,8 @@ pub struct RecommendedEmulator {
     pub executable_name_linux: Option<String>,
     pub logo_url: Option<String>,
     pub install_instructions: String,
+    // Arguments template stored on the EmulatorConfig when installed (see expand_arguments_template)
+    pub default_arguments: Option<String>,
 }
 
 pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
@@ -98,6 +254,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("retroarch".to_string()),
             logo_url: Some("https://www.retroarch.com/images/retroarch-logo.png".to_string()),
             install_instructions: "Download the Windows installer or portable version, extract if needed, and point to retroarch.exe".to_string(),
+            default_arguments: None,
         },
         // PPSSPP - PSP
         RecommendedEmulator {
@@ -117,6 +274,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("PPSSPPSDL".to_string()),
             logo_url: None,
             install_instructions: "Download the Windows version, extract the ZIP, and point to PPSSPPWindows64.exe".to_string(),
+            default_arguments: Some("{fullscreen} {rom}".to_string()),
         },
         // Dolphin - GameCube/Wii
         RecommendedEmulator {
@@ -136,6 +294,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("dolphin-emu".to_string()),
             logo_url: None,
             install_instructions: "Download the latest beta version, extract the 7z archive, and point to Dolphin.exe".to_string(),
+            default_arguments: Some("-b {fullscreen:--config=Dolphin.Display.Fullscreen=True} -e {rom}".to_string()),
         },
         // PCSX2 - PlayStation 2
         RecommendedEmulator {
@@ -155,6 +314,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("pcsx2-qt".to_string()),
             logo_url: None,
             install_instructions: "Download the Windows installer or portable version, extract if needed, and point to pcsx2-qt.exe".to_string(),
+            default_arguments: Some("{fullscreen:-fullscreen} -- {rom}".to_string()),
         },
         // mGBA - Game Boy Advance
         RecommendedEmulator {
@@ -174,6 +334,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("mgba".to_string()),
             logo_url: None,
             install_instructions: "Download the Windows build, extract the 7z archive, and point to mGBA.exe".to_string(),
+            default_arguments: Some("{fullscreen:-f} {rom}".to_string()),
         },
         // DeSmuME - Nintendo DS
         RecommendedEmulator {
@@ -193,6 +354,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("desmume".to_string()),
             logo_url: None,
             install_instructions: "Download the Windows x64 version, extract the ZIP, and point to DeSmuME.exe".to_string(),
+            default_arguments: Some("{rom}".to_string()),
         },
         // Cemu - Wii U
         RecommendedEmulator {
@@ -212,6 +374,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("Cemu.AppImage".to_string()),
             logo_url: None,
             install_instructions: "Download the latest Windows version, extract the ZIP, and point to Cemu.exe. Note: Requires powerful hardware".to_string(),
+            default_arguments: Some("{fullscreen:-f} -g {rom}".to_string()),
         },
         // Citra - Nintendo 3DS
         RecommendedEmulator {
@@ -231,6 +394,7 @@ pub fn get_recommended_emulators() -> Vec<RecommendedEmulator> {
             executable_name_linux: Some("citra-qt".to_string()),
             logo_url: None,
             install_instructions: "Download Citra Nightly, extract the archive, and point to citra-qt.exe".to_string(),
+            default_arguments: Some("{fullscreen:-f} {rom}".to_string()),
         },
     ]
 }
@@ -241,9 +405,80 @@ pub fn get_recommended_emulator_by_id(id: &str) -> Option<RecommendedEmulator> {
         .find(|e| e.id == id)
 }
 
+#[allow(dead_code)]
 pub fn get_recommended_emulators_for_platform(platform: &str) -> Vec<RecommendedEmulator> {
     get_recommended_emulators()
         .into_iter()
         .filter(|e| e.platforms.iter().any(|p| p.to_lowercase() == platform.to_lowercase()))
         .collect()
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_and_capture_args, write_stub_emulator};
+
+    #[test]
+    fn launch_command_passes_hostile_rom_paths_verbatim() {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-emu-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stub = write_stub_emulator(&dir);
+        let emulator = EmulatorConfig::new(
+            "stub".to_string(),
+            "Stub".to_string(),
+            "snes".to_string(),
+            stub.to_string_lossy().to_string(),
+        ).with_arguments("{fullscreen:-f} --rom {rom}".to_string());
+
+        let names = [
+            "My \"Quoted\" Game.sfc",
+            "Game With  Spaces (USA).sfc",
+            "$(touch pwned).sfc",
+            "`touch pwned`; rm -rf x.sfc",
+            "ドラゴンクエスト V 天空の花嫁.sfc",
+        ];
+        for name in names {
+            let rom = dir.join(name);
+            let args = run_and_capture_args(emulator.launch_command(&rom, true), &dir);
+            assert_eq!(args, vec!["-f".to_string(), "--rom".to_string(), rom.to_string_lossy().to_string()]);
+        }
+        assert!(!dir.join("pwned").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn arguments_template_keeps_rom_path_as_one_argument() {
+        let rom = Path::new("/roms/My Game (USA).iso");
+        assert_eq!(
+            expand_arguments_template("-b {fullscreen:--batch-fs} -e {rom}", rom, false),
+            vec!["-b", "-e", "/roms/My Game (USA).iso"]
+        );
+        assert_eq!(
+            expand_arguments_template("{fullscreen} --save \"{rom_dir}/saves/{rom_stem}.sav\"", rom, true),
+            vec!["--fullscreen", "--save", "/roms/saves/My Game (USA).sav"]
+        );
+        assert_eq!(
+            expand_arguments_template("--nogui", rom, true),
+            vec!["--nogui", "/roms/My Game (USA).iso"]
+        );
+    }
+
+    #[test]
+    fn placeholders_inside_the_rom_path_are_not_expanded() {
+        let rom = Path::new("/roms/{rom_stem}/{fullscreen} {fullscreen:-x} {rom}.iso");
+        assert_eq!(
+            expand_arguments_template("{fullscreen:-f} {rom}", rom, true),
+            vec!["-f", "/roms/{rom_stem}/{fullscreen} {fullscreen:-x} {rom}.iso"]
+        );
+        assert_eq!(
+            expand_arguments_template("{fullscreen} --dir={rom_dir} --name={rom_stem}", rom, false),
+            vec!["--dir=/roms/{rom_stem}", "--name={fullscreen} {fullscreen:-x} {rom}"]
+        );
+        // Unknown or unclosed braces are kept as written
+        assert_eq!(
+            expand_arguments_template("--set={other} {rom} --open{", rom, false),
+            vec!["--set={other}", "/roms/{rom_stem}/{fullscreen} {fullscreen:-x} {rom}.iso", "--open{"]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+// Errors returned by Tauri commands. They reach the frontend as
+// {kind, message, retryable, details} so the UI can tell a rate limit from a missing
+// game or a full disk.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Too many requests, try again {}", retry_hint(*.retry_after))]
+    RateLimited { retry_after: Option<u64> },
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Server returned HTTP {status} for {url}")]
+    Http { status: u16, url: String },
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Unexpected response: {0}")]
+    Parse(String),
+    #[error("Not enough disk space: {0}")]
+    DiskFull(String),
+    #[error("File error: {0}")]
+    Io(String),
+    #[error("Database error: {message}")]
+    Database { message: String, retryable: bool },
+    #[error("{0}")]
+    RetroArch(String),
+    #[error("{0}")]
+    Install(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+fn retry_hint(retry_after: Option<u64>) -> String {
+    match retry_after {
+        Some(secs) => format!("in {} seconds", secs),
+        None => "later".to_string(),
+    }
+}
+
+impl AppError {
+    // 404 and 429 get their own kinds; other statuses keep the code
+    pub fn from_status(status: reqwest::StatusCode, url: &str, retry_after: Option<u64>) -> Self {
+        match status {
+            reqwest::StatusCode::NOT_FOUND => AppError::NotFound(format!("Nothing found at {}", url)),
+            reqwest::StatusCode::TOO_MANY_REQUESTS => AppError::RateLimited { retry_after },
+            _ => AppError::Http { status: status.as_u16(), url: url.to_string() },
+        }
+    }
+
+    // Error for a response with a non-success status, honoring Retry-After (in seconds)
+    pub fn from_response(response: &reqwest::Response) -> Self {
+        let retry_after = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok());
+        AppError::from_status(response.status(), response.url().as_str(), retry_after)
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::NotFound(_) => "not_found",
+            AppError::Http { .. } => "http",
+            AppError::Network(_) => "network",
+            AppError::Parse(_) => "parse",
+            AppError::DiskFull(_) => "disk_full",
+            AppError::Io(_) => "io",
+            AppError::Database { .. } => "database",
+            AppError::RetroArch(_) => "retroarch",
+            AppError::Install(_) => "install",
+            AppError::Other(_) => "other",
+        }
+    }
+
+    // Whether the same call may succeed if tried again later
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::RateLimited { .. } | AppError::Network(_) => true,
+            AppError::Http { status, .. } => *status >= 500 || *status == 408,
+            AppError::Database { retryable, .. } => *retryable,
+            _ => false,
+        }
+    }
+
+    pub fn details(&self) -> serde_json::Value {
+        match self {
+            AppError::RateLimited { retry_after } => serde_json::json!({ "retry_after_secs": retry_after }),
+            AppError::Http { status, url } => serde_json::json!({ "status": status, "url": url }),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+// ENOSPC / EDQUOT on Unix, ERROR_HANDLE_DISK_FULL / ERROR_DISK_FULL on Windows
+fn is_disk_full(e: &std::io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(code) if cfg!(windows) => code == 39 || code == 112,
+        Some(code) => code == 28 || code == 122,
+        None => false,
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        if is_disk_full(&e) {
+            AppError::DiskFull(e.to_string())
+        } else if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::NotFound(e.to_string())
+        } else {
+            AppError::Io(e.to_string())
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if let Some(status) = e.status() {
+            let url = e.url().map(|u| u.to_string()).unwrap_or_default();
+            return AppError::from_status(status, &url, None);
+        }
+        if e.is_decode() {
+            AppError::Parse(e.to_string())
+        } else if e.is_builder() {
+            AppError::Other(e.to_string())
+        } else {
+            AppError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        let retryable = match &e {
+            sqlx::Error::PoolTimedOut => true,
+            // SQLITE_BUSY / SQLITE_LOCKED: another connection holds the lock
+            sqlx::Error::Database(db) => matches!(db.code().as_deref(), Some("5") | Some("6")),
+            _ => false,
+        };
+        match e {
+            sqlx::Error::RowNotFound => AppError::NotFound("Not found in the library".to_string()),
+            e => AppError::Database { message: e.to_string(), retryable },
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::Other(e.to_string())
+    }
+}
+
+// Helpers that still return Box<dyn Error> keep their kind when the boxed error is one
+// we know
+impl From<Box<dyn std::error::Error>> for AppError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<AppError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<sqlx::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        match e.downcast::<serde_json::Error>() {
+            Ok(e) => (*e).into(),
+            Err(e) => AppError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
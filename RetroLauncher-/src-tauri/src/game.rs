@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use sqlx::FromRow;
 
+use crate::collection::{RuleField, RuleOp, SmartRule, SmartRules};
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Game {
     pub id: String,
@@ -20,6 +23,38 @@ pub struct Game {
     #[sqlx(default)]
     pub total_playtime: i64,
     pub last_played: Option<String>,
+    // RetroArch core id to use instead of the platform default
+    #[sqlx(default)]
+    #[serde(default)]
+    pub core_override: Option<String>,
+    // ROM hashes and the No-Intro / Redump match, filled in by DAT verification.
+    // verified is 1 when the hashes match an imported DAT entry.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub crc32: Option<String>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub md5: Option<String>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub sha1: Option<String>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub verified: i64,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub region: Option<String>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub revision: Option<String>,
+    // Game this one is a patched copy of (translation, hack...)
+    #[sqlx(default)]
+    #[serde(default)]
+    pub base_game_id: Option<String>,
+    // IPS/UPS/BPS patch RetroArch applies at launch; rom_path is then the base game's ROM
+    #[sqlx(default)]
+    #[serde(default)]
+    pub soft_patch: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -29,10 +64,12 @@ pub struct GameStats {
     pub last_played: Option<String>,
 }
 
+#[allow(dead_code)]
 pub struct GameLibrary {
     pub games: Vec<Game>,
 }
 
+#[allow(dead_code)]
 impl GameLibrary {
     pub fn new() -> Self {
         GameLibrary { games: Vec::new() }
@@ -54,3 +91,171 @@ impl GameLibrary {
             .collect()
     }
 }
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    // Best full-text match first; falls back to title without a search text
+    Relevance,
+    Title,
+    Platform,
+    LastPlayed,
+    Playtime,
+    PlayCount,
+    Added,
+}
+
+// A page of the library, matched against the full-text index when `query` is set
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LibrarySearch {
+    pub query: Option<String>,
+    pub platform: Option<String>,
+    // Matches genres containing the text, e.g. "RPG" for "Action RPG"
+    pub genre: Option<String>,
+    pub tag: Option<String>,
+    pub favorite: Option<bool>,
+    pub verified: Option<bool>,
+    // Relevance when searching, title otherwise
+    pub sort: Option<LibrarySort>,
+    // Title and platform sort ascending by default, everything else descending
+    pub descending: Option<bool>,
+    pub offset: i64,
+    pub limit: Option<i64>,
+}
+
+// A page of the library without full-text search, for browsing views
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GameQuery {
+    pub platform: Option<String>,
+    pub favorite: Option<bool>,
+    pub sort: Option<LibrarySort>,
+    pub descending: Option<bool>,
+    pub offset: i64,
+    pub limit: Option<i64>,
+}
+
+impl From<&GameQuery> for LibrarySearch {
+    fn from(query: &GameQuery) -> Self {
+        LibrarySearch {
+            platform: query.platform.clone(),
+            favorite: query.favorite,
+            sort: query.sort,
+            descending: query.descending,
+            offset: query.offset,
+            limit: query.limit,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryPage {
+    pub games: Vec<Game>,
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LibraryPlatform {
+    pub platform: String,
+    pub game_count: i64,
+}
+
+impl LibrarySearch {
+    pub fn text(&self) -> Option<&str> {
+        self.query.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    pub fn sort(&self) -> LibrarySort {
+        match self.sort {
+            Some(LibrarySort::Relevance) | None if self.text().is_some() => LibrarySort::Relevance,
+            Some(LibrarySort::Relevance) | None => LibrarySort::Title,
+            Some(sort) => sort,
+        }
+    }
+
+    pub fn descending(&self) -> bool {
+        self.descending.unwrap_or(!matches!(self.sort(), LibrarySort::Title | LibrarySort::Platform))
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.max(0)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    // The filters expressed as smart collection rules, all of which must match
+    pub fn filter_rules(&self) -> SmartRules {
+        let mut rules = Vec::new();
+        // Blank filters are unset, not rules that match nothing
+        let mut push = |field, op, value: Option<JsonValue>| {
+            if let Some(value) = value.filter(|v| !v.as_str().is_some_and(|s| s.trim().is_empty())) {
+                rules.push(SmartRule { field, op, value });
+            }
+        };
+
+        push(RuleField::Platform, RuleOp::Eq, self.platform.clone().map(JsonValue::from));
+        push(RuleField::Genre, RuleOp::Contains, self.genre.clone().map(JsonValue::from));
+        push(RuleField::Tag, RuleOp::Eq, self.tag.clone().map(JsonValue::from));
+        push(RuleField::Favorite, RuleOp::Eq, self.favorite.map(JsonValue::from));
+        push(RuleField::Verified, RuleOp::Eq, self.verified.map(JsonValue::from));
+
+        SmartRules { rules, ..Default::default() }
+    }
+}
+
+// A single play session recorded by the process supervisor
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PlaySession {
+    pub id: i64,
+    pub game_id: String,
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration: i64,
+    pub core: Option<String>,
+    pub exit_status: String,
+    pub exit_code: Option<i32>,
+}
+
+// Session data handed to the database when a supervised game exits
+#[derive(Debug, Clone)]
+pub struct NewPlaySession {
+    pub game_id: String,
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration: i64,
+    pub core: Option<String>,
+    pub exit_status: String,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PeriodPlaytime {
+    pub period: String,
+    pub total_seconds: i64,
+    pub sessions: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PlatformPlaytime {
+    pub platform: String,
+    pub total_seconds: i64,
+    pub sessions: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GamePlaytime {
+    pub game_id: String,
+    pub title: String,
+    pub platform: String,
+    pub total_seconds: i64,
+    pub sessions: i64,
+}
@@ -4,17 +4,35 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 use tauri::Manager;
-use std::path::PathBuf;
 
+mod archive;
+mod bios;
+mod collection;
+mod dat;
 mod database;
+mod download_queue;
+mod downloader;
 mod emulator;
+mod error;
 mod crocdb;
 mod game;
+mod migrations;
+mod multidisc;
+mod patch;
+mod process;
 mod retroarch;
+mod romid;
+mod scanner;
+#[cfg(test)]
+mod test_support;
 
+use collection::{Collection, SmartRules, Tag};
 use database::Database;
+use download_queue::DownloadQueue;
+use error::AppError;
 use emulator::EmulatorConfig;
-use game::Game;
+use game::{Game, GameQuery, LibraryPage, LibraryPlatform, LibrarySearch};
+use process::ProcessSupervisor;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppState {
@@ -23,9 +41,26 @@ pub struct AppState {
 
 // Game launching command
 #[tauri::command]
-async fn launch_game(game_id: String, state: tauri::State<'_, Database>, app_handle: tauri::AppHandle) -> Result<String, String> {
-    let game = state.get_game(&game_id).await
-        .map_err(|e| e.to_string())?;
+async fn launch_game(
+    game_id: String,
+    state: tauri::State<'_, Database>,
+    supervisor: tauri::State<'_, ProcessSupervisor>,
+    app_handle: tauri::AppHandle
+) -> Result<String, AppError> {
+    let game = state.get_game(&game_id).await?;
+
+    if supervisor.is_running(&game_id) {
+        return Err(format!("'{}' is already running", game.title).into());
+    }
+
+    let fullscreen = state.get_setting("launch_fullscreen").await?
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+
+    // Games assigned to a standalone emulator bypass RetroArch entirely
+    if !game.emulator_id.is_empty() && game.emulator_id != "retroarch" {
+        return launch_with_emulator(&game, fullscreen, &state, &supervisor).await;
+    }
 
     // Get app directory for RetroArch path
     let app_dir = app_handle.path_resolver()
@@ -33,81 +68,195 @@ async fn launch_game(game_id: String, state: tauri::State<'_, Database>, app_han
         .ok_or("Failed to get app data directory")?;
 
     let retroarch_path = app_dir.join("retroarch");
-    let retroarch_exe = retroarch_path.join("retroarch.exe");
+    let retroarch_exe = retroarch::retroarch_executable(&retroarch_path);
 
     if !retroarch_exe.exists() {
-        return Err("RetroArch is not installed. Please install it from the Emulators tab.".to_string());
+        return Err(AppError::RetroArch("RetroArch is not installed. Please install it from the Emulators tab.".to_string()));
     }
 
-    // Get any installed core for the platform (preferred: recommended, fallback: any)
-    let core = retroarch::get_any_installed_core_for_platform(&game.platform, &retroarch_path)
-        .ok_or(format!(
-            "No core installed for platform '{}'. Please install a core from the Emulators tab.",
-            game.platform
-        ))?;
+    // Resolve the core: per-game override, then platform default, then recommended/any installed
+    let platform_default = state.get_platform_default_core(&game.platform).await?;
+    let resolution = retroarch::resolve_core_for_game(
+        &game.platform,
+        &retroarch_path,
+        game.core_override.as_deref(),
+        platform_default.as_deref(),
+    )?;
+    let core = resolution.core;
 
     let core_path = retroarch_path.join("cores").join(&core.filename);
 
     if !core_path.exists() {
-        return Err(format!("Core '{}' is not installed. Please install it from the Emulators tab.", core.name));
+        return Err(AppError::RetroArch(format!("Core '{}' is not installed. Please install it from the Emulators tab.", core.name)));
     }
 
-    // Update play count and last played
-    state.update_game_stats(&game_id).await
-        .map_err(|e| e.to_string())?;
+    // Cores without their BIOS fail to start without saying why; stop before launching
+    let system_dir = bios::system_dir(&retroarch_path);
+    let missing = bios::missing_for_launch(&game.platform, &core.id, std::path::Path::new(&game.rom_path), &system_dir);
+    if let Some(req) = missing.first() {
+        let files: Vec<&str> = req.files.iter().map(|f| f.filename).collect();
+        return Err(AppError::RetroArch(format!(
+            "{} needs the {}, which is missing. Place {}{} in {} or import it from your BIOS folder.",
+            core.name,
+            req.description,
+            if files.len() > 1 { "one of " } else { "" },
+            files.join(", "),
+            system_dir.display()
+        )));
+    }
 
     // Launch game with RetroArch
-    #[cfg(target_os = "windows")]
-    {
-        let mut cmd = Command::new(&retroarch_exe);
-        // Fullscreen
-        cmd.arg("--fullscreen");
-        cmd.arg("-L");
-        cmd.arg(core_path);
-        cmd.arg(&game.rom_path);
-
-        cmd.spawn().map_err(|e| format!("Failed to launch game: {}", e))?;
+    let cmd = retroarch::launch_command(
+        &retroarch_exe,
+        &core_path,
+        std::path::Path::new(&game.rom_path),
+        game.soft_patch.as_deref().map(std::path::Path::new),
+        fullscreen,
+    );
+
+    // The supervisor keeps the child and records playtime when it exits
+    supervisor.spawn(&game.id, &game.title, Some(core.id.clone()), cmd)?;
+
+    // Update play count and last played once the game is actually running
+    state.update_game_stats(&game_id).await?;
+
+    Ok(format!("Launched: {} with {} ({})", game.title, core.name, resolution.rule.describe()))
+}
+
+// Launch a game through the standalone emulator registered under game.emulator_id
+async fn launch_with_emulator(
+    game: &Game,
+    fullscreen: bool,
+    state: &Database,
+    supervisor: &ProcessSupervisor,
+) -> Result<String, AppError> {
+    let emulator = state.get_emulator(&game.emulator_id).await
+        .map_err(|_| AppError::NotFound(format!(
+            "Emulator '{}' is not registered. Please install or add it from the Emulators tab.",
+            game.emulator_id
+        )))?;
+
+    if game.soft_patch.is_some() {
+        return Err(format!(
+            "'{}' is soft-patched, which only RetroArch supports. Launch it with RetroArch or apply the patch to a copy instead.",
+            game.title
+        ).into());
     }
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c");
-        let launch_cmd = format!("\"{}\" --fullscreen -L \"{}\" \"{}\"",
-            retroarch_exe.to_string_lossy(),
-            core_path.to_string_lossy(),
-            game.rom_path
-        );
-        cmd.arg(launch_cmd);
-        cmd.spawn().map_err(|e| format!("Failed to launch game: {}", e))?;
+    let executable = emulator::resolve_executable(&emulator.executable_path);
+    if !executable.exists() {
+        return Err(AppError::NotFound(format!("Emulator executable not found: {}", executable.display())));
     }
 
-    Ok(format!("Launched: {}", game.title))
+    let cmd = emulator.launch_command(std::path::Path::new(&game.rom_path), fullscreen);
+
+    supervisor.spawn(&game.id, &game.title, Some(emulator.id.clone()), cmd)?;
+
+    // Update play count and last played once the game is actually running
+    state.update_game_stats(&game.id).await?;
+
+    Ok(format!("Launched: {} with {}", game.title, emulator.name))
+}
+
+// Assign the emulator used to launch a game ("retroarch" or an id from the emulators table)
+#[tauri::command]
+async fn set_game_emulator(game_id: String, emulator_id: String, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    if emulator_id != "retroarch" {
+        state.get_emulator(&emulator_id).await
+            .map_err(|_| AppError::NotFound(format!("Emulator '{}' is not registered", emulator_id)))?;
+    }
+    state.set_game_emulator(&game_id, &emulator_id).await.map_err(AppError::from)
+}
+
+// Preview which core a launch would use, and why
+#[tauri::command]
+async fn resolve_game_core(
+    game_id: String,
+    state: tauri::State<'_, Database>,
+    app_handle: tauri::AppHandle
+) -> Result<retroarch::CoreResolution, AppError> {
+    let game = state.get_game(&game_id).await?;
+
+    let app_dir = app_handle.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to get app data directory")?;
+
+    let platform_default = state.get_platform_default_core(&game.platform).await?;
+
+    retroarch::resolve_core_for_game(
+        &game.platform,
+        &app_dir.join("retroarch"),
+        game.core_override.as_deref(),
+        platform_default.as_deref(),
+    )
+}
+
+// Set or clear (None) the core used for a single game
+#[tauri::command]
+async fn set_game_core_override(game_id: String, core_id: Option<String>, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.set_core_override(&game_id, core_id.as_deref()).await.map_err(AppError::from)
+}
+
+// Set or clear (None) the default core for every game of a platform
+#[tauri::command]
+async fn set_platform_default_core(platform: String, core_id: Option<String>, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.set_platform_default_core(&platform, core_id.as_deref()).await.map_err(AppError::from)
+}
+
+// List games whose process is still running
+#[tauri::command]
+async fn get_running_games(supervisor: tauri::State<'_, ProcessSupervisor>) -> Result<Vec<process::RunningGame>, AppError> {
+    Ok(supervisor.running_games())
+}
+
+// Stop a running game (playtime is recorded when the process exits)
+#[tauri::command]
+async fn stop_game(game_id: String, supervisor: tauri::State<'_, ProcessSupervisor>) -> Result<(), AppError> {
+    Ok(supervisor.stop(&game_id)?)
 }
 
 // Get all games from library
 #[tauri::command]
-async fn get_games(state: tauri::State<'_, Database>) -> Result<Vec<Game>, String> {
-    state.get_all_games().await.map_err(|e| e.to_string())
+async fn get_games(state: tauri::State<'_, Database>) -> Result<Vec<Game>, AppError> {
+    state.get_all_games().await.map_err(AppError::from)
+}
+
+// One page of the library, filtered by platform and favorite
+#[tauri::command]
+async fn query_games(query: GameQuery, state: tauri::State<'_, Database>) -> Result<LibraryPage, AppError> {
+    state.query_games(&query).await.map_err(AppError::from)
+}
+
+// Platforms present in the library, with their game counts
+#[tauri::command]
+async fn get_library_platforms(state: tauri::State<'_, Database>) -> Result<Vec<LibraryPlatform>, AppError> {
+    state.get_library_platforms().await.map_err(AppError::from)
+}
+
+// One page of the library, full-text searched over title, description, developer, genre and tags
+#[tauri::command]
+async fn search_library(search: LibrarySearch, state: tauri::State<'_, Database>) -> Result<LibraryPage, AppError> {
+    state.search_library(&search).await.map_err(AppError::from)
 }
 
 // Delete a game
 #[tauri::command]
-async fn delete_game(game_id: String, state: tauri::State<'_, Database>) -> Result<(), String> {
-    state.delete_game(&game_id).await.map_err(|e| e.to_string())
+async fn delete_game(game_id: String, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.delete_game(&game_id).await.map_err(AppError::from)
 }
 
 // Add game to library
 #[tauri::command]
-async fn add_game(game: Game, state: tauri::State<'_, Database>) -> Result<String, String> {
-    state.add_game(game).await.map_err(|e| e.to_string())?;
+async fn add_game(game: Game, state: tauri::State<'_, Database>) -> Result<String, AppError> {
+    state.add_game(game).await?;
     Ok("Game added successfully".to_string())
 }
 
 // Search CrocDB
 #[tauri::command]
-async fn search_crocdb(query: String) -> Result<Vec<crocdb::CrocDBGame>, String> {
-    crocdb::search_games(&query).await.map_err(|e| e.to_string())
+async fn search_crocdb(query: String, state: tauri::State<'_, Database>) -> Result<Vec<crocdb::CrocDBGame>, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::search_games(&client, &query).await
 }
 
 // Search CrocDB with advanced filters
@@ -118,113 +267,275 @@ async fn search_crocdb_advanced(
     regions: Option<Vec<String>>,
     max_results: Option<i32>,
     page: Option<i32>,
-) -> Result<crocdb::SearchResultsData, String> {
-    crocdb::search_games_advanced(search_key, platforms, regions, max_results, page)
+    state: tauri::State<'_, Database>
+) -> Result<crocdb::SearchResultsData, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::search_games_advanced(&client, &state, search_key, platforms, regions, max_results, page)
         .await
-        .map_err(|e| e.to_string())
 }
 
 // Get specific CrocDB entry by slug
 #[tauri::command]
-async fn get_crocdb_entry(slug: String) -> Result<crocdb::CrocDBGame, String> {
-    crocdb::get_entry(&slug).await.map_err(|e| e.to_string())
+async fn get_crocdb_entry(slug: String, state: tauri::State<'_, Database>) -> Result<crocdb::CrocDBGame, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::get_entry(&client, &state, &slug).await
 }
 
 // Get random CrocDB entry
 #[tauri::command]
-async fn get_random_crocdb_entry() -> Result<crocdb::CrocDBGame, String> {
-    crocdb::get_random_entry().await.map_err(|e| e.to_string())
+async fn get_random_crocdb_entry(state: tauri::State<'_, Database>) -> Result<crocdb::CrocDBGame, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::get_random_entry(&client).await
 }
 
 // Get available platforms from CrocDB
 #[tauri::command]
-async fn get_crocdb_platforms() -> Result<std::collections::HashMap<String, crocdb::PlatformInfo>, String> {
-    crocdb::get_platforms().await.map_err(|e| e.to_string())
+async fn get_crocdb_platforms(state: tauri::State<'_, Database>) -> Result<std::collections::HashMap<String, crocdb::PlatformInfo>, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::get_platforms(&client, &state).await
 }
 
 // Get available regions from CrocDB
 #[tauri::command]
-async fn get_crocdb_regions() -> Result<std::collections::HashMap<String, String>, String> {
-    crocdb::get_regions().await.map_err(|e| e.to_string())
+async fn get_crocdb_regions(state: tauri::State<'_, Database>) -> Result<std::collections::HashMap<String, String>, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::get_regions(&client, &state).await
 }
 
 // Get CrocDB database info
 #[tauri::command]
-async fn get_crocdb_info() -> Result<crocdb::DatabaseInfo, String> {
-    crocdb::get_database_info().await.map_err(|e| e.to_string())
+async fn get_crocdb_info(state: tauri::State<'_, Database>) -> Result<crocdb::DatabaseInfo, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    crocdb::get_database_info(&client).await
+}
+
+// Drop every cached CrocDB response; returns how many were removed
+#[tauri::command]
+async fn clear_crocdb_cache(state: tauri::State<'_, Database>) -> Result<u64, AppError> {
+    state.clear_response_cache().await.map_err(AppError::from)
 }
 
 // Download from CrocDB
 #[tauri::command]
-async fn download_from_crocdb(slug: String, destination: String) -> Result<String, String> {
-    crocdb::download_game(&slug, &destination).await.map_err(|e| e.to_string())
+async fn download_from_crocdb(
+    slug: String,
+    destination: String,
+    link: Option<crocdb::LinkSelector>,
+    state: tauri::State<'_, Database>
+) -> Result<String, AppError> {
+    let client = crocdb::CrocDbClient::from_settings(&state).await?;
+    let prefs = state.get_link_preferences().await?;
+    crocdb::download_game(&client, &state, &slug, &destination, link.as_ref(), &prefs).await
 }
 
-// Install game from CrocDB (download, extract, add to library)
+// Install game from CrocDB (download, extract, add to library). The install runs on
+// the download queue; progress and completion arrive as "download-progress" events.
+// `link` picks one of the entry's download links; otherwise the "link_preferences"
+// setting ranks them.
 #[tauri::command]
 async fn install_game_from_crocdb(
     slug: String,
+    link: Option<crocdb::LinkSelector>,
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<String, AppError> {
+    let item = queue.enqueue(&slug, link).await?;
+    Ok(format!("'{}' added to the download queue", item.title))
+}
+
+// Download queue
+#[tauri::command]
+async fn list_downloads(
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<Vec<download_queue::DownloadItem>, AppError> {
+    queue.list().await
+}
+
+#[tauri::command]
+async fn pause_download(
+    slug: String,
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<(), AppError> {
+    queue.pause(&slug).await
+}
+
+#[tauri::command]
+async fn resume_download(
+    slug: String,
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<(), AppError> {
+    queue.resume(&slug).await
+}
+
+#[tauri::command]
+async fn cancel_download(
+    slug: String,
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<(), AppError> {
+    queue.cancel(&slug).await
+}
+
+// Import ROMs already on disk (recursive), emitting progress on "download-progress"
+#[tauri::command]
+async fn scan_directory(
+    path: String,
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, Database>
-) -> Result<String, String> {
-    // Get app data directory for installation
+) -> Result<scanner::ScanSummary, AppError> {
+    scanner::scan_directory(std::path::Path::new(&path), &state, Some(&app_handle))
+        .await
+        .map_err(AppError::from)
+}
+
+// No-Intro / Redump DAT files
+#[tauri::command]
+async fn import_dat_file(
+    path: String,
+    state: tauri::State<'_, Database>
+) -> Result<dat::DatFile, AppError> {
+    dat::import_dat_file(&state, std::path::Path::new(&path))
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_dat_files(state: tauri::State<'_, Database>) -> Result<Vec<dat::DatFile>, AppError> {
+    state.get_dat_files().await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn delete_dat_file(dat_id: i64, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.delete_dat_file(dat_id).await.map_err(AppError::from)
+}
+
+// Hash a game's ROM and match it against the imported DATs
+#[tauri::command]
+async fn verify_game(
+    game_id: String,
+    state: tauri::State<'_, Database>
+) -> Result<dat::Verification, AppError> {
+    dat::verify_game(&state, &game_id).await.map_err(AppError::from)
+}
+
+// Re-check every game, e.g. after importing a new DAT. Games whose ROM is missing are skipped.
+#[tauri::command]
+async fn verify_library(state: tauri::State<'_, Database>) -> Result<Vec<dat::Verification>, AppError> {
+    let games = state.get_all_games().await?;
+    let mut results = Vec::new();
+    for game in games {
+        match dat::verify_game(&state, &game.id).await {
+            Ok(verification) => results.push(verification),
+            Err(e) => eprintln!("Failed to verify {}: {}", game.id, e),
+        }
+    }
+    Ok(results)
+}
+
+// Required and optional BIOS files per platform and whether RetroArch's system
+// directory has a verified copy
+#[tauri::command]
+async fn verify_bios(platform: Option<String>, app_handle: tauri::AppHandle) -> Result<Vec<bios::BiosStatus>, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
+    let system_dir = bios::system_dir(&app_dir.join("retroarch"));
+    tokio::task::spawn_blocking(move || bios::verify(&system_dir, platform.as_deref()))
+        .await
+        .map_err(AppError::from)
+}
 
-    let install_dir = app_dir.join("games");
-    std::fs::create_dir_all(&install_dir)
-        .map_err(|e| format!("Failed to create games directory: {}", e))?;
-
-    // Install the game (download + extract) with progress events
-    let install_result = crocdb::install_game_complete(
-        &slug,
-        "retroarch",
-        &install_dir.to_string_lossy().to_string(),
-        Some(&app_handle)
-    ).await.map_err(|e| e.to_string())?;
-
-    // Generate unique ID
-    let game_id = format!("crocdb_{}", slug.replace("-", "_"));
-
-    // Add to database
-    let game = game::Game {
-        id: game_id.clone(),
-        title: install_result.title.clone(),
-        platform: install_result.platform.clone(),
-        rom_path: install_result.rom_path,
-        cover_path: install_result.cover_path,
-        emulator_id: "retroarch".to_string(), // Placeholder, not used anymore
-        description: Some(format!("Downloaded from CrocDB: {}", slug)),
-        release_year: None,
-        genre: None,
-        developer: None,
-        is_favorite: 0,
-        play_count: 0,
-        total_playtime: 0,
-        last_played: None,
-    };
+// Copy known BIOS files (matched by MD5) from a folder into RetroArch's system directory
+#[tauri::command]
+async fn import_bios_files(folder: String, app_handle: tauri::AppHandle) -> Result<bios::BiosImportSummary, AppError> {
+    let app_dir = app_handle.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to get app data directory")?;
+    let system_dir = bios::system_dir(&app_dir.join("retroarch"));
+    tokio::task::spawn_blocking(move || bios::import_from_folder(std::path::Path::new(&folder), &system_dir))
+        .await?
+        .map_err(AppError::from)
+}
+
+// Add a patched copy of a game from an IPS, UPS or BPS file. With `soft`, RetroArch
+// applies the patch at launch instead of a patched ROM being written.
+#[tauri::command]
+async fn patch_game(
+    game_id: String,
+    patch_path: String,
+    soft: Option<bool>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Database>
+) -> Result<Game, AppError> {
+    let games_dir = download_queue::games_dir(&app_handle)?;
+    patch::patch_game(&state, &game_id, std::path::Path::new(&patch_path), soft.unwrap_or(false), &games_dir)
+        .await
+        .map_err(AppError::from)
+}
+
+// Play history
+#[tauri::command]
+async fn get_play_sessions(
+    game_id: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<i64>,
+    state: tauri::State<'_, Database>
+) -> Result<Vec<game::PlaySession>, AppError> {
+    state.get_play_sessions(game_id.as_deref(), from.as_deref(), to.as_deref(), limit.unwrap_or(100))
+        .await
+        .map_err(AppError::from)
+}
+
+// Playtime per "day" (default), "week" or "month"
+#[tauri::command]
+async fn get_playtime_by_period(
+    period: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    state: tauri::State<'_, Database>
+) -> Result<Vec<game::PeriodPlaytime>, AppError> {
+    state.get_playtime_by_period(period.as_deref().unwrap_or("day"), from.as_deref(), to.as_deref())
+        .await
+        .map_err(AppError::from)
+}
 
-    state.add_game(game).await.map_err(|e| e.to_string())?;
+#[tauri::command]
+async fn get_playtime_by_platform(
+    from: Option<String>,
+    to: Option<String>,
+    state: tauri::State<'_, Database>
+) -> Result<Vec<game::PlatformPlaytime>, AppError> {
+    state.get_playtime_by_platform(from.as_deref(), to.as_deref())
+        .await
+        .map_err(AppError::from)
+}
 
-    Ok(format!("Game '{}' installed successfully!", install_result.title))
+#[tauri::command]
+async fn get_top_games(
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<i64>,
+    state: tauri::State<'_, Database>
+) -> Result<Vec<game::GamePlaytime>, AppError> {
+    state.get_top_games(from.as_deref(), to.as_deref(), limit.unwrap_or(10))
+        .await
+        .map_err(AppError::from)
 }
 
 // Emulator management
 #[tauri::command]
-async fn get_emulators(state: tauri::State<'_, Database>) -> Result<Vec<EmulatorConfig>, String> {
-    state.get_all_emulators().await.map_err(|e| e.to_string())
+async fn get_emulators(state: tauri::State<'_, Database>) -> Result<Vec<EmulatorConfig>, AppError> {
+    state.get_all_emulators().await.map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn add_emulator(emulator: EmulatorConfig, state: tauri::State<'_, Database>) -> Result<String, String> {
-    state.add_emulator(emulator).await.map_err(|e| e.to_string())?;
+async fn add_emulator(emulator: EmulatorConfig, state: tauri::State<'_, Database>) -> Result<String, AppError> {
+    state.add_emulator(emulator).await?;
     Ok("Emulator added successfully".to_string())
 }
 
 // Get recommended emulators
 #[tauri::command]
-async fn get_recommended_emulators() -> Result<Vec<emulator::RecommendedEmulator>, String> {
+async fn get_recommended_emulators() -> Result<Vec<emulator::RecommendedEmulator>, AppError> {
     Ok(emulator::get_recommended_emulators())
 }
 
@@ -234,11 +545,7 @@ async fn install_emulator(
     emulator_id: String,
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, Database>
-) -> Result<String, String> {
-    use std::fs::File;
-    use std::io::BufReader;
-    use zip::ZipArchive;
-
+) -> Result<String, AppError> {
     // Get recommended emulator info
     let recommended = emulator::get_recommended_emulator_by_id(&emulator_id)
         .ok_or("Emulator not found in recommendations")?;
@@ -262,56 +569,37 @@ async fn install_emulator(
     }.ok_or("No download URL available for your operating system")?;
 
     // Download the emulator
-    let filename = download_url.split('/').last().unwrap_or("emulator.zip");
+    let filename = download_url.split('/').next_back().unwrap_or("emulator.zip");
     let download_path = emulators_dir.join(filename);
 
-    let client = reqwest::Client::new();
-    let response = client.get(&download_url).send().await
-        .map_err(|e| format!("Download failed: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {}", response.status()));
-    }
-
-    let bytes = response.bytes().await
-        .map_err(|e| format!("Failed to read download: {}", e))?;
-
-    std::fs::write(&download_path, bytes)
-        .map_err(|e| format!("Failed to save download: {}", e))?;
+    downloader::download_file(
+        &download_url,
+        &download_path,
+        &downloader::DownloadOptions::default(),
+        &downloader::ProgressEvent {
+            slug: format!("emulator:{}", emulator_id),
+            stage: "downloading".to_string(),
+            message: format!("Downloading {}...", recommended.name),
+            title: Some(recommended.name.clone()),
+        },
+        Some(&app_handle),
+    ).await?;
 
     // Extract if it's an archive
     let extract_dir = emulators_dir.join("app");
-    if filename.ends_with(".zip") {
-        let file = File::open(&download_path)
-            .map_err(|e| format!("Failed to open archive: {}", e))?;
-        let reader = BufReader::new(file);
-        let mut archive = ZipArchive::new(reader)
-            .map_err(|e| format!("Failed to read archive: {}", e))?;
-
-        std::fs::create_dir_all(&extract_dir)
-            .map_err(|e| format!("Failed to create extract directory: {}", e))?;
-
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)
-                .map_err(|e| format!("Failed to read archive entry: {}", e))?;
-            let outpath = extract_dir.join(file.name());
-
-            if file.name().ends_with('/') {
-                std::fs::create_dir_all(&outpath)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    std::fs::create_dir_all(p)
-                        .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-                }
-                let mut outfile = File::create(&outpath)
-                    .map_err(|e| format!("Failed to create file: {}", e))?;
-                std::io::copy(&mut file, &mut outfile)
-                    .map_err(|e| format!("Failed to extract file: {}", e))?;
-            }
-        }
-    } else if filename.ends_with(".7z") {
-        return Err("7z extraction not yet supported. Please extract manually and add the emulator path.".to_string());
+    if archive::ArchiveFormat::detect(&download_path).is_some() {
+        let event = downloader::ProgressEvent {
+            slug: format!("emulator:{}", emulator_id),
+            stage: "extracting".to_string(),
+            message: "Extracting emulator...".to_string(),
+            title: Some(recommended.name.clone()),
+        };
+        let (archive_path, dest, handle) = (download_path.clone(), extract_dir.clone(), app_handle.clone());
+        tokio::task::spawn_blocking(move || {
+            archive::extract_archive(&archive_path, &dest, &archive::ExtractOptions::default(), &event, Some(&handle))
+        })
+        .await?
+        .map_err(|e| format!("Failed to extract archive: {}", e))?;
     }
 
     // Find executable
@@ -376,7 +664,7 @@ async fn install_emulator(
             name: format!("{} ({})", recommended.name, platform_name),
             platform: platform.clone(),
             executable_path: executable_path.to_string_lossy().to_string(),
-            arguments: None,
+            arguments: recommended.default_arguments.clone(),
             icon_path: None,
         };
 
@@ -389,32 +677,139 @@ async fn install_emulator(
 
 // Get user settings
 #[tauri::command]
-async fn get_settings(state: tauri::State<'_, Database>) -> Result<serde_json::Value, String> {
-    state.get_settings().await.map_err(|e| e.to_string())
+async fn get_settings(state: tauri::State<'_, Database>) -> Result<serde_json::Value, AppError> {
+    state.get_settings().await.map_err(AppError::from)
 }
 
 // Update user settings
 #[tauri::command]
-async fn update_settings(settings: serde_json::Value, state: tauri::State<'_, Database>) -> Result<String, String> {
-    state.update_settings(settings).await.map_err(|e| e.to_string())?;
+async fn update_settings(
+    settings: serde_json::Value,
+    state: tauri::State<'_, Database>,
+    queue: tauri::State<'_, DownloadQueue>
+) -> Result<String, AppError> {
+    state.update_settings(settings).await?;
+    // Pick up a changed max_concurrent_downloads
+    queue.reschedule();
     Ok("Settings updated".to_string())
 }
 
 // Toggle favorite
 #[tauri::command]
-async fn toggle_favorite(game_id: String, state: tauri::State<'_, Database>) -> Result<bool, String> {
-    state.toggle_favorite(&game_id).await.map_err(|e| e.to_string())
+async fn toggle_favorite(game_id: String, state: tauri::State<'_, Database>) -> Result<bool, AppError> {
+    state.toggle_favorite(&game_id).await.map_err(AppError::from)
 }
 
 // Get game stats
 #[tauri::command]
-async fn get_game_stats(game_id: String, state: tauri::State<'_, Database>) -> Result<game::GameStats, String> {
-    state.get_game_stats(&game_id).await.map_err(|e| e.to_string())
+async fn get_game_stats(game_id: String, state: tauri::State<'_, Database>) -> Result<game::GameStats, AppError> {
+    state.get_game_stats(&game_id).await.map_err(AppError::from)
+}
+
+// Collections. A collection with rules is a smart collection whose games are the ones matching them.
+fn collection_rules_json(name: &str, rules: Option<&SmartRules>) -> Result<Option<String>, AppError> {
+    if name.trim().is_empty() {
+        return Err("Collection name can't be empty".into());
+    }
+    match rules {
+        Some(rules) => {
+            rules.validate()?;
+            Ok(Some(serde_json::to_string(rules)?))
+        }
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+async fn get_collections(state: tauri::State<'_, Database>) -> Result<Vec<Collection>, AppError> {
+    state.get_collections().await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn create_collection(
+    name: String,
+    description: Option<String>,
+    rules: Option<SmartRules>,
+    state: tauri::State<'_, Database>
+) -> Result<Collection, AppError> {
+    let rules = collection_rules_json(&name, rules.as_ref())?;
+    Ok(state.create_collection(name.trim(), description.as_deref(), rules.as_deref()).await?)
+}
+
+#[tauri::command]
+async fn update_collection(
+    collection_id: i64,
+    name: String,
+    description: Option<String>,
+    rules: Option<SmartRules>,
+    state: tauri::State<'_, Database>
+) -> Result<Collection, AppError> {
+    let rules = collection_rules_json(&name, rules.as_ref())?;
+    Ok(state.update_collection(collection_id, name.trim(), description.as_deref(), rules.as_deref()).await?)
+}
+
+#[tauri::command]
+async fn delete_collection(collection_id: i64, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.delete_collection(collection_id).await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn add_game_to_collection(collection_id: i64, game_id: String, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    let collection = state.get_collection(collection_id).await?;
+    if collection.is_smart() {
+        return Err(format!("\"{}\" is a smart collection; its games come from its rules", collection.name).into());
+    }
+    // Fails with NotFound for an unknown game
+    state.get_game(&game_id).await?;
+    Ok(state.add_game_to_collection(collection_id, &game_id).await?)
+}
+
+#[tauri::command]
+async fn remove_game_from_collection(collection_id: i64, game_id: String, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.remove_game_from_collection(collection_id, &game_id).await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_collection_games(collection_id: i64, state: tauri::State<'_, Database>) -> Result<Vec<Game>, AppError> {
+    let collection = state.get_collection(collection_id).await?;
+    Ok(state.get_collection_games(&collection).await?)
+}
+
+// Tags
+#[tauri::command]
+async fn get_tags(state: tauri::State<'_, Database>) -> Result<Vec<Tag>, AppError> {
+    state.get_tags().await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_game_tags(game_id: String, state: tauri::State<'_, Database>) -> Result<Vec<Tag>, AppError> {
+    state.get_game_tags(&game_id).await.map_err(AppError::from)
+}
+
+// Replace a game's tags, creating new ones by name
+#[tauri::command]
+async fn set_game_tags(game_id: String, tags: Vec<String>, state: tauri::State<'_, Database>) -> Result<Vec<Tag>, AppError> {
+    state.get_game(&game_id).await?;
+    Ok(state.set_game_tags(&game_id, &tags).await?)
+}
+
+// Rename or recolor a tag
+#[tauri::command]
+async fn update_tag(tag_id: i64, name: String, color: Option<String>, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err("Tag name can't be empty".into());
+    }
+    state.update_tag(tag_id, name.trim(), color.as_deref()).await.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn delete_tag(tag_id: i64, state: tauri::State<'_, Database>) -> Result<(), AppError> {
+    state.delete_tag(tag_id).await.map_err(AppError::from)
 }
 
 // RetroArch management
 #[tauri::command]
-async fn check_retroarch_status(app_handle: tauri::AppHandle) -> Result<retroarch::RetroArchStatus, String> {
+async fn check_retroarch_status(app_handle: tauri::AppHandle) -> Result<retroarch::RetroArchStatus, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
@@ -423,31 +818,31 @@ async fn check_retroarch_status(app_handle: tauri::AppHandle) -> Result<retroarc
 }
 
 #[tauri::command]
-async fn install_retroarch(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn install_retroarch(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
 
     // Download RetroArch
     let archive_path = retroarch::download_retroarch(&app_dir, Some(&app_handle))
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     // Extract RetroArch
-    let extract_path = retroarch::extract_retroarch(&archive_path, &app_dir, Some(&app_handle))
-        .map_err(|e| e.to_string())?;
+    let (dir, handle) = (app_dir.clone(), app_handle.clone());
+    let extract_path = tokio::task::spawn_blocking(move || {
+        retroarch::extract_retroarch(&archive_path, &dir, Some(&handle))
+    }).await??;
 
     // Prepare cores cache immediately (download + extract core pack)
     // This ensures the UI can list all available cores from the local cache on first launch
     let _ = retroarch::prepare_cores_cache(&app_dir, Some(&app_handle))
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(format!("RetroArch installed successfully to: {:?}", extract_path))
 }
 
 #[tauri::command]
-async fn get_retroarch_cores(platform: Option<String>, app_handle: tauri::AppHandle) -> Result<Vec<retroarch::RetroArchCore>, String> {
+async fn get_retroarch_cores(platform: Option<String>, app_handle: tauri::AppHandle) -> Result<Vec<retroarch::RetroArchCore>, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
@@ -455,9 +850,11 @@ async fn get_retroarch_cores(platform: Option<String>, app_handle: tauri::AppHan
     let retroarch_path = app_dir.join("retroarch");
 
     // Prefer listing available cores from the prepared AppData cache
-    // AppData/Roaming/com.retrolauncher.app/cache/retroarch_cores/RetroArch-Win64
-    let cache_retroarch = app_dir.join("cache").join("retroarch_cores").join("RetroArch-Win64");
-    let resource_retroarch = if cache_retroarch.exists() {
+    // e.g. AppData/Roaming/com.retrolauncher.app/cache/retroarch_cores/RetroArch-Win64
+    let cache_retroarch = retroarch::RetroArchTarget::current()
+        .archive_root_dir()
+        .map(|dir| app_dir.join("cache").join("retroarch_cores").join(dir));
+    let resource_retroarch = if let Some(cache_retroarch) = cache_retroarch.filter(|p| p.exists()) {
         println!("✅ Using cached cores directory at: {:?}", cache_retroarch);
         Some(cache_retroarch)
     } else {
@@ -484,7 +881,7 @@ async fn get_retroarch_cores(platform: Option<String>, app_handle: tauri::AppHan
 }
 
 #[tauri::command]
-async fn install_retroarch_core(core_id: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn install_retroarch_core(core_id: String, app_handle: tauri::AppHandle) -> Result<String, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
@@ -492,16 +889,15 @@ async fn install_retroarch_core(core_id: String, app_handle: tauri::AppHandle) -
     let retroarch_path = app_dir.join("retroarch");
 
     if !retroarch_path.exists() {
-        return Err("RetroArch is not installed".to_string());
+        return Err(AppError::RetroArch("RetroArch is not installed".to_string()));
     }
 
     retroarch::install_core(&core_id, &retroarch_path, Some(&app_handle))
         .await
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn get_installed_cores(app_handle: tauri::AppHandle) -> Result<Vec<retroarch::RetroArchCore>, String> {
+async fn get_installed_cores(app_handle: tauri::AppHandle) -> Result<Vec<retroarch::RetroArchCore>, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
@@ -522,7 +918,7 @@ async fn get_installed_cores(app_handle: tauri::AppHandle) -> Result<Vec<retroar
 
 // Uninstall all RetroArch cores (optionally remove cache)
 #[tauri::command]
-async fn uninstall_all_retroarch_cores(app_handle: tauri::AppHandle, remove_cache: Option<bool>) -> Result<String, String> {
+async fn uninstall_all_retroarch_cores(app_handle: tauri::AppHandle, remove_cache: Option<bool>) -> Result<String, AppError> {
     let app_dir = app_handle
         .path_resolver()
         .app_data_dir()
@@ -530,17 +926,16 @@ async fn uninstall_all_retroarch_cores(app_handle: tauri::AppHandle, remove_cach
 
     let retroarch_path = app_dir.join("retroarch");
     if !retroarch_path.exists() {
-        return Err("RetroArch is not installed".to_string());
+        return Err(AppError::RetroArch("RetroArch is not installed".to_string()));
     }
 
-    let removed = retroarch::uninstall_all_cores(&retroarch_path, remove_cache.unwrap_or(false))
-        .map_err(|e| e.to_string())?;
+    let removed = retroarch::uninstall_all_cores(&retroarch_path, remove_cache.unwrap_or(false))?;
 
-    Ok(format!("Removed {} core DLLs{}", removed, if remove_cache.unwrap_or(false) { " and cleared cache" } else { "" }))
+    Ok(format!("Removed {} core libraries{}", removed, if remove_cache.unwrap_or(false) { " and cleared cache" } else { "" }))
 }
 
 #[tauri::command]
-async fn install_all_retroarch_cores(app_handle: tauri::AppHandle, force: Option<bool>) -> Result<String, String> {
+async fn install_all_retroarch_cores(app_handle: tauri::AppHandle, force: Option<bool>) -> Result<String, AppError> {
     let app_dir = app_handle
         .path_resolver()
         .app_data_dir()
@@ -548,23 +943,24 @@ async fn install_all_retroarch_cores(app_handle: tauri::AppHandle, force: Option
 
     let retroarch_path = app_dir.join("retroarch");
     if !retroarch_path.exists() {
-        return Err("RetroArch is not installed".to_string());
+        return Err(AppError::RetroArch("RetroArch is not installed".to_string()));
     }
 
     // Prepare cores cache once to speed up installs
     let _ = retroarch::prepare_cores_cache(&app_dir, Some(&app_handle))
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     // Detect optional resource directory (Ressource/RetroArch-Win64) like in get_retroarch_cores
     let mut resource_retroarch = None;
-    let dev_path = std::path::PathBuf::from("D:\\RetroLauncher\\Ressource\\RetroArch-Win64");
-    if dev_path.exists() {
-        resource_retroarch = Some(dev_path);
-    } else if let Ok(current) = std::env::current_dir() {
-        let rel_path = current.join("Ressource").join("RetroArch-Win64");
-        if rel_path.exists() {
-            resource_retroarch = Some(rel_path);
+    if let Some(root_dir) = retroarch::RetroArchTarget::current().archive_root_dir() {
+        let dev_path = std::path::PathBuf::from("D:\\RetroLauncher\\Ressource").join(&root_dir);
+        if dev_path.exists() {
+            resource_retroarch = Some(dev_path);
+        } else if let Ok(current) = std::env::current_dir() {
+            let rel_path = current.join("Ressource").join(&root_dir);
+            if rel_path.exists() {
+                resource_retroarch = Some(rel_path);
+            }
         }
     }
 
@@ -626,19 +1022,19 @@ async fn install_all_retroarch_cores(app_handle: tauri::AppHandle, force: Option
             installed_count, skipped_count
         ))
     } else {
-        Err(format!(
+        Err(AppError::RetroArch(format!(
             "Installed: {}, Skipped: {}, Errors: {} -> {:?}",
             installed_count,
             skipped_count,
             errors.len(),
             errors
-        ))
+        )))
     }
 }
 
 // Check if cores cache is prepared (extracted), to control first-core popup behavior
 #[tauri::command]
-async fn is_cores_cache_ready(app_handle: tauri::AppHandle) -> Result<bool, String> {
+async fn is_cores_cache_ready(app_handle: tauri::AppHandle) -> Result<bool, AppError> {
     let app_dir = app_handle.path_resolver()
         .app_data_dir()
         .ok_or("Failed to get app data directory")?;
@@ -648,7 +1044,7 @@ async fn is_cores_cache_ready(app_handle: tauri::AppHandle) -> Result<bool, Stri
 
 // Open folder in explorer
 #[tauri::command]
-fn open_folder_in_explorer(path: String) -> Result<(), String> {
+fn open_folder_in_explorer(path: String) -> Result<(), AppError> {
     // Vérifier que le chemin existe
     let path_obj = std::path::Path::new(&path);
     if !path_obj.exists() {
@@ -699,17 +1095,32 @@ fn main() {
             let db_path = app_dir.join("retrolauncher.db");
             println!("Database path: {:?}", db_path);
 
+            // Runs schema migrations; refuses to open a database from a newer release
             let db = tauri::async_runtime::block_on(async {
-                Database::new(&db_path).await.expect("Failed to initialize database")
-            });
+                Database::new(&db_path).await
+            }).map_err(|e| format!("Failed to initialize database: {}", e))?;
+
+            let queue = DownloadQueue::new(db.clone(), download_queue::games_dir(&app.app_handle())?, Some(app.app_handle()));
+            queue.start();
 
+            app.manage(ProcessSupervisor::new(db.clone(), Some(app.app_handle())));
             app.manage(db);
+            app.manage(queue);
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             launch_game,
+            resolve_game_core,
+            set_game_core_override,
+            set_platform_default_core,
+            set_game_emulator,
+            get_running_games,
+            stop_game,
             get_games,
+            query_games,
+            get_library_platforms,
+            search_library,
             delete_game,
             add_game,
             search_crocdb,
@@ -719,8 +1130,22 @@ fn main() {
             get_crocdb_platforms,
             get_crocdb_regions,
             get_crocdb_info,
+            clear_crocdb_cache,
             download_from_crocdb,
             install_game_from_crocdb,
+            list_downloads,
+            pause_download,
+            resume_download,
+            cancel_download,
+            scan_directory,
+            import_dat_file,
+            get_dat_files,
+            delete_dat_file,
+            verify_game,
+            verify_library,
+            patch_game,
+            verify_bios,
+            import_bios_files,
             get_emulators,
             add_emulator,
             get_recommended_emulators,
@@ -729,6 +1154,22 @@ fn main() {
             update_settings,
             toggle_favorite,
             get_game_stats,
+            get_collections,
+            create_collection,
+            update_collection,
+            delete_collection,
+            add_game_to_collection,
+            remove_game_from_collection,
+            get_collection_games,
+            get_tags,
+            get_game_tags,
+            set_game_tags,
+            update_tag,
+            delete_tag,
+            get_play_sessions,
+            get_playtime_by_period,
+            get_playtime_by_platform,
+            get_top_games,
             check_retroarch_status,
             install_retroarch,
             get_retroarch_cores,
@@ -0,0 +1,527 @@
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+// A schema step. Steps are applied in order, each in its own transaction, and recorded
+// in `schema_version`. Never edit a released step: append a new one instead.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub statements: &'static [&'static str],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    // Databases created before versioning already have these tables, hence IF NOT EXISTS
+    Migration {
+        version: 1,
+        description: "initial schema",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS games (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                rom_path TEXT NOT NULL,
+                cover_path TEXT,
+                emulator_id TEXT NOT NULL,
+                description TEXT,
+                release_year INTEGER,
+                genre TEXT,
+                developer TEXT,
+                is_favorite INTEGER DEFAULT 0,
+                play_count INTEGER DEFAULT 0,
+                total_playtime INTEGER DEFAULT 0,
+                last_played TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS emulators (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                executable_path TEXT NOT NULL,
+                arguments TEXT,
+                icon_path TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS screenshots (
+                id TEXT PRIMARY KEY,
+                game_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (game_id) REFERENCES games(id)
+            )
+            "#,
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "play sessions",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS play_sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                game_id TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                duration INTEGER NOT NULL DEFAULT 0,
+                core TEXT,
+                exit_status TEXT NOT NULL,
+                exit_code INTEGER,
+                FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_play_sessions_started_at ON play_sessions(started_at)",
+            "CREATE INDEX IF NOT EXISTS idx_play_sessions_game_id ON play_sessions(game_id)",
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "per-game core override",
+        statements: &[
+            "ALTER TABLE games ADD COLUMN core_override TEXT",
+        ],
+    },
+    Migration {
+        version: 4,
+        description: "DAT files and ROM verification",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS dat_files (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                version TEXT,
+                imported_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS dat_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                dat_id INTEGER NOT NULL,
+                game_name TEXT NOT NULL,
+                rom_name TEXT NOT NULL,
+                size INTEGER,
+                crc32 TEXT,
+                md5 TEXT,
+                sha1 TEXT,
+                region TEXT,
+                revision TEXT,
+                FOREIGN KEY (dat_id) REFERENCES dat_files(id) ON DELETE CASCADE
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_dat_entries_crc32 ON dat_entries(crc32)",
+            "CREATE INDEX IF NOT EXISTS idx_dat_entries_md5 ON dat_entries(md5)",
+            "CREATE INDEX IF NOT EXISTS idx_dat_entries_sha1 ON dat_entries(sha1)",
+            "ALTER TABLE games ADD COLUMN crc32 TEXT",
+            "ALTER TABLE games ADD COLUMN md5 TEXT",
+            "ALTER TABLE games ADD COLUMN sha1 TEXT",
+            "ALTER TABLE games ADD COLUMN verified INTEGER DEFAULT 0",
+            "ALTER TABLE games ADD COLUMN region TEXT",
+            "ALTER TABLE games ADD COLUMN revision TEXT",
+        ],
+    },
+    Migration {
+        version: 5,
+        description: "download queue",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS downloads (
+                slug TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued',
+                progress REAL NOT NULL DEFAULT 0,
+                bytes_received INTEGER NOT NULL DEFAULT 0,
+                total_bytes INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_downloads_status ON downloads(status)",
+        ],
+    },
+    Migration {
+        version: 6,
+        description: "download link selection",
+        statements: &[
+            "ALTER TABLE downloads ADD COLUMN link_selector TEXT",
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "patched games",
+        statements: &[
+            "ALTER TABLE games ADD COLUMN base_game_id TEXT",
+            "ALTER TABLE games ADD COLUMN soft_patch TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_games_base_game_id ON games(base_game_id)",
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "crocdb response cache",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS crocdb_cache (
+                key TEXT PRIMARY KEY,
+                endpoint TEXT NOT NULL,
+                body TEXT NOT NULL,
+                etag TEXT,
+                last_modified TEXT,
+                fetched_at INTEGER NOT NULL
+            )
+            "#,
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "collections and tags",
+        statements: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS collections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                description TEXT,
+                rules TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS collection_games (
+                collection_id INTEGER NOT NULL,
+                game_id TEXT NOT NULL,
+                added_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (collection_id, game_id),
+                FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+                FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_collection_games_game_id ON collection_games(game_id)",
+            r#"
+            CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                color TEXT
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS game_tags (
+                game_id TEXT NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (game_id, tag_id),
+                FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_game_tags_tag_id ON game_tags(tag_id)",
+        ],
+    },
+    // games_fts holds one row per game, kept in sync with games and its tags by triggers
+    Migration {
+        version: 10,
+        description: "library full-text search",
+        statements: &[
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS games_fts USING fts5(
+                game_id UNINDEXED,
+                title,
+                description,
+                developer,
+                genre,
+                tags,
+                tokenize = 'unicode61 remove_diacritics 2'
+            )
+            "#,
+            r#"
+            INSERT INTO games_fts (game_id, title, description, developer, genre, tags)
+            SELECT g.id, g.title, g.description, g.developer, g.genre,
+                   (SELECT group_concat(t.name, ' ') FROM game_tags gt JOIN tags t ON t.id = gt.tag_id
+                    WHERE gt.game_id = g.id)
+            FROM games g
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS games_fts_insert AFTER INSERT ON games BEGIN
+                INSERT INTO games_fts (game_id, title, description, developer, genre, tags)
+                VALUES (new.id, new.title, new.description, new.developer, new.genre, NULL);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS games_fts_update AFTER UPDATE OF title, description, developer, genre ON games BEGIN
+                UPDATE games_fts
+                SET title = new.title, description = new.description, developer = new.developer, genre = new.genre
+                WHERE game_id = new.id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS games_fts_delete AFTER DELETE ON games BEGIN
+                DELETE FROM games_fts WHERE game_id = old.id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS game_tags_fts_insert AFTER INSERT ON game_tags BEGIN
+                UPDATE games_fts
+                SET tags = (SELECT group_concat(t.name, ' ') FROM game_tags gt JOIN tags t ON t.id = gt.tag_id
+                            WHERE gt.game_id = new.game_id)
+                WHERE game_id = new.game_id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS game_tags_fts_delete AFTER DELETE ON game_tags BEGIN
+                UPDATE games_fts
+                SET tags = (SELECT group_concat(t.name, ' ') FROM game_tags gt JOIN tags t ON t.id = gt.tag_id
+                            WHERE gt.game_id = old.game_id)
+                WHERE game_id = old.game_id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS tags_fts_rename AFTER UPDATE OF name ON tags BEGIN
+                UPDATE games_fts
+                SET tags = (SELECT group_concat(t.name, ' ') FROM game_tags gt JOIN tags t ON t.id = gt.tag_id
+                            WHERE gt.game_id = games_fts.game_id)
+                WHERE game_id IN (SELECT game_id FROM game_tags WHERE tag_id = new.id);
+            END
+            "#,
+        ],
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+// Bring the database up to the latest schema. A backup is written next to the database
+// before any step runs on existing data, and a database written by a newer release is rejected.
+pub async fn run(pool: &SqlitePool, db_path: &Path) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    let current = current_version(pool).await?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(sqlx::Error::Configuration(format!(
+            "Database schema version {} is newer than this version of RetroLauncher supports ({}). \
+             Please update RetroLauncher, or restore a backup made by this version.",
+            current, latest
+        ).into()));
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    // Unversioned databases from older releases still hold user data worth backing up
+    if current > 0 || has_legacy_tables(pool).await? {
+        let backup_path = backup_path(db_path, current);
+        eprintln!("Backing up database to {:?} before migrating from v{} to v{}", backup_path, current, latest);
+        sqlx::query("VACUUM INTO ?")
+            .bind(backup_path.to_string_lossy().to_string())
+            .execute(pool)
+            .await?;
+    }
+
+    for migration in pending {
+        eprintln!("Applying database migration v{}: {}", migration.version, migration.description);
+        let mut tx = pool.begin().await?;
+        for statement in migration.statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        sqlx::query("INSERT INTO schema_version (version, description) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+async fn has_legacy_tables(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let row: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'games'"
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0 > 0)
+}
+
+// e.g. retrolauncher.db -> retrolauncher.db.v2-20240501-183000.bak
+fn backup_path(db_path: &Path, version: i64) -> PathBuf {
+    let file_name = db_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "retrolauncher.db".to_string());
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    db_path.with_file_name(format!("{}.v{}-{}.bak", file_name, version, stamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-migrations-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn open(db_path: &Path) -> SqlitePool {
+        let options = SqliteConnectOptions::new().filename(db_path).create_if_missing(true);
+        SqlitePoolOptions::new().max_connections(1).connect_with(options).await.unwrap()
+    }
+
+    fn backups(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".bak"))
+            .collect()
+    }
+
+    async fn game_titles(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_as::<_, (String,)>("SELECT title FROM games ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(title,)| title)
+            .collect()
+    }
+
+    #[test]
+    fn versions_are_strictly_increasing() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0].version < pair[1].version));
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[tokio::test]
+    async fn creates_a_fresh_database_without_a_backup() {
+        let dir = test_dir("fresh");
+        let db_path = dir.join("retrolauncher.db");
+        let pool = open(&db_path).await;
+
+        run(&pool, &db_path).await.unwrap();
+
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        assert!(backups(&dir).is_empty());
+        let applied: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM schema_version").fetch_one(&pool).await.unwrap();
+        assert_eq!(applied.0, MIGRATIONS.len() as i64);
+
+        // Running again on an up-to-date database changes nothing
+        run(&pool, &db_path).await.unwrap();
+        assert!(backups(&dir).is_empty());
+    }
+
+    #[tokio::test]
+    async fn upgrades_an_unversioned_database_and_backs_it_up() {
+        let dir = test_dir("legacy");
+        let db_path = dir.join("retrolauncher.db");
+        let pool = open(&db_path).await;
+        // Tables as releases before versioning created them, with a game in them
+        for statement in MIGRATIONS[0].statements {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        sqlx::query("INSERT INTO games (id, title, platform, rom_path, emulator_id) VALUES ('g1', 'Legacy', 'snes', '/roms/a.sfc', 'retroarch')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        run(&pool, &db_path).await.unwrap();
+
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        assert_eq!(game_titles(&pool).await, ["Legacy"]);
+
+        let backups = backups(&dir);
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("retrolauncher.db.v0-"), "{}", backups[0]);
+        let backup = open(&dir.join(&backups[0])).await;
+        assert_eq!(game_titles(&backup).await, ["Legacy"]);
+        let versioned: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name = 'schema_version'")
+            .fetch_one(&backup)
+            .await
+            .unwrap();
+        assert_eq!(versioned.0, 1);
+        assert_eq!(current_version(&backup).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn upgrades_from_the_baseline_schema() {
+        let dir = test_dir("baseline");
+        let db_path = dir.join("retrolauncher.db");
+        // A database left at v1 by the first versioned release
+        let pool = open(&db_path).await;
+        for statement in MIGRATIONS[0].statements {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        sqlx::query("CREATE TABLE schema_version (version INTEGER PRIMARY KEY, description TEXT NOT NULL, applied_at TEXT DEFAULT CURRENT_TIMESTAMP)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO schema_version (version, description) VALUES (1, 'initial schema')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        run(&pool, &db_path).await.unwrap();
+
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        let backups = backups(&dir);
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("retrolauncher.db.v1-"), "{}", backups[0]);
+        // Later steps really ran: the search index from the last one exists
+        let fts: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name = 'games_fts'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(fts.0, 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_a_database_from_a_newer_release() {
+        let dir = test_dir("newer");
+        let db_path = dir.join("retrolauncher.db");
+        let pool = open(&db_path).await;
+        run(&pool, &db_path).await.unwrap();
+        sqlx::query("INSERT INTO schema_version (version, description) VALUES (?, 'from the future')")
+            .bind(latest_version() + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = run(&pool, &db_path).await.unwrap_err();
+
+        assert!(matches!(err, sqlx::Error::Configuration(_)), "{:?}", err);
+        assert!(err.to_string().contains("newer"), "{}", err);
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version() + 1);
+        assert!(backups(&dir).is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::crocdb::DownloadLink;
+
+// Disc images a playlist can point at, best first. Sheets come before the tracks they
+// reference, so a "(Disc 1).cue" wins over its "(Disc 1) (Track 1).bin".
+const DISC_EXTENSIONS: &[&str] = &["cue", "gdi", "ccd", "mds", "chd", "pbp", "cso", "iso", "cdi", "img", "bin"];
+
+// "Final Fantasy VII (USA) (Disc 2)" -> 2. Also understands "(Disc 2 of 3)" and "(CD2)".
+pub fn disc_number(name: &str) -> Option<u32> {
+    disc_tag(name).map(|(_, number)| number)
+}
+
+// Byte range of the disc tag, parentheses included, and the disc number
+fn disc_tag(name: &str) -> Option<(std::ops::Range<usize>, u32)> {
+    let mut offset = 0;
+    while let Some(start) = name[offset..].find('(').map(|i| offset + i) {
+        let end = start + name[start..].find(')')?;
+        let tag = name[start + 1..end].trim().to_lowercase();
+        let rest = tag.strip_prefix("disc").or_else(|| tag.strip_prefix("cd"));
+        if let Some(rest) = rest {
+            let digits: String = rest.trim_start().chars().take_while(|c| c.is_ascii_digit()).collect();
+            let tail = rest.trim_start()[digits.len()..].trim();
+            if let Ok(number) = digits.parse() {
+                if tail.is_empty() || tail.starts_with("of ") {
+                    return Some((start..end + 1, number));
+                }
+            }
+        }
+        offset = end + 1;
+    }
+    None
+}
+
+// File stem with the disc tag removed: "Game (USA) (Disc 2).cue" -> "Game (USA)"
+pub fn without_disc_tag(name: &str) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    match disc_tag(&stem) {
+        Some((range, _)) => {
+            let joined = format!("{}{}", &stem[..range.start], &stem[range.end..]);
+            joined.split_whitespace().collect::<Vec<_>>().join(" ")
+        }
+        None => stem,
+    }
+}
+
+fn extension_rank(path: &Path) -> Option<usize> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    DISC_EXTENSIONS.iter().position(|e| *e == ext)
+}
+
+// The other discs of a chosen CrocDB link: links with the same name apart from the disc
+// tag, in the same format. Sorted by disc number; just the chosen link for single discs.
+pub fn disc_links<'a>(links: &'a [DownloadLink], chosen: &'a DownloadLink) -> Vec<&'a DownloadLink> {
+    if disc_number(&chosen.filename).is_none() {
+        return vec![chosen];
+    }
+    let base = without_disc_tag(&chosen.filename);
+    let extension = Path::new(&chosen.filename).extension();
+
+    let mut by_disc: BTreeMap<u32, &DownloadLink> = BTreeMap::new();
+    for link in links {
+        let same_set = without_disc_tag(&link.filename) == base
+            && link.format.eq_ignore_ascii_case(&chosen.format)
+            && Path::new(&link.filename).extension() == extension;
+        if let Some(number) = disc_number(&link.filename).filter(|_| same_set) {
+            // The chosen link wins over mirrors of the same disc
+            if std::ptr::eq(link, chosen) || !by_disc.contains_key(&number) {
+                by_disc.insert(number, link);
+            }
+        }
+    }
+    by_disc.into_values().collect()
+}
+
+// One image per disc when `files` hold a game with two or more discs, sorted by disc number
+pub fn disc_images(files: &[PathBuf]) -> Option<Vec<PathBuf>> {
+    disc_sets(files).into_iter().max_by_key(|discs| discs.len())
+}
+
+// Every game in `files` with two or more discs, each as one image per disc sorted by
+// disc number
+pub fn disc_sets(files: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    // name without disc tag -> disc number -> (extension rank, image)
+    let mut sets: HashMap<String, BTreeMap<u32, (usize, PathBuf)>> = HashMap::new();
+    for file in files {
+        let Some(rank) = extension_rank(file) else { continue };
+        let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let Some(number) = disc_number(&name) else { continue };
+        // "(Disc 1) (Track 02).bin" belongs to the sheet of disc 1
+        let stem = without_disc_tag(&name);
+        let base = match stem.find(" (Track") {
+            Some(i) => stem[..i].to_string(),
+            None => stem,
+        };
+        let discs = sets.entry(base).or_default();
+        match discs.get(&number) {
+            Some((best, _)) if *best <= rank => {}
+            _ => {
+                discs.insert(number, (rank, file.clone()));
+            }
+        }
+    }
+
+    sets.into_values()
+        .filter(|discs| discs.len() >= 2)
+        .map(|discs| discs.into_values().map(|(_, path)| path).collect())
+        .collect()
+}
+
+// Write "<name without disc tag>.m3u" in the deepest directory holding every disc so
+// RetroArch can swap discs. Entries are relative to the playlist.
+pub fn write_m3u(discs: &[PathBuf]) -> std::io::Result<PathBuf> {
+    let dir = discs.first()
+        .and_then(|first| first.ancestors().skip(1).find(|dir| discs.iter().all(|disc| disc.starts_with(dir))))
+        .unwrap_or(Path::new(""));
+    write_m3u_in(dir, discs)
+}
+
+// Write "<name without disc tag>.m3u" in `dir`. Discs outside `dir` are listed by their
+// full path, so the playlist can live apart from folders we shouldn't write to.
+pub fn write_m3u_in(dir: &Path, discs: &[PathBuf]) -> std::io::Result<PathBuf> {
+    let first = discs.first().ok_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "No discs to write a playlist for",
+    ))?;
+    let name = without_disc_tag(&first.file_name().unwrap_or_default().to_string_lossy());
+    let playlist = dir.join(format!("{}.m3u", name));
+
+    let mut content = String::new();
+    for disc in discs {
+        let entry = disc.strip_prefix(dir).unwrap_or(disc);
+        content.push_str(&entry.to_string_lossy().replace('\\', "/"));
+        content.push('\n');
+    }
+    std::fs::write(&playlist, content)?;
+    Ok(playlist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-multidisc-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(discs: &[PathBuf]) -> Vec<String> {
+        discs.iter().map(|d| d.file_name().unwrap().to_string_lossy().to_string()).collect()
+    }
+
+    #[test]
+    fn reads_disc_tags() {
+        assert_eq!(disc_number("Final Fantasy VII (USA) (Disc 2)"), Some(2));
+        assert_eq!(disc_number("Final Fantasy VII (USA) (Disc 2 of 3).chd"), Some(2));
+        assert_eq!(disc_number("Policenauts (Japan) (CD2).cue"), Some(2));
+        assert_eq!(disc_number("Policenauts (Japan) (cd 3)"), Some(3));
+        assert_eq!(disc_number("Discworld (Europe) (Disc 1)"), Some(1));
+        assert_eq!(disc_number("Game (Discovery Edition)"), None);
+        assert_eq!(disc_number("Game (Disc 1A)"), None);
+        assert_eq!(disc_number("Game (USA) (Rev 1)"), None);
+        assert_eq!(disc_number("Game (Disc 2"), None);
+
+        assert_eq!(without_disc_tag("Game (USA) (Disc 2).cue"), "Game (USA)");
+        assert_eq!(without_disc_tag("Game (Disc 1 of 2) (USA).chd"), "Game (USA)");
+        assert_eq!(without_disc_tag("Game (USA).iso"), "Game (USA)");
+    }
+
+    #[test]
+    fn groups_discs_by_game() {
+        let files: Vec<PathBuf> = [
+            "Game (Disc 1) (Track 1).bin",
+            "Game (Disc 1) (Track 2).bin",
+            "Game (Disc 1).cue",
+            "Game (Disc 2) (Track 1).bin",
+            "Game (Disc 2).cue",
+            "Raw (Disc 2) (Track 1).bin",
+            "Raw (Disc 1) (Track 1).bin",
+            "Raw (Disc 1) (Track 2).bin",
+            "Lonely (Disc 1).iso",
+            "Single.iso",
+            "Notes (Disc 1).txt",
+            "Notes (Disc 2).txt",
+        ].iter().map(PathBuf::from).collect();
+
+        let mut sets: Vec<Vec<String>> = disc_sets(&files).iter().map(|discs| names(discs)).collect();
+        sets.sort();
+        // Sheets beat their tracks; without sheets the tracks stand in, one per disc
+        assert_eq!(sets, [
+            vec!["Game (Disc 1).cue", "Game (Disc 2).cue"],
+            vec!["Raw (Disc 1) (Track 1).bin", "Raw (Disc 2) (Track 1).bin"],
+        ]);
+
+        let images = disc_images(&files[..5]).unwrap();
+        assert_eq!(names(&images), ["Game (Disc 1).cue", "Game (Disc 2).cue"]);
+        assert!(disc_images(&files[8..]).is_none());
+    }
+
+    #[test]
+    fn writes_relative_and_absolute_entries() {
+        let dir = test_dir("m3u");
+        let discs = vec![dir.join("a").join("Game (Disc 1).chd"), dir.join("b").join("Game (Disc 2).chd")];
+
+        // The playlist goes to the deepest common folder, with entries relative to it
+        let playlist = write_m3u(&discs).unwrap();
+        assert_eq!(playlist, dir.join("Game.m3u"));
+        assert_eq!(std::fs::read_to_string(&playlist).unwrap(), "a/Game (Disc 1).chd\nb/Game (Disc 2).chd\n");
+
+        // Discs outside the playlist's folder keep their full path
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        let playlist = write_m3u_in(&dir.join("a"), &discs).unwrap();
+        assert_eq!(playlist, dir.join("a").join("Game.m3u"));
+        let content = std::fs::read_to_string(&playlist).unwrap();
+        let absolute = discs[1].to_string_lossy().replace('\\', "/");
+        assert_eq!(content, format!("Game (Disc 1).chd\n{}\n", absolute));
+
+        let err = write_m3u(&[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(write_m3u_in(&dir, &[]).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
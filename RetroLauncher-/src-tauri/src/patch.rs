@@ -0,0 +1,619 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::crocdb;
+use crate::dat;
+use crate::database::Database;
+use crate::game::Game;
+use crate::scanner;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(b"PATCH") {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(b"UPS1") {
+            Some(PatchFormat::Ups)
+        } else if patch.starts_with(b"BPS1") {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+
+    // Extension RetroArch expects for soft-patching
+    pub fn extension(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Ups => "ups",
+            PatchFormat::Bps => "bps",
+        }
+    }
+}
+
+// Apply an IPS, UPS or BPS patch to `source`. UPS and BPS patches carry CRC32s of the
+// source, the result and the patch itself; all three are checked.
+pub fn apply_patch(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(source, patch),
+        Some(PatchFormat::Ups) => apply_ups(source, patch),
+        Some(PatchFormat::Bps) => apply_bps(source, patch),
+        None => Err("Unrecognized patch format (expected IPS, UPS or BPS)".to_string()),
+    }
+}
+
+// CRC32 of the ROM a UPS or BPS patch was made against; IPS records none
+pub fn expected_source_crc(patch: &[u8]) -> Option<u32> {
+    match PatchFormat::detect(patch)? {
+        PatchFormat::Ips => None,
+        PatchFormat::Ups | PatchFormat::Bps => read_u32_le(patch, patch.len().checked_sub(12)?),
+    }
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+const TRUNCATED: &str = "Patch is truncated";
+
+// Larger than any cartridge; keeps a corrupt size field from exhausting memory
+const MAX_TARGET_SIZE: usize = 1024 * 1024 * 1024;
+
+fn check_target_size(size: usize) -> Result<usize, String> {
+    if size > MAX_TARGET_SIZE {
+        return Err("Malformed patch (target is too large)".to_string());
+    }
+    Ok(size)
+}
+
+// IPS: "PATCH", then records of a 3-byte offset and 2-byte length followed by the
+// data, or by a 2-byte run length and fill byte when the length is 0. "EOF" ends the
+// records and may be followed by a 3-byte size to truncate the result to.
+fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = source.to_vec();
+    let mut pos = 5;
+    let byte = |at: usize| patch.get(at).copied().map(usize::from).ok_or(TRUNCATED);
+    loop {
+        if patch.get(pos..pos + 3) == Some(b"EOF") {
+            pos += 3;
+            break;
+        }
+        let offset = (byte(pos)? << 16) | (byte(pos + 1)? << 8) | byte(pos + 2)?;
+        let size = (byte(pos + 3)? << 8) | byte(pos + 4)?;
+        pos += 5;
+        if size == 0 {
+            let run = (byte(pos)? << 8) | byte(pos + 1)?;
+            let fill = byte(pos + 2)? as u8;
+            pos += 3;
+            if out.len() < offset + run {
+                out.resize(offset + run, 0);
+            }
+            out[offset..offset + run].fill(fill);
+        } else {
+            let data = patch.get(pos..pos + size).ok_or(TRUNCATED)?;
+            pos += size;
+            if out.len() < offset + size {
+                out.resize(offset + size, 0);
+            }
+            out[offset..offset + size].copy_from_slice(data);
+        }
+    }
+    if let Some(size) = patch.get(pos..pos + 3) {
+        out.truncate(((size[0] as usize) << 16) | ((size[1] as usize) << 8) | size[2] as usize);
+    }
+    Ok(out)
+}
+
+// Variable-length integer shared by UPS and BPS
+fn read_number(patch: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value: u64 = 0;
+    let mut shift: u64 = 1;
+    loop {
+        let byte = *patch.get(*pos).ok_or(TRUNCATED)?;
+        *pos += 1;
+        value = value.checked_add((byte & 0x7F) as u64 * shift).ok_or("Malformed patch")?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift.checked_shl(7).ok_or("Malformed patch")?;
+        value = value.checked_add(shift).ok_or("Malformed patch")?;
+    }
+}
+
+fn read_size(patch: &[u8], pos: &mut usize) -> Result<usize, String> {
+    usize::try_from(read_number(patch, pos)?).map_err(|_| "Malformed patch".to_string())
+}
+
+// Footer shared by UPS and BPS: source, target and patch CRC32s. The patch CRC covers
+// everything before it.
+fn check_footer(source: &[u8], patch: &[u8]) -> Result<(u32, usize), String> {
+    let footer = patch.len().checked_sub(12).filter(|f| *f >= 4).ok_or(TRUNCATED)?;
+    let patch_crc = read_u32_le(patch, footer + 8).ok_or(TRUNCATED)?;
+    if crc32fast::hash(&patch[..footer + 8]) != patch_crc {
+        return Err("Patch is corrupt (checksum mismatch)".to_string());
+    }
+    let source_crc = read_u32_le(patch, footer).ok_or(TRUNCATED)?;
+    let actual = crc32fast::hash(source);
+    if actual != source_crc {
+        return Err(format!(
+            "This patch is for a different ROM (expects CRC32 {:08x}, the game's ROM is {:08x})",
+            source_crc, actual
+        ));
+    }
+    let target_crc = read_u32_le(patch, footer + 4).ok_or(TRUNCATED)?;
+    Ok((target_crc, footer))
+}
+
+fn check_target(target: &[u8], target_crc: u32) -> Result<(), String> {
+    if crc32fast::hash(target) != target_crc {
+        return Err("Patched ROM failed its checksum; the patch or the ROM is damaged".to_string());
+    }
+    Ok(())
+}
+
+// UPS: "UPS1", source and target sizes, then hunks of a skip count and bytes XORed
+// onto the source, each hunk ending with a 0 byte
+fn apply_ups(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (target_crc, footer) = check_footer(source, patch)?;
+    let mut pos = 4;
+    let _source_size = read_size(patch, &mut pos)?;
+    let target_size = check_target_size(read_size(patch, &mut pos)?)?;
+
+    let mut out = source.to_vec();
+    out.resize(target_size, 0);
+    let mut at = 0usize;
+    while pos < footer {
+        at = at.checked_add(read_size(patch, &mut pos)?).ok_or("Malformed patch")?;
+        loop {
+            let xor = *patch.get(pos).ok_or(TRUNCATED)?;
+            pos += 1;
+            if xor == 0 {
+                at += 1;
+                break;
+            }
+            if let Some(b) = out.get_mut(at) {
+                *b ^= xor;
+            }
+            at += 1;
+        }
+    }
+    check_target(&out, target_crc)?;
+    Ok(out)
+}
+
+// BPS: "BPS1", source and target sizes, metadata, then actions that build the target
+// from source bytes, literal bytes or earlier target bytes
+fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (target_crc, footer) = check_footer(source, patch)?;
+    let mut pos = 4;
+    let _source_size = read_size(patch, &mut pos)?;
+    let target_size = check_target_size(read_size(patch, &mut pos)?)?;
+    let metadata_size = read_size(patch, &mut pos)?;
+    pos = pos.checked_add(metadata_size).ok_or("Malformed patch")?;
+
+    let mut out: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: i64 = 0;
+    let mut target_offset: i64 = 0;
+    let relative = |patch: &[u8], pos: &mut usize| -> Result<i64, String> {
+        let data = read_number(patch, pos)?;
+        let magnitude = (data >> 1) as i64;
+        Ok(if data & 1 != 0 { -magnitude } else { magnitude })
+    };
+
+    while pos < footer {
+        let data = read_number(patch, &mut pos)?;
+        let length = usize::try_from((data >> 2) + 1).map_err(|_| "Malformed patch")?;
+        if length > target_size - out.len() {
+            return Err("Malformed patch (writes past the target size)".to_string());
+        }
+        match data & 3 {
+            // SourceRead: the source bytes at the same position
+            0 => {
+                let start = out.len();
+                let bytes = source.get(start..start + length).ok_or("Malformed patch (reads past the source)")?;
+                out.extend_from_slice(bytes);
+            }
+            // TargetRead: literal bytes from the patch
+            1 => {
+                let bytes = patch.get(pos..pos + length).ok_or(TRUNCATED)?;
+                out.extend_from_slice(bytes);
+                pos += length;
+            }
+            // SourceCopy: source bytes from anywhere
+            2 => {
+                source_offset += relative(patch, &mut pos)?;
+                let start = usize::try_from(source_offset).map_err(|_| "Malformed patch")?;
+                let bytes = source.get(start..start + length).ok_or("Malformed patch (reads past the source)")?;
+                out.extend_from_slice(bytes);
+                source_offset += length as i64;
+            }
+            // TargetCopy: earlier output, byte by byte since the ranges may overlap
+            _ => {
+                target_offset += relative(patch, &mut pos)?;
+                for _ in 0..length {
+                    let byte = usize::try_from(target_offset).ok()
+                        .and_then(|at| out.get(at).copied())
+                        .ok_or("Malformed patch (copies unwritten bytes)")?;
+                    out.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size {
+        return Err("Malformed patch (target is incomplete)".to_string());
+    }
+    check_target(&out, target_crc)?;
+    Ok(out)
+}
+
+// Formats RetroArch loads from the content path; sheets, playlists and archives hold
+// several files and can't be patched as one ROM
+fn is_patchable(rom_path: &Path) -> bool {
+    !matches!(
+        scanner::extension_of(rom_path).as_str(),
+        "cue" | "gdi" | "m3u" | "chd" | "zip" | "7z" | "rar" | "pbp" | "cso" | "rvz" | "wbfs"
+    )
+}
+
+// ROM bytes the patch applies to. UPS and BPS patches name their source by CRC, which
+// tells whether they were made against a ROM with or without its dumper header; the
+// header is put back on the result. IPS patches get the file as is.
+fn split_header(rom_path: &Path, rom: &[u8], patch: &[u8]) -> usize {
+    let skip = dat::header_skip(rom_path).unwrap_or(0) as usize;
+    match expected_source_crc(patch) {
+        Some(crc) if skip > 0 && skip <= rom.len() && crc32fast::hash(rom) != crc
+            && crc32fast::hash(&rom[skip..]) == crc => skip,
+        _ => 0,
+    }
+}
+
+// Add a patched copy of a library game, linked to it through base_game_id. Patched
+// ROMs are written under `games_dir` like installs. With `soft`, only the patch is
+// stored and RetroArch applies it when the game launches.
+pub async fn patch_game(
+    db: &Database,
+    base_game_id: &str,
+    patch_path: &Path,
+    soft: bool,
+    games_dir: &Path,
+) -> Result<Game, Box<dyn Error>> {
+    let base = db.get_game(base_game_id).await?;
+    if base.soft_patch.is_some() {
+        return Err("Patches apply to the original game, not to a soft-patched copy".into());
+    }
+    let rom_path = PathBuf::from(&base.rom_path);
+    if !is_patchable(&rom_path) {
+        return Err(format!("{} holds several files and can't be patched", rom_path.display()).into());
+    }
+
+    let patch = std::fs::read(patch_path)?;
+    let format = PatchFormat::detect(&patch).ok_or("Unrecognized patch format (expected IPS, UPS or BPS)")?;
+    let patch_name = patch_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let title = format!("{} [{}]", base.title, patch_name);
+    let id = format!("patched_{:016x}", scanner::fnv1a64(format!("{}\n{}\n{}", base.id, patch_name, soft).as_bytes()));
+    if db.get_game(&id).await.is_ok() {
+        return Err(format!("'{}' is already in the library", title).into());
+    }
+
+    // Patching checks every CRC, so soft patches go through it too before being stored
+    let source = rom_path.clone();
+    let (skip, patched) = tokio::task::spawn_blocking(move || -> Result<(usize, Vec<u8>), String> {
+        let rom = std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let skip = split_header(&source, &rom, &patch);
+        let mut patched = rom[..skip].to_vec();
+        patched.extend(apply_patch(&rom[skip..], &patch)?);
+        Ok((skip, patched))
+    }).await??;
+
+    // RetroArch applies soft patches to the file as it is, header included, so a patch
+    // made for the headerless ROM would land shifted
+    if soft && skip > 0 {
+        return Err("This patch is for the ROM without its header, which RetroArch can't soft-patch. Apply it as a regular patch instead".into());
+    }
+
+    // Each copy owns its directory, deleted with it
+    let dir_name = if soft { format!("{} (soft patch)", title) } else { title.clone() };
+    let game_dir = crocdb::game_install_dir(games_dir, &base.platform, &dir_name);
+    std::fs::create_dir_all(&game_dir)?;
+    let file_stem = game_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    let (game_rom_path, soft_patch) = if soft {
+        let stored = game_dir.join(format!("{}.{}", file_stem, format.extension()));
+        std::fs::copy(patch_path, &stored)?;
+        (base.rom_path.clone(), Some(stored.to_string_lossy().to_string()))
+    } else {
+        let extension = rom_path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+        let target = game_dir.join(format!("{}.{}", file_stem, extension));
+        std::fs::write(&target, patched)?;
+        (target.to_string_lossy().to_string(), None)
+    };
+
+    let game = Game {
+        id,
+        title,
+        rom_path: game_rom_path,
+        description: Some(format!("{} patch applied to {}", format.extension().to_uppercase(), base.title)),
+        is_favorite: 0,
+        play_count: 0,
+        total_playtime: 0,
+        last_played: None,
+        crc32: None,
+        md5: None,
+        sha1: None,
+        verified: 0,
+        base_game_id: Some(base.id.clone()),
+        soft_patch,
+        ..base
+    };
+    db.add_game(game.clone()).await?;
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-patch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Inverse of read_number
+    fn number(mut n: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let low = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                out.push(0x80 | low);
+                return out;
+            }
+            out.push(low);
+            n -= 1;
+        }
+    }
+
+    // UPS/BPS body followed by the source, target and patch CRC32s
+    fn with_footer(mut body: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        body.extend(crc32fast::hash(source).to_le_bytes());
+        body.extend(crc32fast::hash(target).to_le_bytes());
+        body.extend(crc32fast::hash(&body).to_le_bytes());
+        body
+    }
+
+    fn bps_action(action: u64, length: u64) -> Vec<u8> {
+        number(((length - 1) << 2) | action)
+    }
+
+    fn bps_relative(offset: i64) -> Vec<u8> {
+        number((offset.unsigned_abs() << 1) | (offset < 0) as u64)
+    }
+
+    fn bps_patch(source: &[u8], target: &[u8], actions: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = b"BPS1".to_vec();
+        body.extend(number(source.len() as u64));
+        body.extend(number(target.len() as u64));
+        body.extend(number(0));
+        body.extend(actions.concat());
+        with_footer(body, source, target)
+    }
+
+    #[test]
+    fn reads_variable_length_numbers() {
+        for n in [0, 1, 127, 128, 129, 16_511, 16_512, 16_513, u32::MAX as u64] {
+            let encoded = number(n);
+            let mut pos = 0;
+            assert_eq!(read_number(&encoded, &mut pos), Ok(n));
+            assert_eq!(pos, encoded.len());
+        }
+        assert_eq!(number(128), vec![0x00, 0x80]);
+
+        let mut pos = 0;
+        assert_eq!(read_number(&[0x00, 0x7F], &mut pos), Err(TRUNCATED.to_string()));
+    }
+
+    #[test]
+    fn applies_ips_records_and_runs() {
+        let source = [0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x02, 0xAB, 0xCD]);
+        patch.extend([0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xEE]);
+        patch.extend(b"EOF");
+
+        let out = apply_patch(&source, &patch).unwrap();
+
+        assert_eq!(out, [0x00, 0xAB, 0xCD, 0x00, 0xEE, 0xEE, 0xEE, 0x00]);
+    }
+
+    #[test]
+    fn ips_grows_the_rom_and_truncates_to_the_trailing_size() {
+        let mut grow = b"PATCH".to_vec();
+        grow.extend([0x00, 0x00, 0x06, 0x00, 0x02, 0x11, 0x22]);
+        grow.extend(b"EOF");
+        assert_eq!(apply_patch(&[1, 2, 3, 4], &grow).unwrap(), [1, 2, 3, 4, 0, 0, 0x11, 0x22]);
+
+        let mut truncate = b"PATCH".to_vec();
+        truncate.extend([0x00, 0x00, 0x00, 0x00, 0x01, 0x09]);
+        truncate.extend(b"EOF");
+        truncate.extend([0x00, 0x00, 0x03]);
+        assert_eq!(apply_patch(&[1, 2, 3, 4, 5, 6], &truncate).unwrap(), [9, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_truncated_ips_patches() {
+        let mut short_data = b"PATCH".to_vec();
+        short_data.extend([0x00, 0x00, 0x00, 0x00, 0x04, 0x01, 0x02]);
+        assert_eq!(apply_patch(&[0; 8], &short_data), Err(TRUNCATED.to_string()));
+
+        let mut short_run = b"PATCH".to_vec();
+        short_run.extend([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04]);
+        assert_eq!(apply_patch(&[0; 8], &short_run), Err(TRUNCATED.to_string()));
+
+        let mut no_eof = b"PATCH".to_vec();
+        no_eof.extend([0x00, 0x00, 0x00, 0x00, 0x01, 0x01]);
+        assert_eq!(apply_patch(&[0; 8], &no_eof), Err(TRUNCATED.to_string()));
+    }
+
+    #[test]
+    fn applies_ups_hunks_and_resizes_the_target() {
+        let source = b"ABCD";
+        let target = b"ABXDE";
+        let mut body = b"UPS1".to_vec();
+        body.extend(number(4));
+        body.extend(number(5));
+        // Skip 2, XOR one byte; the terminator steps past the next byte
+        body.extend(number(2));
+        body.extend([b'C' ^ b'X', 0x00]);
+        // Skip 0 from byte 4, which lies past the source
+        body.extend(number(0));
+        body.extend([b'E', 0x00]);
+        let patch = with_footer(body, source, target);
+
+        assert_eq!(expected_source_crc(&patch), Some(crc32fast::hash(source)));
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn ups_skips_with_multi_byte_numbers() {
+        let source = vec![0u8; 300];
+        let mut target = source.clone();
+        target[200] = 0x5A;
+        let mut body = b"UPS1".to_vec();
+        body.extend(number(300));
+        body.extend(number(300));
+        body.extend(number(200));
+        body.extend([0x5A, 0x00]);
+        let patch = with_footer(body, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn checks_ups_checksums() {
+        let source = b"ABCD";
+        let target = b"ABXD";
+        let mut body = b"UPS1".to_vec();
+        body.extend(number(4));
+        body.extend(number(4));
+        body.extend(number(2));
+        body.extend([b'C' ^ b'X', 0x00]);
+        let patch = with_footer(body.clone(), source, target);
+
+        let wrong_rom = apply_patch(b"ABCE", &patch).unwrap_err();
+        assert!(wrong_rom.contains("different ROM"), "{}", wrong_rom);
+
+        let mut corrupt = patch.clone();
+        corrupt[body.len() - 2] ^= 0xFF;
+        assert_eq!(apply_patch(source, &corrupt), Err("Patch is corrupt (checksum mismatch)".to_string()));
+
+        let wrong_target = with_footer(body, source, b"ABYD");
+        let err = apply_patch(source, &wrong_target).unwrap_err();
+        assert!(err.contains("failed its checksum"), "{}", err);
+
+        assert_eq!(apply_patch(source, b"UPS1\x84"), Err(TRUNCATED.to_string()));
+    }
+
+    #[test]
+    fn applies_every_bps_action() {
+        let source = b"ABCDEFGH";
+        let target = b"ABxyFGHABxyFGabab";
+        let patch = bps_patch(source, target, &[
+            // SourceRead "AB"
+            bps_action(0, 2),
+            // TargetRead "xy"
+            [bps_action(1, 2), b"xy".to_vec()].concat(),
+            // SourceCopy "FGH" from +5, then "AB" from 8 - 8
+            [bps_action(2, 3), bps_relative(5)].concat(),
+            [bps_action(2, 2), bps_relative(-8)].concat(),
+            // TargetCopy "xyFG" from +2
+            [bps_action(3, 4), bps_relative(2)].concat(),
+            // TargetRead "ab", then an overlapping TargetCopy from 6 + 7 that repeats it
+            [bps_action(1, 2), b"ab".to_vec()].concat(),
+            [bps_action(3, 2), bps_relative(7)].concat(),
+        ]);
+
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn rejects_out_of_range_bps_actions() {
+        let source = b"ABCD";
+
+        let past_source = bps_patch(source, b"ABCD", &[[bps_action(2, 2), bps_relative(3)].concat()]);
+        let err = apply_patch(source, &past_source).unwrap_err();
+        assert!(err.contains("reads past the source"), "{}", err);
+
+        let before_source = bps_patch(source, b"AB", &[[bps_action(2, 2), bps_relative(-1)].concat()]);
+        assert!(apply_patch(source, &before_source).is_err());
+
+        let unwritten = bps_patch(source, b"ABCD", &[
+            bps_action(0, 2),
+            [bps_action(3, 2), bps_relative(2)].concat(),
+        ]);
+        let err = apply_patch(source, &unwritten).unwrap_err();
+        assert!(err.contains("copies unwritten bytes"), "{}", err);
+
+        let past_target = bps_patch(source, b"AB", &[bps_action(0, 4)]);
+        let err = apply_patch(source, &past_target).unwrap_err();
+        assert!(err.contains("writes past the target size"), "{}", err);
+
+        let incomplete = bps_patch(source, b"ABCD", &[bps_action(0, 2)]);
+        let err = apply_patch(source, &incomplete).unwrap_err();
+        assert!(err.contains("target is incomplete"), "{}", err);
+    }
+
+    #[test]
+    fn checks_bps_checksums() {
+        let source = b"ABCD";
+        let patch = bps_patch(source, b"ABCD", &[bps_action(0, 4)]);
+        assert_eq!(apply_patch(source, &patch).unwrap(), b"ABCD");
+
+        let err = apply_patch(b"ABCE", &patch).unwrap_err();
+        assert!(err.contains("different ROM"), "{}", err);
+
+        let mut corrupt = patch.clone();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xFF;
+        assert_eq!(apply_patch(source, &corrupt), Err("Patch is corrupt (checksum mismatch)".to_string()));
+
+        let mut truncated = patch.clone();
+        truncated.truncate(14);
+        assert!(apply_patch(source, &truncated).is_err());
+    }
+
+    #[test]
+    fn splits_the_header_only_when_the_patch_names_the_headerless_rom() {
+        let dir = test_dir("split-header");
+        let rom_path = dir.join("game.nes");
+        let mut rom = b"NES\x1a".to_vec();
+        rom.resize(16, 0);
+        rom.extend(b"PRGDATA!");
+        std::fs::write(&rom_path, &rom).unwrap();
+        let body = &rom[16..];
+
+        let headerless = bps_patch(body, body, &[bps_action(0, 8)]);
+        assert_eq!(split_header(&rom_path, &rom, &headerless), 16);
+
+        let headered = bps_patch(&rom, &rom, &[bps_action(0, 24)]);
+        assert_eq!(split_header(&rom_path, &rom, &headered), 0);
+
+        let mut ips = b"PATCH".to_vec();
+        ips.extend(b"EOF");
+        assert_eq!(split_header(&rom_path, &rom, &ips), 0);
+
+        let unheadered_path = dir.join("game.bin");
+        std::fs::write(&unheadered_path, body).unwrap();
+        assert_eq!(split_header(&unheadered_path, body, &headerless), 0);
+    }
+}
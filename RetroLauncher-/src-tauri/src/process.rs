@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::Manager;
+use tokio::sync::oneshot;
+
+use crate::database::Database;
+use crate::game::NewPlaySession;
+
+// Same format as SQLite's datetime('now'), so sessions sort and filter alongside last_played
+const SESSION_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningGame {
+    pub game_id: String,
+    pub title: String,
+    pub pid: Option<u32>,
+    pub core: Option<String>,
+    pub started_at: String,
+}
+
+struct Session {
+    info: RunningGame,
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+// Keeps track of games launched by the app. Each launched process is awaited on a
+// background task; when it exits the session is written to play_sessions and its length
+// is added to the game's total_playtime.
+#[derive(Clone)]
+pub struct ProcessSupervisor {
+    db: Database,
+    app_handle: Option<tauri::AppHandle>,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl ProcessSupervisor {
+    pub fn new(db: Database, app_handle: Option<tauri::AppHandle>) -> Self {
+        ProcessSupervisor {
+            db,
+            app_handle,
+            sessions: Arc::default(),
+        }
+    }
+
+    pub fn is_running(&self, game_id: &str) -> bool {
+        self.sessions.lock().unwrap().contains_key(game_id)
+    }
+
+    pub fn running_games(&self) -> Vec<RunningGame> {
+        self.sessions.lock().unwrap()
+            .values()
+            .map(|s| s.info.clone())
+            .collect()
+    }
+
+    // Spawn the launch command and supervise it until it exits
+    pub fn spawn(
+        &self,
+        game_id: &str,
+        title: &str,
+        core: Option<String>,
+        command: std::process::Command,
+    ) -> Result<RunningGame, String> {
+        // Checked and claimed under one lock, so a double launch can't start two instances
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(game_id) {
+            return Err(format!("'{}' is already running", title));
+        }
+
+        let mut child = tokio::process::Command::from(command)
+            .spawn()
+            .map_err(|e| format!("Failed to launch game: {}", e))?;
+
+        let info = RunningGame {
+            game_id: game_id.to_string(),
+            title: title.to_string(),
+            pid: child.id(),
+            core,
+            started_at: chrono::Utc::now().format(SESSION_TIME_FORMAT).to_string(),
+        };
+
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        sessions.insert(game_id.to_string(), Session {
+            info: info.clone(),
+            stop_tx: Some(stop_tx),
+        });
+        drop(sessions);
+
+        self.emit("game-started", &info);
+
+        let supervisor = self.clone();
+        let session_info = info.clone();
+        let started = Instant::now();
+        tauri::async_runtime::spawn(async move {
+            let (status, stopped) = tokio::select! {
+                status = child.wait() => (status, false),
+                _ = &mut stop_rx => {
+                    let _ = child.kill().await;
+                    (child.wait().await, true)
+                }
+            };
+
+            let duration_secs = started.elapsed().as_secs() as i64;
+            let exit_code = status.as_ref().ok().and_then(|s| s.code());
+            // A non-zero exit code is the game quitting on its own terms; only a signal
+            // or losing track of the process counts as a crash
+            let exit_status = match &status {
+                _ if stopped => "stopped",
+                Ok(s) if s.code().is_some() => "exited",
+                _ => "crashed",
+            };
+            supervisor.sessions.lock().unwrap().remove(&session_info.game_id);
+
+            let session = NewPlaySession {
+                game_id: session_info.game_id.clone(),
+                started_at: session_info.started_at.clone(),
+                ended_at: chrono::Utc::now().format(SESSION_TIME_FORMAT).to_string(),
+                duration: duration_secs,
+                core: session_info.core.clone(),
+                exit_status: exit_status.to_string(),
+                exit_code,
+            };
+            if let Err(e) = supervisor.db.record_play_session(&session).await {
+                eprintln!("Failed to record play session for {}: {}", session_info.game_id, e);
+            }
+
+            supervisor.emit("game-exited", serde_json::json!({
+                "gameId": session_info.game_id,
+                "title": session_info.title,
+                "durationSecs": duration_secs,
+                "exitStatus": exit_status,
+                "exitCode": exit_code,
+            }));
+        });
+
+        Ok(info)
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit_all(event, payload);
+        }
+    }
+
+    // Ask the supervising task to kill the game's process
+    pub fn stop(&self, game_id: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(game_id)
+            .ok_or(format!("Game '{}' is not running", game_id))?;
+        match session.stop_tx.take() {
+            Some(tx) => {
+                let _ = tx.send(());
+                Ok(())
+            }
+            None => Err(format!("'{}' is already stopping", session.info.title)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::game::{Game, PlaySession};
+    use crate::test_support::write_stub_emulator;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::time::Duration;
+
+    async fn test_supervisor(name: &str) -> (ProcessSupervisor, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-process-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(&dir.join("retrolauncher.db")).await.unwrap();
+        db.add_game(game("g1")).await.unwrap();
+        (ProcessSupervisor::new(db, None), dir)
+    }
+
+    fn game(id: &str) -> Game {
+        Game {
+            id: id.to_string(),
+            title: "Game".to_string(),
+            platform: "snes".to_string(),
+            rom_path: format!("/roms/{}.sfc", id),
+            cover_path: None,
+            emulator_id: "retroarch".to_string(),
+            description: None,
+            release_year: None,
+            genre: None,
+            developer: None,
+            is_favorite: 0,
+            play_count: 0,
+            total_playtime: 0,
+            last_played: None,
+            core_override: None,
+            crc32: None,
+            md5: None,
+            sha1: None,
+            verified: 0,
+            region: None,
+            revision: None,
+            base_game_id: None,
+            soft_patch: None,
+        }
+    }
+
+    fn shell(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    // The session recorded once the supervising task has seen the process exit
+    async fn recorded_session(supervisor: &ProcessSupervisor) -> PlaySession {
+        for _ in 0..250 {
+            let sessions = supervisor.db.get_play_sessions(Some("g1"), None, None, 10).await.unwrap();
+            if let Some(session) = sessions.into_iter().next() {
+                return session;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("no play session was recorded");
+    }
+
+    #[tokio::test]
+    async fn records_a_session_when_the_game_exits() {
+        let (supervisor, dir) = test_supervisor("exit").await;
+        let mut cmd = Command::new(write_stub_emulator(&dir));
+        cmd.arg("game.sfc").env("STUB_OUT", dir.join("args.bin"));
+
+        let info = supervisor.spawn("g1", "Game", Some("snes9x".to_string()), cmd).unwrap();
+        assert!(info.pid.is_some());
+
+        let session = recorded_session(&supervisor).await;
+        assert_eq!((session.exit_status.as_str(), session.exit_code), ("exited", Some(0)));
+        assert_eq!(session.core.as_deref(), Some("snes9x"));
+        assert_eq!(session.started_at, info.started_at);
+        assert_eq!(std::fs::read(dir.join("args.bin")).unwrap(), b"game.sfc\0");
+        assert!(!supervisor.is_running("g1"));
+    }
+
+    #[tokio::test]
+    async fn non_zero_exit_codes_are_not_crashes() {
+        let (supervisor, _dir) = test_supervisor("exit-code").await;
+        supervisor.spawn("g1", "Game", None, shell("exit 3")).unwrap();
+
+        let session = recorded_session(&supervisor).await;
+        assert_eq!((session.exit_status.as_str(), session.exit_code), ("exited", Some(3)));
+    }
+
+    #[tokio::test]
+    async fn killed_games_are_crashes() {
+        let (supervisor, _dir) = test_supervisor("signal").await;
+        supervisor.spawn("g1", "Game", None, shell("kill -9 $$")).unwrap();
+
+        let session = recorded_session(&supervisor).await;
+        assert_eq!((session.exit_status.as_str(), session.exit_code), ("crashed", None));
+    }
+
+    #[tokio::test]
+    async fn refuses_a_second_launch_and_stops_the_first() {
+        let (supervisor, _dir) = test_supervisor("stop").await;
+        supervisor.spawn("g1", "Game", None, shell("sleep 30")).unwrap();
+
+        let err = supervisor.spawn("g1", "Game", None, shell("sleep 30")).unwrap_err();
+        assert_eq!(err, "'Game' is already running");
+        assert_eq!(supervisor.running_games().len(), 1);
+        assert!(supervisor.stop("g2").is_err());
+
+        supervisor.stop("g1").unwrap();
+        assert_eq!(supervisor.stop("g1").unwrap_err(), "'Game' is already stopping");
+
+        let session = recorded_session(&supervisor).await;
+        assert_eq!(session.exit_status, "stopped");
+        assert!(session.duration < 30);
+        assert!(!supervisor.is_running("g1"));
+        assert_eq!(supervisor.db.get_game("g1").await.unwrap().total_playtime, session.duration);
+    }
+}
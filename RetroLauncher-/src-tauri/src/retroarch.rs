@@ -4,13 +4,179 @@ use std::fs;
 use std::io::Write;
 use std::env;
 use tauri::Manager;
-use futures_util::StreamExt;
-use std::time::{Instant, Duration};
+
+use crate::archive::{self, ExtractOptions};
+use crate::downloader::{self, DownloadOptions, ProgressEvent};
+use crate::error::AppError;
 
 const RETROARCH_VERSION: &str = "1.21.0";
-const RETROARCH_DOWNLOAD_URL: &str = "https://buildbot.libretro.com/stable/1.21.0/windows/x86_64/RetroArch.7z";
-const CORES_BASE_URL: &str = "https://buildbot.libretro.com/stable/1.21.0/windows/x86_64/latest/cores";
-const CORES_PACK_URL: &str = "https://buildbot.libretro.com/stable/1.21.0/windows/x86_64/RetroArch_cores.7z";
+const BUILDBOT_STABLE_URL: &str = "https://buildbot.libretro.com/stable/1.21.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetOs {
+    Windows,
+    Linux,
+    MacOs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    SevenZip,
+    Dmg,
+}
+
+// Describes how RetroArch is packaged and laid out on a given OS/arch.
+// Everything that used to hard-code retroarch.exe, the Win64 buildbot URLs or
+// `_libretro.dll` goes through this so Linux and macOS installs work the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetroArchTarget {
+    pub os: TargetOs,
+    pub arch: &'static str,
+}
+
+impl RetroArchTarget {
+    // Target for the OS/arch this binary was built for
+    pub fn current() -> Self {
+        let os = if cfg!(target_os = "windows") {
+            TargetOs::Windows
+        } else if cfg!(target_os = "macos") {
+            TargetOs::MacOs
+        } else {
+            TargetOs::Linux
+        };
+        RetroArchTarget { os, arch: env::consts::ARCH }
+    }
+
+    // Buildbot directory for this target (e.g. "windows/x86_64", "apple/osx/arm64")
+    fn buildbot_dir(&self) -> String {
+        match self.os {
+            TargetOs::Windows => format!("windows/{}", self.arch),
+            TargetOs::Linux => format!("linux/{}", self.arch),
+            TargetOs::MacOs => {
+                let arch = if self.arch == "aarch64" { "arm64" } else { self.arch };
+                format!("apple/osx/{}", arch)
+            }
+        }
+    }
+
+    pub fn archive_kind(&self) -> ArchiveKind {
+        match self.os {
+            TargetOs::MacOs => ArchiveKind::Dmg,
+            _ => ArchiveKind::SevenZip,
+        }
+    }
+
+    // File name the RetroArch archive is saved under in the downloads directory
+    pub fn retroarch_archive_name(&self) -> &'static str {
+        match self.archive_kind() {
+            ArchiveKind::SevenZip => "RetroArch.7z",
+            ArchiveKind::Dmg => "RetroArch.dmg",
+        }
+    }
+
+    pub fn retroarch_download_url(&self) -> String {
+        match self.os {
+            // macOS builds are universal binaries shipped as a single dmg
+            TargetOs::MacOs => format!("{}/apple/osx/universal/RetroArch_Metal.dmg", BUILDBOT_STABLE_URL),
+            _ => format!("{}/{}/RetroArch.7z", BUILDBOT_STABLE_URL, self.buildbot_dir()),
+        }
+    }
+
+    pub fn cores_base_url(&self) -> String {
+        format!("{}/{}/latest/cores", BUILDBOT_STABLE_URL, self.buildbot_dir())
+    }
+
+    // The buildbot only publishes a full cores pack for Windows and Linux
+    pub fn cores_pack_url(&self) -> Option<String> {
+        match self.os {
+            TargetOs::MacOs => None,
+            _ => Some(format!("{}/{}/RetroArch_cores.7z", BUILDBOT_STABLE_URL, self.buildbot_dir())),
+        }
+    }
+
+    // Top-level directory inside the RetroArch archive (moved up after extraction)
+    pub fn archive_root_dir(&self) -> Option<String> {
+        match self.os {
+            TargetOs::Windows => Some(if self.arch == "x86" { "RetroArch-Win32" } else { "RetroArch-Win64" }.to_string()),
+            TargetOs::Linux => Some(format!("RetroArch-Linux-{}", self.arch)),
+            TargetOs::MacOs => None,
+        }
+    }
+
+    // Path of the executable relative to the retroarch install directory
+    pub fn executable_relative_path(&self) -> PathBuf {
+        match self.os {
+            TargetOs::Windows => PathBuf::from("retroarch.exe"),
+            TargetOs::Linux => PathBuf::from(format!("RetroArch-Linux-{}.AppImage", self.arch)),
+            TargetOs::MacOs => Path::new("RetroArch.app").join("Contents").join("MacOS").join("RetroArch"),
+        }
+    }
+
+    pub fn core_extension(&self) -> &'static str {
+        match self.os {
+            TargetOs::Windows => "dll",
+            TargetOs::Linux => "so",
+            TargetOs::MacOs => "dylib",
+        }
+    }
+
+    // e.g. "snes9x" -> "snes9x_libretro.so"
+    pub fn core_filename(&self, core_id: &str) -> String {
+        format!("{}_libretro.{}", core_id, self.core_extension())
+    }
+
+    // e.g. "snes9x_libretro.so" -> Some("snes9x"), None for files of other targets
+    pub fn core_id_from_filename(&self, filename: &str) -> Option<String> {
+        filename
+            .strip_suffix(&format!("_libretro.{}", self.core_extension()))
+            .map(|id| id.to_string())
+    }
+
+    pub fn is_core_file(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case(self.core_extension()))
+            .unwrap_or(false)
+    }
+
+    // Rewrite a core filename from the predefined (Windows) table for this target
+    fn localize_core_filename(&self, filename: &str) -> String {
+        match filename.strip_suffix("_libretro.dll") {
+            Some(id) => self.core_filename(id),
+            None => filename.to_string(),
+        }
+    }
+}
+
+// Path to the RetroArch executable inside the install directory for the current target
+pub fn retroarch_executable(retroarch_path: &Path) -> PathBuf {
+    retroarch_path.join(RetroArchTarget::current().executable_relative_path())
+}
+
+// Build the RetroArch launch command as a plain argv on every OS (no `sh -c`),
+// so the ROM path is never interpreted by a shell. A soft patch is passed with
+// --ips/--ups/--bps so RetroArch applies it in memory.
+pub fn launch_command(
+    retroarch_exe: &Path,
+    core_path: &Path,
+    rom_path: &Path,
+    soft_patch: Option<&Path>,
+    fullscreen: bool,
+) -> std::process::Command {
+    let mut cmd = std::process::Command::new(retroarch_exe);
+    if fullscreen {
+        cmd.arg("--fullscreen");
+    }
+    if let Some(patch) = soft_patch {
+        let extension = patch.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        cmd.arg(format!("--{}", extension));
+        cmd.arg(patch);
+    }
+    cmd.arg("-L");
+    cmd.arg(core_path);
+    cmd.arg(rom_path);
+    cmd
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetroArchStatus {
@@ -35,7 +201,8 @@ pub struct RetroArchCore {
 
 // Get all available cores mapped by platform
 pub fn get_available_cores() -> Vec<RetroArchCore> {
-    vec![
+    let target = RetroArchTarget::current();
+    let cores = vec![
         // NES
         RetroArchCore {
             id: "nestopia".to_string(),
@@ -227,10 +394,22 @@ pub fn get_available_cores() -> Vec<RetroArchCore> {
             recommended: true,
             alt_filenames: None,
         },
-    ]
+    ];
+
+    // The table above uses Windows filenames; adapt them to the running target
+    cores
+        .into_iter()
+        .map(|mut core| {
+            core.filename = target.localize_core_filename(&core.filename);
+            core.alt_filenames = core.alt_filenames
+                .map(|alts| alts.iter().map(|f| target.localize_core_filename(f)).collect());
+            core
+        })
+        .collect()
 }
 
 // Get cores for a specific platform
+#[allow(dead_code)]
 pub fn get_cores_for_platform(platform: &str, retroarch_path: &Path) -> Vec<RetroArchCore> {
     let mut cores = get_available_cores()
         .into_iter()
@@ -248,6 +427,7 @@ pub fn get_cores_for_platform(platform: &str, retroarch_path: &Path) -> Vec<Retr
 }
 
 // Get all installed cores
+#[allow(dead_code)]
 pub fn get_installed_cores(retroarch_path: &Path) -> Vec<RetroArchCore> {
     let mut all_cores = get_available_cores();
     let cores_dir = retroarch_path.join("cores");
@@ -263,7 +443,7 @@ pub fn get_installed_cores(retroarch_path: &Path) -> Vec<RetroArchCore> {
 // Check if RetroArch is installed
 pub fn check_retroarch_status(app_dir: &Path) -> RetroArchStatus {
     let retroarch_path = app_dir.join("retroarch");
-    let exe_path = retroarch_path.join("retroarch.exe");
+    let exe_path = retroarch_executable(&retroarch_path);
 
     if exe_path.exists() {
         RetroArchStatus {
@@ -281,52 +461,38 @@ pub fn check_retroarch_status(app_dir: &Path) -> RetroArchStatus {
 }
 
 // Download RetroArch portable
-pub async fn download_retroarch(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+pub async fn download_retroarch(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, AppError> {
     let download_dir = app_dir.join("downloads");
     fs::create_dir_all(&download_dir)?;
 
-    let file_path = download_dir.join("RetroArch.7z");
-
-    println!("Downloading RetroArch from: {}", RETROARCH_DOWNLOAD_URL);
-
-    // Stream download with progress
-    let mut resp = reqwest::get(RETROARCH_DOWNLOAD_URL).await?;
-    let total = resp.content_length().unwrap_or(0);
-    let mut file = fs::File::create(&file_path)?;
-    let mut downloaded: u64 = 0;
-    let start = Instant::now();
-    let mut last_emit = Instant::now();
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let data = chunk?;
-        file.write_all(&data)?;
-        downloaded += data.len() as u64;
-        if last_emit.elapsed() >= Duration::from_millis(120) {
-            if let Some(handle) = app_handle {
-                let speed_bps = (downloaded as f64 / start.elapsed().as_secs_f64().max(0.001)) as f64;
-                let pct = if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 };
-                let _ = handle.emit_all("download-progress", serde_json::json!({
-                    "slug": "retroarch",
-                    "stage": "downloading_retroarch",
-                    "progress": pct,
-                    "message": "Downloading RetroArch...",
-                    "bytesReceived": downloaded,
-                    "totalBytes": total,
-                    "speedBps": speed_bps,
-                    "title": "RetroArch",
-                }));
-            }
-            last_emit = Instant::now();
-        }
-    }
+    let target = RetroArchTarget::current();
+    let download_url = target.retroarch_download_url();
+    let file_path = download_dir.join(target.retroarch_archive_name());
+
+    println!("Downloading RetroArch from: {}", download_url);
+
+    downloader::download_file(
+        &download_url,
+        &file_path,
+        &DownloadOptions::default(),
+        &ProgressEvent {
+            slug: "retroarch".to_string(),
+            stage: "downloading_retroarch".to_string(),
+            message: "Downloading RetroArch...".to_string(),
+            title: Some("RetroArch".to_string()),
+        },
+        app_handle,
+    ).await?;
 
     println!("Downloaded RetroArch to: {:?}", file_path);
 
     Ok(file_path)
 }
 
-// Extract RetroArch using 7z
-pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+// Extract RetroArch (7z on Windows/Linux, dmg on macOS). Blocking: call it from
+// spawn_blocking in async code.
+pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, AppError> {
+    let target = RetroArchTarget::current();
     let extract_dir = app_dir.join("retroarch");
 
     // Remove existing installation
@@ -338,7 +504,6 @@ pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option
 
     println!("Extracting RetroArch to: {:?}", extract_dir);
 
-    // Use sevenz_rust crate to extract
     if let Some(handle) = app_handle { let _ = handle.emit_all("download-progress", serde_json::json!({
         "slug": "retroarch",
         "stage": "extracting_retroarch",
@@ -346,12 +511,23 @@ pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option
         "message": "Extracting RetroArch...",
         "title": "RetroArch",
     })); }
-    sevenz_rust::decompress_file(archive_path, &extract_dir)
-        .map_err(|e| format!("Failed to extract 7z archive: {}", e))?;
+    match target.archive_kind() {
+        ArchiveKind::SevenZip => {
+            let event = ProgressEvent {
+                slug: "retroarch".to_string(),
+                stage: "extracting_retroarch".to_string(),
+                message: "Extracting RetroArch...".to_string(),
+                title: Some("RetroArch".to_string()),
+            };
+            archive::extract_archive(archive_path, &extract_dir, &ExtractOptions::default(), &event, app_handle)
+                .map_err(|e| format!("Failed to extract 7z archive: {}", e))?;
+        }
+        ArchiveKind::Dmg => copy_app_from_dmg(archive_path, &extract_dir)?,
+    }
 
-    // The archive extracts to a "RetroArch-Win64" subdirectory, move contents up
-    let inner_dir = extract_dir.join("RetroArch-Win64");
-    if inner_dir.exists() {
+    // The archive extracts to a target-specific subdirectory (e.g. "RetroArch-Win64"), move contents up
+    let inner_dir = target.archive_root_dir().map(|dir| extract_dir.join(dir));
+    if let Some(inner_dir) = inner_dir.filter(|dir| dir.exists()) {
         // Move all files from inner_dir to extract_dir
         for entry in fs::read_dir(&inner_dir)? {
             let entry = entry?;
@@ -369,6 +545,18 @@ pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option
         fs::remove_dir(&inner_dir)?;
     }
 
+    // AppImages lose their executable bit inside the 7z archive
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let exe_path = extract_dir.join(target.executable_relative_path());
+        if exe_path.exists() {
+            let mut perms = fs::metadata(&exe_path)?.permissions();
+            perms.set_mode(perms.mode() | 0o755);
+            fs::set_permissions(&exe_path, perms)?;
+        }
+    }
+
     if let Some(handle) = app_handle { let _ = handle.emit_all("download-progress", serde_json::json!({
         "slug": "retroarch",
         "stage": "completed",
@@ -380,8 +568,41 @@ pub fn extract_retroarch(archive_path: &Path, app_dir: &Path, app_handle: Option
     Ok(extract_dir)
 }
 
+// Mount a dmg with hdiutil and copy RetroArch.app out of it
+fn copy_app_from_dmg(dmg_path: &Path, extract_dir: &Path) -> Result<(), AppError> {
+    use std::process::Command;
+
+    let mount_point = env::temp_dir().join(format!("retrolauncher-dmg-{}", std::process::id()));
+    fs::create_dir_all(&mount_point)?;
+
+    let status = Command::new("hdiutil")
+        .arg("attach")
+        .arg("-nobrowse")
+        .arg("-readonly")
+        .arg("-mountpoint")
+        .arg(&mount_point)
+        .arg(dmg_path)
+        .status()
+        .map_err(|e| AppError::RetroArch(format!("Failed to run hdiutil: {}", e)))?;
+    if !status.success() {
+        return Err(AppError::RetroArch(format!("Failed to mount RetroArch dmg: {}", dmg_path.display())));
+    }
+
+    let app_bundle = mount_point.join("RetroArch.app");
+    let copy_result = if app_bundle.exists() {
+        copy_dir_all(&app_bundle, &extract_dir.join("RetroArch.app")).map_err(AppError::from)
+    } else {
+        Err(AppError::RetroArch("RetroArch.app not found in dmg".to_string()))
+    };
+
+    let _ = Command::new("hdiutil").arg("detach").arg(&mount_point).arg("-quiet").status();
+    let _ = fs::remove_dir(&mount_point);
+
+    copy_result
+}
+
 // Download the official cores pack (contains all cores) into app downloads dir
-async fn download_cores_pack(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+async fn download_cores_pack(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, AppError> {
     let download_dir = app_dir.join("downloads");
     fs::create_dir_all(&download_dir)?;
     let archive_path = download_dir.join("RetroArch_cores.7z");
@@ -392,45 +613,29 @@ async fn download_cores_pack(app_dir: &Path, app_handle: Option<&tauri::AppHandl
         return Ok(archive_path);
     }
 
-    println!("Downloading RetroArch cores pack from: {}", CORES_PACK_URL);
-    let mut resp = reqwest::get(CORES_PACK_URL).await?;
-    if !resp.status().is_success() {
-        return Err(format!("Failed to download cores pack (HTTP {}): {}", resp.status(), CORES_PACK_URL).into());
-    }
-    let total = resp.content_length().unwrap_or(0);
-    let mut f = fs::File::create(&archive_path)?;
-    let mut downloaded: u64 = 0;
-    let start = Instant::now();
-    let mut last_emit = Instant::now();
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let data = chunk?;
-        f.write_all(&data)?;
-        downloaded += data.len() as u64;
-        if last_emit.elapsed() >= Duration::from_millis(150) {
-            if let Some(handle) = app_handle {
-                let speed_bps = (downloaded as f64 / start.elapsed().as_secs_f64().max(0.001)) as f64;
-                let pct = if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 };
-                let _ = handle.emit_all("download-progress", serde_json::json!({
-                    "slug": "cores_pack",
-                    "stage": "downloading_cores",
-                    "progress": pct,
-                    "message": "Downloading cores pack...",
-                    "bytesReceived": downloaded,
-                    "totalBytes": total,
-                    "speedBps": speed_bps,
-                    "title": "RetroArch Cores",
-                }));
-            }
-            last_emit = Instant::now();
-        }
-    }
+    let cores_pack_url = RetroArchTarget::current()
+        .cores_pack_url()
+        .ok_or_else(|| AppError::RetroArch("No cores pack is published for this platform".to_string()))?;
+
+    println!("Downloading RetroArch cores pack from: {}", cores_pack_url);
+    downloader::download_file(
+        &cores_pack_url,
+        &archive_path,
+        &DownloadOptions::default(),
+        &ProgressEvent {
+            slug: "cores_pack".to_string(),
+            stage: "downloading_cores".to_string(),
+            message: "Downloading cores pack...".to_string(),
+            title: Some("RetroArch Cores".to_string()),
+        },
+        app_handle,
+    ).await?;
     println!("Cores pack downloaded successfully");
     Ok(archive_path)
 }
 
 // Ensure cores pack is extracted once into an application cache directory and return that path
-fn ensure_cores_cache_extracted(app_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+fn ensure_cores_cache_extracted(app_dir: &Path) -> Result<PathBuf, AppError> {
     let cache_dir = app_dir.join("cache").join("retroarch_cores");
     let marker = cache_dir.join(".extracted");
     if marker.exists() {
@@ -450,12 +655,13 @@ fn ensure_cores_cache_extracted(app_dir: &Path) -> Result<PathBuf, Box<dyn std::
 }
 
 // Extract all cores from the official pack directly into retroarch/cores
-fn extract_cores_pack(archive_path: &Path, retroarch_path: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<(), Box<dyn std::error::Error>> {
+#[allow(dead_code)]
+fn extract_cores_pack(archive_path: &Path, retroarch_path: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<(), AppError> {
     let cores_dir = retroarch_path.join("cores");
     fs::create_dir_all(&cores_dir)?;
     println!("Extracting cores pack to: {:?}", cores_dir);
     // The 7z file contains many DLLs; extract into cores directory
-    sevenz_rust::decompress_file(archive_path, &cores_dir)
+    archive::extract_archive(archive_path, &cores_dir, &ExtractOptions::default(), &cores_pack_extract_event(), app_handle)
         .map_err(|e| format!("Failed to extract cores pack: {}", e))?;
     if let Some(handle) = app_handle { let _ = handle.emit_all("download-progress", serde_json::json!({
         "slug": "cores_pack",
@@ -467,8 +673,18 @@ fn extract_cores_pack(archive_path: &Path, retroarch_path: &Path, app_handle: Op
     Ok(())
 }
 
+fn cores_pack_extract_event() -> ProgressEvent {
+    ProgressEvent {
+        slug: "cores_pack".to_string(),
+        stage: "extracting_cores".to_string(),
+        message: "Extracting cores pack...".to_string(),
+        title: Some("RetroArch Cores".to_string()),
+    }
+}
+
 // Download and install a core
-pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<String, Box<dyn std::error::Error>> {
+pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<String, AppError> {
+    let target = RetroArchTarget::current();
     let cores = get_available_cores();
     // Try to find in predefined list first
     let predefined = cores.iter().find(|c| c.id == core_id).cloned();
@@ -478,7 +694,7 @@ pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Opti
         core
     } else {
         // Fallback: infer from id (detected cores from Ressource/)
-        let filename = format!("{}_libretro.dll", core_id);
+        let filename = target.core_filename(core_id);
         let (platform, platform_name, base_desc) = detect_platform_from_core_name(core_id);
         RetroArchCore {
             id: core_id.to_string(),
@@ -503,57 +719,42 @@ pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Opti
     }
 
     // Try individual core zip first (some cores may still be accessible this way)
-    let core_zip_url = format!("{}/{}.zip", CORES_BASE_URL, core.filename);
+    let core_zip_url = format!("{}/{}.zip", target.cores_base_url(), core.filename);
     println!("Attempting individual core download: {}", core_zip_url);
-    let try_individual = reqwest::get(&core_zip_url).await;
-    let mut used_pack = false;
-    if let Ok(resp) = try_individual {
-        if resp.status().is_success() {
-            // stream the individual core zip and emit progress
-            let total = resp.content_length().unwrap_or(0);
-            let mut stream = resp.bytes_stream();
-            let temp_zip = cores_dir.join(format!("{}.zip", core.id));
-            let mut f = fs::File::create(&temp_zip)?;
-            let mut downloaded: u64 = 0;
-            let start = Instant::now();
-            let mut last_emit = Instant::now();
-            while let Some(chunk) = stream.next().await {
-                let data = chunk?;
-                f.write_all(&data)?;
-                downloaded += data.len() as u64;
-                if last_emit.elapsed() >= Duration::from_millis(120) {
-                    if let Some(handle) = app_handle {
-                        let speed_bps = (downloaded as f64 / start.elapsed().as_secs_f64().max(0.001)) as f64;
-                        let pct = if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 };
-                        let _ = handle.emit_all("download-progress", serde_json::json!({
-                            "slug": format!("core:{}", core.id),
-                            "stage": "downloading_core",
-                            "progress": pct,
-                            "message": format!("Downloading core {}...", core.name),
-                            "bytesReceived": downloaded,
-                            "totalBytes": total,
-                            "speedBps": speed_bps,
-                            "title": format!("Core {}", core.name),
-                        }));
-                    }
-                    last_emit = Instant::now();
-                }
-            }
-            // Extract dll from the zip
-            let zip_file = fs::File::open(&temp_zip)?;
-            let mut archive = zip::ZipArchive::new(zip_file)?;
+    let temp_zip = cores_dir.join(format!("{}.zip", core.id));
+    let try_individual = downloader::download_file(
+        &core_zip_url,
+        &temp_zip,
+        &DownloadOptions::default(),
+        &ProgressEvent {
+            slug: format!("core:{}", core.id),
+            stage: "downloading_core".to_string(),
+            message: format!("Downloading core {}...", core.name),
+            title: Some(format!("Core {}", core.name)),
+        },
+        app_handle,
+    ).await;
+    let used_pack = match try_individual {
+        Ok(_) => {
+            // Extract the zip next to the cores and keep only the core library
+            let temp_dir = cores_dir.join(format!(".{}-extract", core.id));
+            let event = ProgressEvent {
+                slug: format!("core:{}", core.id),
+                stage: "extracting_core".to_string(),
+                message: format!("Extracting core {}...", core.name),
+                title: Some(format!("Core {}", core.name)),
+            };
+            let (zip, dest, handle) = (temp_zip.clone(), temp_dir.clone(), app_handle.cloned());
+            let files = tokio::task::spawn_blocking(move || {
+                archive::extract_archive(&zip, &dest, &ExtractOptions::default(), &event, handle.as_ref())
+            }).await??;
             let mut extracted = false;
-            for i in 0..archive.len() {
-                let mut zf = archive.by_index(i)?;
-                let name = zf.name().to_string();
-                if name.to_lowercase().ends_with(".dll") {
-                    println!("Found DLL in individual core zip: {}", name);
-                    let mut out = fs::File::create(&target_core_path)?;
-                    std::io::copy(&mut zf, &mut out)?;
-                    extracted = true;
-                    break;
-                }
+            if let Some(core_file) = files.iter().find(|f| target.is_core_file(f)) {
+                println!("Found core library in individual core zip: {:?}", core_file);
+                fs::copy(core_file, &target_core_path)?;
+                extracted = true;
             }
+            fs::remove_dir_all(&temp_dir).ok();
             fs::remove_file(&temp_zip).ok();
             if extracted {
                 println!("Installed core via individual zip: {:?}", target_core_path);
@@ -565,26 +766,26 @@ pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Opti
                     "title": format!("Core {}", core.name),
                 })); }
                 return Ok(format!("Core {} installed successfully", core.name));
-            } else {
-                // No DLL in zip -> fallback to pack
-                println!("No DLL found in individual core zip, falling back to cores pack");
-                used_pack = true;
             }
-        } else {
-            used_pack = true; // fallback to pack
+            // No core library in zip -> fallback to pack
+            println!("No core library found in individual core zip, falling back to cores pack");
+            true
         }
-    } else {
-        used_pack = true; // network error on individual, try pack
-    }
+        Err(e) => {
+            // Not published individually or network error, try pack
+            println!("Individual core download failed ({}), falling back to cores pack", e);
+            true
+        }
+    };
 
-    // Fallback: download and reuse the official cores pack, then copy the single DLL needed
-    if used_pack {
+    // Fallback: download and reuse the official cores pack, then copy the single core needed
+    if used_pack && target.cores_pack_url().is_some() {
         // Prepare cache once
-        let app_dir = retroarch_path.parent().ok_or("Invalid retroarch path")?;
+        let app_dir = retroarch_path.parent().ok_or_else(|| AppError::RetroArch("Invalid retroarch path".to_string()))?;
         let _cache_dir = prepare_cores_cache(app_dir, app_handle).await?;
     }
 
-    // After extraction (or if pack already cached), the DLL may be inside a subfolder.
+    // After extraction (or if pack already cached), the core may be inside a subfolder.
     // Look for it inside the cache and copy to cores directory.
     if !target_core_path.exists() {
         let _ = install_core_from_cache(&core, retroarch_path)?;
@@ -601,15 +802,15 @@ pub async fn install_core(core_id: &str, retroarch_path: &Path, app_handle: Opti
         })); }
         Ok(format!("Core {} installed successfully", core.name))
     } else {
-        Err(format!(
+        Err(AppError::RetroArch(format!(
             "Failed to install core '{}'. File '{}' not found in cache/cores/resources. See logs for details.",
             core.name, core.filename
-        ).into())
+        )))
     }
 }
 
 // Prepare cores cache: download and extract the cores pack once, with events
-pub async fn prepare_cores_cache(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+pub async fn prepare_cores_cache(app_dir: &Path, app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf, AppError> {
     let pack_path = download_cores_pack(app_dir, app_handle).await?;
     let cache_dir = ensure_cores_cache_extracted(app_dir)?;
     let cache_marker = cache_dir.join(".extracted");
@@ -621,8 +822,11 @@ pub async fn prepare_cores_cache(app_dir: &Path, app_handle: Option<&tauri::AppH
             "message": "Extracting cores pack...",
             "title": "RetroArch Cores",
         })); }
-        sevenz_rust::decompress_file(&pack_path, &cache_dir)
-            .map_err(|e| format!("Failed to extract cores pack to cache: {}", e))?;
+        let (dest, handle) = (cache_dir.clone(), app_handle.cloned());
+        tokio::task::spawn_blocking(move || {
+            archive::extract_archive(&pack_path, &dest, &ExtractOptions::default(), &cores_pack_extract_event(), handle.as_ref())
+        }).await?
+            .map_err(|e| AppError::RetroArch(format!("Failed to extract cores pack to cache: {}", e)))?;
         let mut f = fs::File::create(&cache_marker)?;
         f.write_all(b"ok")?;
         if let Some(handle) = app_handle { let _ = handle.emit_all("download-progress", serde_json::json!({
@@ -637,13 +841,13 @@ pub async fn prepare_cores_cache(app_dir: &Path, app_handle: Option<&tauri::AppH
 }
 
 // Try to install a core by copying from cache/resources into retroarch/cores. Returns true if copied.
-pub fn install_core_from_cache(core: &RetroArchCore, retroarch_path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+pub fn install_core_from_cache(core: &RetroArchCore, retroarch_path: &Path) -> Result<bool, AppError> {
     let cores_dir = retroarch_path.join("cores");
     let target_core_path = cores_dir.join(&core.filename);
     if target_core_path.exists() {
         return Ok(true);
     }
-    let app_dir = retroarch_path.parent().ok_or("Invalid retroarch path")?;
+    let app_dir = retroarch_path.parent().ok_or_else(|| AppError::RetroArch("Invalid retroarch path".to_string()))?;
     let cache_dir = app_dir.join("cache").join("retroarch_cores");
     let mut candidates = vec![core.filename.clone()];
     if let Some(alts) = &core.alt_filenames { candidates.extend(alts.clone()); }
@@ -657,6 +861,7 @@ pub fn install_core_from_cache(core: &RetroArchCore, retroarch_path: &Path) -> R
 }
 
 // Get recommended core for a platform
+#[allow(dead_code)]
 pub fn get_recommended_core_for_platform(platform: &str) -> Option<String> {
     let cores = get_available_cores();
     cores.iter()
@@ -667,7 +872,7 @@ pub fn get_recommended_core_for_platform(platform: &str) -> Option<String> {
 // Get any installed core for a platform (preferred: recommended, fallback: any)
 pub fn get_any_installed_core_for_platform(platform: &str, retroarch_path: &Path) -> Option<RetroArchCore> {
     // Use full detection (predefined + detected in cores dir)
-    let mut cores = get_all_cores_with_detection(retroarch_path)
+    let cores = get_all_cores_with_detection(retroarch_path)
         .into_iter()
         .filter(|c| c.platform == platform && c.installed)
         .collect::<Vec<_>>();
@@ -681,6 +886,76 @@ pub fn get_any_installed_core_for_platform(platform: &str, retroarch_path: &Path
     cores.into_iter().next()
 }
 
+// Which rule picked the core used to launch a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreRule {
+    GameOverride,
+    PlatformDefault,
+    Recommended,
+    FirstInstalled,
+}
+
+impl CoreRule {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CoreRule::GameOverride => "per-game override",
+            CoreRule::PlatformDefault => "platform default",
+            CoreRule::Recommended => "recommended core",
+            CoreRule::FirstInstalled => "first installed core",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreResolution {
+    pub core: RetroArchCore,
+    pub rule: CoreRule,
+}
+
+// Resolve the core for a launch: per-game override, then per-platform default from settings,
+// then the recommended core, then any installed core for the platform.
+// An explicit override that is not installed is an error rather than a silent fallback.
+pub fn resolve_core_for_game(
+    platform: &str,
+    retroarch_path: &Path,
+    game_override: Option<&str>,
+    platform_default: Option<&str>,
+) -> Result<CoreResolution, AppError> {
+    let installed = get_all_cores_with_detection(retroarch_path)
+        .into_iter()
+        .filter(|c| c.installed)
+        .collect::<Vec<_>>();
+
+    let find_installed = |core_id: &str, rule: CoreRule| -> Result<CoreResolution, AppError> {
+        installed.iter()
+            .find(|c| c.id == core_id)
+            .map(|c| CoreResolution { core: c.clone(), rule })
+            .ok_or_else(|| AppError::RetroArch(format!(
+                "Core '{}' ({}) is not installed. Please install it from the Emulators tab.",
+                core_id, rule.describe()
+            )))
+    };
+
+    if let Some(core_id) = game_override.filter(|id| !id.is_empty()) {
+        return find_installed(core_id, CoreRule::GameOverride);
+    }
+
+    if let Some(core_id) = platform_default.filter(|id| !id.is_empty()) {
+        return find_installed(core_id, CoreRule::PlatformDefault);
+    }
+
+    get_any_installed_core_for_platform(platform, retroarch_path)
+        .map(|core| {
+            let rule = if core.recommended { CoreRule::Recommended } else { CoreRule::FirstInstalled };
+            CoreResolution { core, rule }
+        })
+        .ok_or_else(|| AppError::RetroArch(format!(
+            "No core installed for platform '{}'. Please install a core from the Emulators tab.",
+            platform
+        )))
+}
+
 // Helper function to detect platform from core name
 fn detect_platform_from_core_name(core_name: &str) -> (&str, &str, &str) {
     // Returns (platform_id, platform_name, description)
@@ -874,14 +1149,19 @@ pub fn scan_installed_cores_from_directory(cores_path: &Path, mark_as_installed:
         return detected_cores;
     }
 
-    // Read all .dll files in the cores directory
+    let target = RetroArchTarget::current();
+
+    // Read all core libraries (.dll/.so/.dylib depending on target) in the cores directory
     if let Ok(entries) = std::fs::read_dir(&cores_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("dll") {
+            if target.is_core_file(&path) {
                 if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-                    // Extract core name from filename (remove _libretro.dll)
-                    let core_id = filename.replace("_libretro.dll", "");
+                    // Extract core name from filename (remove _libretro.<ext>)
+                    let core_id = match target.core_id_from_filename(filename) {
+                        Some(id) => id,
+                        None => continue,
+                    };
                     let core_name = core_id.replace("_", " ");
 
                     // Check if this core is already in our predefined list
@@ -988,15 +1268,16 @@ fn find_any_file_recursive(root: &Path, candidates: &[String]) -> Option<PathBuf
     None
 }
 
-// Uninstall all cores by removing all DLLs from retroarch/cores. Optionally remove the extracted cache.
-pub fn uninstall_all_cores(retroarch_path: &Path, remove_cache: bool) -> Result<usize, Box<dyn std::error::Error>> {
+// Uninstall all cores by removing all core libraries from retroarch/cores. Optionally remove the extracted cache.
+pub fn uninstall_all_cores(retroarch_path: &Path, remove_cache: bool) -> Result<usize, AppError> {
+    let target = RetroArchTarget::current();
     let cores_dir = retroarch_path.join("cores");
     let mut removed = 0usize;
     if cores_dir.exists() {
         if let Ok(entries) = fs::read_dir(&cores_dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("dll") {
+                if target.is_core_file(&path) {
                     fs::remove_file(&path).ok();
                     removed += 1;
                 }
@@ -1033,13 +1314,14 @@ fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
 
 // Try to locate a local resources cores directory (useful for offline installs)
 fn get_resource_cores_dir() -> Option<PathBuf> {
+    let root_dir = RetroArchTarget::current().archive_root_dir()?;
     // Strategy: start from current executable directory and probe typical locations
     if let Ok(exe) = env::current_exe() {
         let mut cur = exe.parent().map(|p| p.to_path_buf());
-        // Walk up a few levels to find project root containing 'Ressource/RetroArch-Win64/cores'
+        // Walk up a few levels to find project root containing 'Ressource/RetroArch-<target>/cores'
         for _ in 0..5 {
             if let Some(dir) = &cur {
-                let candidate = dir.join("Ressource").join("RetroArch-Win64").join("cores");
+                let candidate = dir.join("Ressource").join(&root_dir).join("cores");
                 if candidate.exists() {
                     return Some(candidate);
                 }
@@ -1049,3 +1331,31 @@ fn get_resource_cores_dir() -> Option<PathBuf> {
     }
     None
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_support::{run_and_capture_args, write_stub_emulator};
+
+    #[test]
+    fn launch_command_does_not_go_through_a_shell() {
+        let dir = env::temp_dir().join(format!("retrolauncher-ra-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let stub = write_stub_emulator(&dir);
+        let core = dir.join("snes9x_libretro.so");
+
+        for name in ["Quote \" and 'single'.sfc", "$(touch pwned) `touch pwned`.sfc", "Pokémon Édition Rouge.gb"] {
+            let rom = dir.join(name);
+            let args = run_and_capture_args(launch_command(&stub, &core, &rom, None, true), &dir);
+            assert_eq!(args, vec![
+                "--fullscreen".to_string(),
+                "-L".to_string(),
+                core.to_string_lossy().to_string(),
+                rom.to_string_lossy().to_string(),
+            ]);
+        }
+        assert!(!dir.join("pwned").exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
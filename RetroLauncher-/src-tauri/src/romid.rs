@@ -0,0 +1,777 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::scanner::{extension_of, first_image};
+
+// Enough for a SNES HiROM header behind a 512-byte copier header, and the ISO9660
+// primary volume descriptor of a raw (2352-byte sector) image
+const HEADER_READ_SIZE: usize = 0x10200;
+
+// Logo every Game Boy boot ROM compares against
+const GB_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// How sure an identification is: a verified header beats an extension, which beats
+// the name of the folder the file sits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RomIdentity {
+    pub platform: &'static str,
+    pub confidence: Confidence,
+    pub internal_title: Option<String>,
+}
+
+impl RomIdentity {
+    fn new(platform: &'static str, confidence: Confidence, internal_title: Option<String>) -> Self {
+        RomIdentity { platform, confidence, internal_title }
+    }
+}
+
+// Identify a ROM or disc image from its header, then its extension, then its folder.
+// Sheets and playlists are identified by their first track or disc.
+pub fn identify(path: &Path) -> Option<RomIdentity> {
+    let identity = match extension_of(path).as_str() {
+        "cue" | "m3u" => first_image(path).and_then(|image| identify_image(&image)),
+        _ => identify_image(path),
+    };
+    identity.or_else(|| identify_folder(path))
+}
+
+fn identify_image(path: &Path) -> Option<RomIdentity> {
+    let ext = extension_of(path);
+    if ext == "zip" {
+        return identify_zip(path);
+    }
+    let from_header = read_header(path).and_then(|(buf, size)| identify_header(&buf, size, &ext));
+    let from_extension = identify_extension(&ext);
+    match (from_header, from_extension) {
+        (Some(header), Some(extension)) if extension.confidence > header.confidence => Some(extension),
+        (Some(header), _) => Some(header),
+        (None, extension) => extension,
+    }
+}
+
+fn read_header(path: &Path) -> Option<(Vec<u8>, u64)> {
+    let mut file = File::open(path).ok()?;
+    let mut buf = Vec::with_capacity(HEADER_READ_SIZE);
+    file.by_ref().take(HEADER_READ_SIZE as u64).read_to_end(&mut buf).ok()?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(buf.len() as u64);
+    Some((buf, size))
+}
+
+// Platform from the first bytes of a file. `size` is the full file size and `ext`
+// its lowercase extension, which only settles cases the header leaves open.
+pub fn identify_header(buf: &[u8], size: u64, ext: &str) -> Option<RomIdentity> {
+    let at = |offset: usize, magic: &[u8]| buf.get(offset..offset + magic.len()) == Some(magic);
+    let high = |platform, title| Some(RomIdentity::new(platform, Confidence::High, title));
+
+    if at(0, b"NES\x1A") || at(0, b"FDS\x1A") || at(0x1, b"*NINTENDO-HVC*") {
+        return high("nes", None);
+    }
+    if let Some(order) = n64_byte_order(buf) {
+        let title = buf.get(0x20..0x34).map(|raw| text(&order.to_big_endian(raw)));
+        return high("n64", title.flatten());
+    }
+    if buf.get(0xB2) == Some(&0x96) && gba_complement(buf).is_some_and(|c| buf.get(0xBD) == Some(&c)) {
+        return high("gba", buf.get(0xA0..0xAC).and_then(text));
+    }
+    if at(0x104, &GB_LOGO) && gb_header_checksum(buf).is_some_and(|c| buf.get(0x14D) == Some(&c)) {
+        let platform = match buf[0x143] {
+            0xC0 => "gbc",
+            // Dual-mode carts run on both; keep whatever the file says it is
+            0x80 if ext == "gbc" => "gbc",
+            _ => "gb",
+        };
+        // The last title bytes hold the manufacturer code and CGB flag on color carts
+        let end = if buf[0x143] & 0x80 != 0 { 0x13F } else { 0x144 };
+        return high(platform, text(&buf[0x134..end]));
+    }
+    // CRC16 of the DS header logo is fixed
+    if buf.get(0x15C..0x15E) == Some(&[0x56, 0xCF]) {
+        return high("nds", buf.get(0x00..0x0C).and_then(text));
+    }
+    if at(0, b"LYNX") {
+        return high("lynx", buf.get(0x0A..0x2A).and_then(text));
+    }
+    if at(0x1, b"ATARI7800") {
+        return high("atari7800", buf.get(0x11..0x31).and_then(text));
+    }
+
+    // Disc system areas, at the start of 2048-byte images or behind the sync and header
+    // of a raw first sector
+    for base in [0x0, 0x10] {
+        if at(base, b"SEGADISCSYSTEM") {
+            return high("segacd", title_of(buf, base + 0x150, 48).or_else(|| title_of(buf, base + 0x120, 48)));
+        }
+        if at(base, b"SEGA SEGASATURN") {
+            return high("saturn", title_of(buf, base + 0x60, 112));
+        }
+        if at(base, b"SEGA SEGAKATANA") {
+            return high("dreamcast", title_of(buf, base + 0x80, 128));
+        }
+    }
+    if at(0x1C, &[0xC2, 0x33, 0x9F, 0x3D]) {
+        return high("gamecube", title_of(buf, 0x20, 0x60));
+    }
+    if at(0x18, &[0x5D, 0x1C, 0x9E, 0xA3]) {
+        return high("wii", title_of(buf, 0x20, 0x60));
+    }
+
+    // ISO9660 primary volume descriptor: 2048-byte sectors, or raw Mode 1 / Mode 2 sectors
+    for pvd in [0x8000, 0x9310, 0x9318] {
+        if at(pvd, b"\x01CD001") {
+            let volume = title_of(buf, pvd + 40, 32);
+            if at(pvd + 8, b"PSP GAME") {
+                return high("psp", volume);
+            }
+            if at(pvd + 8, b"PLAYSTATION") {
+                // PS2 DVDs are far larger than a CD can hold
+                return high(if size > 900 * 1024 * 1024 { "ps2" } else { "ps1" }, volume);
+            }
+        }
+    }
+
+    if at(0x100, b"SEGA") {
+        let confidence = if at(0x100, b"SEGA MEGA DRIVE") || at(0x100, b"SEGA GENESIS") {
+            Confidence::High
+        } else {
+            Confidence::Medium
+        };
+        let platform = if at(0x100, b"SEGA 32X") { "32x" } else { "genesis" };
+        let title = title_of(buf, 0x150, 48).or_else(|| title_of(buf, 0x120, 48));
+        return Some(RomIdentity::new(platform, confidence, title));
+    }
+    for base in [0x7FF0, 0x3FF0, 0x1FF0] {
+        if at(base, b"TMR SEGA") {
+            // Region code in the high nibble of the last header byte: 5-7 are Game Gear
+            let platform = match buf.get(base + 0xF).map(|b| b >> 4) {
+                Some(5..=7) => "gg",
+                _ => "sms",
+            };
+            return high(platform, None);
+        }
+    }
+
+    snes_header(buf, size)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum N64ByteOrder {
+    // .z64, the native order
+    BigEndian,
+    // .v64, 16-bit words swapped
+    ByteSwapped,
+    // .n64, 32-bit words reversed
+    LittleEndian,
+}
+
+impl N64ByteOrder {
+    // Copy of `raw` in big-endian order. `raw` must start on a word boundary.
+    pub fn to_big_endian(self, raw: &[u8]) -> Vec<u8> {
+        let mut out = raw.to_vec();
+        self.make_big_endian(&mut out);
+        out
+    }
+
+    // Reorder whole 32-bit words in place; a trailing partial word is left alone
+    pub fn make_big_endian(self, words: &mut [u8]) {
+        let whole = words.len() / 4 * 4;
+        match self {
+            N64ByteOrder::BigEndian => {}
+            N64ByteOrder::ByteSwapped => words[..whole].chunks_exact_mut(2).for_each(|w| w.swap(0, 1)),
+            N64ByteOrder::LittleEndian => words[..whole].chunks_exact_mut(4).for_each(|w| w.reverse()),
+        }
+    }
+}
+
+// Reads an N64 ROM as big-endian (.z64) whatever order it was dumped in
+pub struct BigEndianReader<R> {
+    inner: R,
+    order: N64ByteOrder,
+}
+
+impl<R: Read> BigEndianReader<R> {
+    pub fn new(inner: R, order: N64ByteOrder) -> Self {
+        BigEndianReader { inner, order }
+    }
+}
+
+impl<R: Read> Read for BigEndianReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Fill whole words so each one can be reordered; only EOF leaves a partial one
+        let len = if buf.len() >= 4 { buf.len() / 4 * 4 } else { buf.len() };
+        let mut filled = 0;
+        while filled < len {
+            match self.inner.read(&mut buf[filled..len]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.order.make_big_endian(&mut buf[..filled]);
+        Ok(filled)
+    }
+}
+
+// Byte order of the N64 ROM at `path`, None for anything else
+pub fn n64_file_byte_order(path: &Path) -> Option<N64ByteOrder> {
+    let mut word = [0u8; 4];
+    File::open(path).ok()?.read_exact(&mut word).ok()?;
+    n64_byte_order(&word)
+}
+
+// Write a big-endian .z64 copy of a byte-swapped or little-endian N64 ROM next to it.
+// Returns the new path, or None when the ROM already is big-endian (or not an N64 ROM).
+// The original is removed unless `keep_original` is set; an existing .z64 is reused.
+pub fn convert_n64_to_z64(path: &Path, keep_original: bool) -> std::io::Result<Option<PathBuf>> {
+    let order = match n64_file_byte_order(path) {
+        Some(N64ByteOrder::BigEndian) | None => return Ok(None),
+        Some(order) => order,
+    };
+    let target = path.with_extension("z64");
+    // A misnamed .z64 can only be fixed by replacing it
+    let in_place = target == path;
+    if in_place && keep_original {
+        return Ok(None);
+    }
+    if in_place || !target.exists() {
+        write_z64(path, order, &target)?;
+    }
+    if !keep_original && !in_place {
+        std::fs::remove_file(path)?;
+    }
+    Ok(Some(target))
+}
+
+// Like convert_n64_to_z64, but the .z64 goes into `dir` and the original is never
+// touched. For ROMs in folders that belong to the user.
+pub fn copy_n64_to_z64(path: &Path, dir: &Path) -> std::io::Result<Option<PathBuf>> {
+    let order = match n64_file_byte_order(path) {
+        Some(N64ByteOrder::BigEndian) | None => return Ok(None),
+        Some(order) => order,
+    };
+    let file_name = path.file_name().ok_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "ROM path has no file name",
+    ))?;
+    let target = dir.join(file_name).with_extension("z64");
+    if !target.exists() {
+        std::fs::create_dir_all(dir)?;
+        write_z64(path, order, &target)?;
+    }
+    Ok(Some(target))
+}
+
+// Stream `path` into `target` in big-endian order through a .part file, so an
+// interrupted conversion never leaves a truncated .z64 behind
+fn write_z64(path: &Path, order: N64ByteOrder, target: &Path) -> std::io::Result<()> {
+    let partial = target.with_extension("z64.part");
+    let result = (|| {
+        let mut reader = BigEndianReader::new(std::io::BufReader::new(File::open(path)?), order);
+        let mut writer = std::io::BufWriter::new(File::create(&partial)?);
+        std::io::copy(&mut reader, &mut writer)?;
+        std::io::Write::flush(&mut writer)?;
+        std::fs::rename(&partial, target)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+// convert_n64_to_z64 off the async runtime. Falls back to the original path when the
+// ROM needs no conversion or converting fails, so the game can still be added.
+pub async fn normalize_n64_rom(path: PathBuf, keep_original: bool) -> PathBuf {
+    let source = path.clone();
+    normalized(path, tokio::task::spawn_blocking(move || convert_n64_to_z64(&source, keep_original)).await)
+}
+
+// copy_n64_to_z64 off the async runtime, with the same fallback
+pub async fn normalize_n64_copy(path: PathBuf, dir: PathBuf) -> PathBuf {
+    let source = path.clone();
+    normalized(path, tokio::task::spawn_blocking(move || copy_n64_to_z64(&source, &dir)).await)
+}
+
+fn normalized(
+    path: PathBuf,
+    result: Result<std::io::Result<Option<PathBuf>>, tokio::task::JoinError>,
+) -> PathBuf {
+    match result {
+        Ok(Ok(Some(z64))) => z64,
+        Ok(Ok(None)) => path,
+        Ok(Err(e)) => {
+            eprintln!("Failed to convert {} to .z64: {}", path.display(), e);
+            path
+        }
+        Err(e) => {
+            eprintln!("Failed to convert {} to .z64: {}", path.display(), e);
+            path
+        }
+    }
+}
+
+// Byte order of an N64 ROM, from the PI configuration word every cart starts with
+pub fn n64_byte_order(buf: &[u8]) -> Option<N64ByteOrder> {
+    match buf.get(0..4)? {
+        [0x80, 0x37, 0x12, 0x40] => Some(N64ByteOrder::BigEndian),
+        [0x37, 0x80, 0x40, 0x12] => Some(N64ByteOrder::ByteSwapped),
+        [0x40, 0x12, 0x37, 0x80] => Some(N64ByteOrder::LittleEndian),
+        _ => None,
+    }
+}
+
+fn gba_complement(buf: &[u8]) -> Option<u8> {
+    let sum = buf.get(0xA0..0xBD)?.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    Some(0u8.wrapping_sub(sum).wrapping_sub(0x19))
+}
+
+fn gb_header_checksum(buf: &[u8]) -> Option<u8> {
+    Some(buf.get(0x134..0x14D)?.iter().fold(0u8, |acc, b| acc.wrapping_sub(*b).wrapping_sub(1)))
+}
+
+// SNES carts have no magic number: score the LoROM and HiROM header locations (behind
+// an optional 512-byte copier header) on checksum, map mode and title, keep the best.
+fn snes_header(buf: &[u8], size: u64) -> Option<RomIdentity> {
+    let copier = if size % 1024 == 512 { 512 } else { 0 };
+    [(0x7FC0, 0x0), (0xFFC0, 0x1)]
+        .into_iter()
+        .filter_map(|(offset, map_bit)| {
+            let header = buf.get(copier + offset..copier + offset + 0x20)?;
+            let checksum = u16::from_le_bytes([header[0x1E], header[0x1F]]);
+            let complement = u16::from_le_bytes([header[0x1C], header[0x1D]]);
+            let title = text(&header[..0x15]);
+            let map_mode = header[0x15];
+
+            let mut score = 0;
+            if checksum ^ complement == 0xFFFF {
+                score += 4;
+            }
+            if map_mode & 0xE0 == 0x20 && map_mode & 0x01 == map_bit {
+                score += 2;
+            }
+            // ROM size byte: 2^n KiB, between 256 KiB and 8 MiB in practice
+            if (0x08..=0x0D).contains(&header[0x17]) {
+                score += 1;
+            }
+            if title.is_some() {
+                score += 1;
+            }
+            Some((score, title))
+        })
+        .max_by_key(|(score, _)| *score)
+        .and_then(|(score, title)| match score {
+            7.. => Some(RomIdentity::new("snes", Confidence::High, title)),
+            5..=6 => Some(RomIdentity::new("snes", Confidence::Medium, title)),
+            _ => None,
+        })
+}
+
+fn title_of(buf: &[u8], offset: usize, len: usize) -> Option<String> {
+    buf.get(offset..offset + len).and_then(text)
+}
+
+// Printable ASCII header text, trimmed of padding. None when blank or binary.
+fn text(raw: &[u8]) -> Option<String> {
+    let raw = match raw.iter().position(|b| *b == 0) {
+        Some(end) => &raw[..end],
+        None => raw,
+    };
+    if raw.iter().any(|b| !(0x20..0x7F).contains(b)) {
+        return None;
+    }
+    let title = String::from_utf8_lossy(raw).split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() { None } else { Some(title) }
+}
+
+// Extensions only one system uses
+pub fn identify_extension(ext: &str) -> Option<RomIdentity> {
+    let platform = match ext {
+        "nes" | "fds" | "unf" => "nes",
+        "sfc" | "smc" | "swc" | "fig" => "snes",
+        "n64" | "z64" | "v64" => "n64",
+        "gba" => "gba",
+        "gb" => "gb",
+        "gbc" => "gbc",
+        "nds" => "nds",
+        "3ds" | "cia" => "3ds",
+        "md" | "gen" | "smd" => "genesis",
+        "32x" => "32x",
+        "sms" => "sms",
+        "gg" => "gg",
+        "pce" | "sgx" => "pce",
+        "ngp" | "ngc" => "ngp",
+        "ws" | "wsc" => "wonderswan",
+        "a26" => "atari2600",
+        "a78" => "atari7800",
+        "lnx" => "lynx",
+        "vb" => "virtualboy",
+        "gdi" | "cdi" => "dreamcast",
+        "cso" | "pbp" => "psp",
+        "gcm" | "rvz" | "gcz" => "gamecube",
+        "wbfs" => "wii",
+        "wua" | "wux" | "rpx" => "wiiu",
+        _ => return None,
+    };
+    Some(RomIdentity::new(platform, Confidence::Medium, None))
+}
+
+// Use conventional collection folder names ("PSX", "Saturn"...) as a last resort.
+// Only for disc formats, whose extensions say nothing about the system.
+fn identify_folder(path: &Path) -> Option<RomIdentity> {
+    if !matches!(extension_of(path).as_str(), "bin" | "iso" | "img" | "cue" | "chd" | "m3u") {
+        return None;
+    }
+    path.ancestors().skip(1).find_map(|dir| {
+        let name = dir.file_name()?.to_string_lossy().to_lowercase();
+        let platform = match name.as_str() {
+            "psx" | "ps1" | "playstation" => "ps1",
+            "ps2" | "playstation 2" | "playstation2" => "ps2",
+            "psp" => "psp",
+            "saturn" | "sega saturn" => "saturn",
+            "segacd" | "sega cd" | "megacd" | "mega cd" => "segacd",
+            "dreamcast" | "dc" => "dreamcast",
+            "pce" | "pcecd" | "pc engine" | "turbografx" => "pce",
+            "gamecube" | "gc" | "ngc" => "gamecube",
+            "wii" => "wii",
+            _ => return None,
+        };
+        Some(RomIdentity::new(platform, Confidence::Low, None))
+    })
+}
+
+// Zipped cartridge ROMs: the most confident of the entries, read from their headers
+fn identify_zip(path: &Path) -> Option<RomIdentity> {
+    let file = File::open(path).ok()?;
+    let mut archive = zip::ZipArchive::new(std::io::BufReader::new(file)).ok()?;
+    let mut best: Option<RomIdentity> = None;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).ok()?;
+        let name = PathBuf::from(entry.name());
+        let ext = extension_of(&name);
+        // Disc images are too large to sniff inside an archive, and need their sheets
+        if entry.is_dir() || matches!(ext.as_str(), "zip" | "bin" | "iso" | "cue" | "img") {
+            continue;
+        }
+        let size = entry.size();
+        let mut buf = Vec::with_capacity(HEADER_READ_SIZE);
+        if entry.take(HEADER_READ_SIZE as u64).read_to_end(&mut buf).is_err() {
+            continue;
+        }
+        let identity = identify_header(&buf, size, &ext).or_else(|| identify_extension(&ext));
+        if let Some(identity) = identity {
+            if Some(identity.confidence) > best.as_ref().map(|b| b.confidence) {
+                best = Some(identity);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-romid-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn self_referencing_playlist_is_not_followed() {
+        let dir = test_dir("m3u-self");
+        let playlist = dir.join("Game.m3u");
+        fs::write(&playlist, "Game.m3u\n").unwrap();
+
+        assert_eq!(identify(&playlist), None);
+    }
+
+    #[test]
+    fn playlists_listing_each_other_are_not_followed() {
+        let dir = test_dir("m3u-cycle");
+        fs::write(dir.join("a.m3u"), "b.m3u\n").unwrap();
+        fs::write(dir.join("b.m3u"), "a.m3u\n").unwrap();
+        fs::write(dir.join("loop.cue"), "FILE \"loop.cue\" BINARY\n").unwrap();
+
+        assert_eq!(identify(&dir.join("a.m3u")), None);
+        assert_eq!(identify(&dir.join("loop.cue")), None);
+    }
+
+    #[test]
+    fn playlist_resolves_through_its_sheet_to_the_track() {
+        let dir = test_dir("m3u-cue").join("PSX");
+        fs::create_dir_all(&dir).unwrap();
+        let mut track = vec![0u8; 0x9400];
+        track[0x9310..0x9316].copy_from_slice(b"\x01CD001");
+        track[0x9318..0x9323].copy_from_slice(b"PLAYSTATION");
+        fs::write(dir.join("Game (Track 1).bin"), &track).unwrap();
+        fs::write(dir.join("Game.cue"), "FILE \"Game (Track 1).bin\" BINARY\n  TRACK 01 MODE2/2352\n").unwrap();
+        fs::write(dir.join("Game.m3u"), "# discs\nGame.cue\n").unwrap();
+
+        let identity = identify(&dir.join("Game.m3u")).unwrap();
+
+        assert_eq!(identity.platform, "ps1");
+        assert_eq!(identity.confidence, Confidence::High);
+    }
+
+    fn identity(platform: &'static str, confidence: Confidence, title: Option<&str>) -> Option<RomIdentity> {
+        Some(RomIdentity::new(platform, confidence, title.map(str::to_string)))
+    }
+
+    fn gb_rom(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0u8; 0x150];
+        rom[0x104..0x134].copy_from_slice(&GB_LOGO);
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x143] = cgb_flag;
+        rom[0x14D] = gb_header_checksum(&rom).unwrap();
+        rom
+    }
+
+    fn gba_rom(title: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0u8; 0xC0];
+        rom[0xA0..0xA0 + title.len()].copy_from_slice(title);
+        rom[0xB2] = 0x96;
+        rom[0xBD] = gba_complement(&rom).unwrap();
+        rom
+    }
+
+    fn snes_rom(copier: usize, offset: usize, map_mode: u8, checksum_ok: bool) -> Vec<u8> {
+        let mut rom = vec![0u8; copier + 0x10000];
+        let header = &mut rom[copier + offset..copier + offset + 0x20];
+        header[..0x15].copy_from_slice(b"SUPER TEST GAME      ");
+        header[0x15] = map_mode;
+        header[0x17] = 0x09;
+        let checksum: u16 = 0x1234;
+        let complement = if checksum_ok { !checksum } else { checksum };
+        header[0x1C..0x1E].copy_from_slice(&complement.to_le_bytes());
+        header[0x1E..0x20].copy_from_slice(&checksum.to_le_bytes());
+        rom
+    }
+
+    fn genesis_rom(system: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0x20u8; 0x200];
+        rom[0x100..0x100 + system.len()].copy_from_slice(system);
+        rom[0x150..0x15C].copy_from_slice(b"SONIC TEST  ");
+        rom
+    }
+
+    // Big-endian N64 header with a title, padded to whole words
+    fn z64_rom(len: usize) -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+        rom[0..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        rom[0x20..0x34].copy_from_slice(b"N64 TEST TITLE      ");
+        rom
+    }
+
+    #[test]
+    fn identifies_nes_headers() {
+        let mut rom = b"NES\x1A".to_vec();
+        rom.resize(0x4010, 0);
+        assert_eq!(identify_header(&rom, rom.len() as u64, "nes"), identity("nes", Confidence::High, None));
+
+        let mut fds = b"FDS\x1A".to_vec();
+        fds.resize(0x10, 0);
+        assert_eq!(identify_header(&fds, 16, "fds"), identity("nes", Confidence::High, None));
+    }
+
+    #[test]
+    fn identifies_n64_headers_in_every_byte_order() {
+        let z64 = z64_rom(0x40);
+        for order in [N64ByteOrder::BigEndian, N64ByteOrder::ByteSwapped, N64ByteOrder::LittleEndian] {
+            // Each reordering is its own inverse
+            let dumped = order.to_big_endian(&z64);
+            assert_eq!(n64_byte_order(&dumped), Some(order));
+            assert_eq!(
+                identify_header(&dumped, dumped.len() as u64, "n64"),
+                identity("n64", Confidence::High, Some("N64 TEST TITLE")),
+            );
+        }
+    }
+
+    #[test]
+    fn identifies_game_boy_headers_by_logo_and_checksum() {
+        let gb = gb_rom(b"TETRIS", 0x00);
+        assert_eq!(identify_header(&gb, 0x8000, "gb"), identity("gb", Confidence::High, Some("TETRIS")));
+
+        // Color carts end the title with a manufacturer code
+        let gbc = gb_rom(b"POKEMON CRYBYTE", 0xC0);
+        assert_eq!(identify_header(&gbc, 0x8000, "gb"), identity("gbc", Confidence::High, Some("POKEMON CRY")));
+
+        // Dual-mode carts follow the extension
+        let dual = gb_rom(b"ZELDA", 0x80);
+        assert_eq!(identify_header(&dual, 0x8000, "gbc").unwrap().platform, "gbc");
+        assert_eq!(identify_header(&dual, 0x8000, "gb").unwrap().platform, "gb");
+
+        let mut bad_checksum = gb.clone();
+        bad_checksum[0x14D] ^= 0xFF;
+        assert_eq!(identify_header(&bad_checksum, 0x8000, "gb"), None);
+    }
+
+    #[test]
+    fn identifies_gba_headers_by_complement() {
+        let rom = gba_rom(b"METROID4");
+        assert_eq!(identify_header(&rom, 0x800000, "gba"), identity("gba", Confidence::High, Some("METROID4")));
+
+        let mut bad_complement = rom.clone();
+        bad_complement[0xBD] ^= 0xFF;
+        assert_eq!(identify_header(&bad_complement, 0x800000, "gba"), None);
+    }
+
+    #[test]
+    fn identifies_genesis_headers() {
+        let rom = genesis_rom(b"SEGA MEGA DRIVE ");
+        assert_eq!(identify_header(&rom, 0x80000, "md"), identity("genesis", Confidence::High, Some("SONIC TEST")));
+
+        let genesis = genesis_rom(b"SEGA GENESIS    ");
+        assert_eq!(identify_header(&genesis, 0x80000, "bin").unwrap().confidence, Confidence::High);
+
+        // A bare "SEGA" is a weaker hint
+        let other = genesis_rom(b"SEGA            ");
+        assert_eq!(identify_header(&other, 0x80000, "bin").unwrap().confidence, Confidence::Medium);
+
+        let x32 = genesis_rom(b"SEGA 32X        ");
+        assert_eq!(identify_header(&x32, 0x80000, "32x").unwrap().platform, "32x");
+    }
+
+    #[test]
+    fn scores_snes_headers() {
+        let lorom = snes_rom(0, 0x7FC0, 0x20, true);
+        assert_eq!(
+            identify_header(&lorom, 0x100000, "sfc"),
+            identity("snes", Confidence::High, Some("SUPER TEST GAME")),
+        );
+
+        let hirom = snes_rom(0, 0xFFC0, 0x21, true);
+        assert_eq!(identify_header(&hirom, 0x100000, "sfc").unwrap().confidence, Confidence::High);
+
+        // Behind a 512-byte copier header, which only the file size reveals
+        let copier = snes_rom(512, 0x7FC0, 0x20, true);
+        assert_eq!(identify_header(&copier, 0x100200, "smc").unwrap().confidence, Confidence::High);
+        assert_eq!(identify_header(&copier, 0x100000, "smc"), None);
+
+        // A checksum pair and a plausible size make a guess; a wrong map mode is no proof
+        let wrong_map = snes_rom(0, 0x7FC0, 0x21, true);
+        assert_eq!(identify_header(&wrong_map, 0x100000, "sfc").unwrap().confidence, Confidence::Medium);
+
+        let no_checksum = snes_rom(0, 0x7FC0, 0x20, false);
+        assert_eq!(identify_header(&no_checksum, 0x100000, "sfc"), None);
+    }
+
+    #[test]
+    fn ranks_header_over_extension_over_folder() {
+        assert!(Confidence::High > Confidence::Medium && Confidence::Medium > Confidence::Low);
+
+        let dir = test_dir("confidence");
+        let psx = dir.join("PSX");
+        fs::create_dir_all(&psx).unwrap();
+
+        // A verified header wins over what the extension claims
+        let misnamed = dir.join("game.gba");
+        let mut nes = b"NES\x1A".to_vec();
+        nes.resize(0x4010, 0);
+        fs::write(&misnamed, &nes).unwrap();
+        assert_eq!(identify(&misnamed), identity("nes", Confidence::High, None));
+
+        // Without a header the extension decides
+        let headerless = psx.join("game.sfc");
+        fs::write(&headerless, [0u8; 0x100]).unwrap();
+        assert_eq!(identify(&headerless), identity("snes", Confidence::Medium, None));
+
+        // Disc images with neither fall back to the folder name
+        let disc = psx.join("game.bin");
+        fs::write(&disc, [0u8; 0x100]).unwrap();
+        assert_eq!(identify(&disc), identity("ps1", Confidence::Low, None));
+
+        let unknown = dir.join("game.bin");
+        fs::write(&unknown, [0u8; 0x100]).unwrap();
+        assert_eq!(identify(&unknown), None);
+    }
+
+    #[test]
+    fn converts_v64_and_n64_dumps_to_z64() {
+        let dir = test_dir("n64-convert");
+        // Larger than the copy buffer, so words are reordered across reads
+        let z64 = z64_rom(0x6000);
+
+        for (name, order) in [("swapped.v64", N64ByteOrder::ByteSwapped), ("reversed.n64", N64ByteOrder::LittleEndian)] {
+            let dumped = order.to_big_endian(&z64);
+            assert_ne!(dumped, z64);
+            let path = dir.join(name);
+            fs::write(&path, &dumped).unwrap();
+
+            let converted = convert_n64_to_z64(&path, true).unwrap().unwrap();
+
+            assert_eq!(converted, path.with_extension("z64"));
+            assert_eq!(fs::read(&converted).unwrap(), z64);
+            assert_eq!(fs::read(&path).unwrap(), dumped);
+            assert!(!path.with_extension("z64.part").exists());
+        }
+    }
+
+    #[test]
+    fn conversion_replaces_the_original_unless_kept() {
+        let dir = test_dir("n64-replace");
+        let z64 = z64_rom(0x100);
+        let path = dir.join("game.n64");
+        fs::write(&path, N64ByteOrder::LittleEndian.to_big_endian(&z64)).unwrap();
+
+        let converted = convert_n64_to_z64(&path, false).unwrap().unwrap();
+
+        assert_eq!(fs::read(&converted).unwrap(), z64);
+        assert!(!path.exists());
+
+        // A misnamed .z64 holding a .v64 dump is rewritten in place
+        let misnamed = dir.join("misnamed.z64");
+        fs::write(&misnamed, N64ByteOrder::ByteSwapped.to_big_endian(&z64)).unwrap();
+        assert_eq!(convert_n64_to_z64(&misnamed, true).unwrap(), None);
+        assert_eq!(convert_n64_to_z64(&misnamed, false).unwrap(), Some(misnamed.clone()));
+        assert_eq!(fs::read(&misnamed).unwrap(), z64);
+    }
+
+    #[test]
+    fn big_endian_and_other_files_are_not_converted() {
+        let dir = test_dir("n64-noop");
+        let z64 = dir.join("game.z64");
+        fs::write(&z64, z64_rom(0x100)).unwrap();
+        let other = dir.join("game.v64");
+        fs::write(&other, [0u8; 0x100]).unwrap();
+
+        assert_eq!(convert_n64_to_z64(&z64, false).unwrap(), None);
+        assert_eq!(convert_n64_to_z64(&other, false).unwrap(), None);
+        assert_eq!(copy_n64_to_z64(&z64, &dir.join("out")).unwrap(), None);
+        assert!(z64.exists() && other.exists());
+    }
+
+    #[test]
+    fn copies_leave_the_source_folder_alone() {
+        let dir = test_dir("n64-copy");
+        let roms = dir.join("roms");
+        fs::create_dir_all(&roms).unwrap();
+        let z64 = z64_rom(0x100);
+        let dumped = N64ByteOrder::ByteSwapped.to_big_endian(&z64);
+        let path = roms.join("game.v64");
+        fs::write(&path, &dumped).unwrap();
+
+        let copy = copy_n64_to_z64(&path, &dir.join("converted")).unwrap().unwrap();
+
+        assert_eq!(copy, dir.join("converted").join("game.z64"));
+        assert_eq!(fs::read(&copy).unwrap(), z64);
+        assert_eq!(fs::read(&path).unwrap(), dumped);
+        assert_eq!(fs::read_dir(&roms).unwrap().count(), 1);
+    }
+}
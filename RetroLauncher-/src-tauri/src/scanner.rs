@@ -0,0 +1,418 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::dat;
+use crate::database::Database;
+use crate::game::Game;
+use crate::multidisc;
+use crate::romid;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub files_scanned: usize,
+    pub added: usize,
+    pub duplicates: usize,
+    pub unrecognized: usize,
+    pub added_game_ids: Vec<String>,
+}
+
+// Walk `root` recursively, detect ROMs and add them to the library.
+// Files already in the library (same rom_path) are skipped.
+pub async fn scan_directory(
+    root: &Path,
+    db: &Database,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<ScanSummary, Box<dyn std::error::Error>> {
+    let emit_progress = |stage: &str, progress: f32, message: &str| {
+        if let Some(handle) = app_handle {
+            let _ = handle.emit_all("download-progress", serde_json::json!({
+                "slug": "scan_library",
+                "stage": stage,
+                "progress": progress,
+                "message": message,
+                "title": "Library Scan",
+            }));
+        }
+    };
+
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()).into());
+    }
+
+    emit_progress("scanning", 0.0, "Listing files...");
+    // Scanned folders belong to the user and are never written to: playlists for loose
+    // "(Disc N)" images and converted N64 ROMs go to app data. Without an app data dir
+    // each disc is imported on its own and N64 ROMs are left in their byte order.
+    let app_data_dir = app_handle.and_then(|handle| handle.path_resolver().app_data_dir());
+    let playlist_dir = app_data_dir.as_ref().map(|dir| dir.join("playlists"));
+    let converted_dir = app_data_dir.as_ref().map(|dir| dir.join("converted"));
+
+    // Walking and reading sheets is blocking filesystem work
+    let walk_root = root.to_path_buf();
+    let group_discs = playlist_dir.is_some();
+    let (files, referenced, disc_sets) = tokio::task::spawn_blocking(move || {
+        let files = collect_files(&walk_root);
+        let referenced = referenced_by_sheets(&files);
+        let disc_sets = if group_discs { loose_disc_sets(&files, &referenced) } else { Vec::new() };
+        (files, referenced, disc_sets)
+    })
+    .await?;
+    let in_disc_set: HashSet<&PathBuf> = disc_sets.iter().flatten().collect();
+
+    let normalize_n64 = db.get_normalize_n64().await?;
+
+    let mut summary = ScanSummary::default();
+    let total = files.len().max(1) as f32;
+    let mut last_emit = Instant::now();
+
+    for (index, path) in files.iter().enumerate() {
+        summary.files_scanned += 1;
+
+        if last_emit.elapsed() >= Duration::from_millis(120) {
+            let pct = (index as f32 / total) * 100.0;
+            emit_progress("scanning", pct, &format!("Scanned {} / {} files ({} added)", index, files.len(), summary.added));
+            last_emit = Instant::now();
+        }
+
+        // Track files of a .cue sheet and discs of an .m3u playlist are imported through
+        // the sheet or playlist itself
+        if referenced.contains(path) || in_disc_set.contains(path) {
+            continue;
+        }
+
+        let platform = match romid::identify(path) {
+            Some(identity) => identity.platform,
+            None => {
+                summary.unrecognized += 1;
+                continue;
+            }
+        };
+
+        let rom_file = match &converted_dir {
+            Some(dir) if normalize_n64 && platform == "n64" => {
+                romid::normalize_n64_copy(path.clone(), generated_dir(dir, path)).await
+            }
+            _ => path.clone(),
+        };
+
+        let rom_path = rom_file.to_string_lossy().to_string();
+        if db.rom_path_exists(&rom_path).await? {
+            summary.duplicates += 1;
+            continue;
+        }
+
+        let title = clean_title(&path.file_stem().unwrap_or_default().to_string_lossy());
+        let game = local_game(rom_path, title, platform);
+        let game_id = game.id.clone();
+        db.add_game(game).await?;
+        summary.added += 1;
+        summary.added_game_ids.push(game_id);
+    }
+
+    // One game per multi-disc set, launched through its playlist
+    if let Some(playlist_dir) = &playlist_dir {
+        for discs in &disc_sets {
+            let first = &discs[0];
+            let Some(identity) = romid::identify(first) else {
+                summary.unrecognized += 1;
+                continue;
+            };
+            let dir = generated_dir(playlist_dir, first);
+            let playlist = match std::fs::create_dir_all(&dir).and_then(|_| multidisc::write_m3u_in(&dir, discs)) {
+                Ok(playlist) => playlist,
+                Err(e) => {
+                    eprintln!("Failed to write playlist for {}: {}", first.display(), e);
+                    continue;
+                }
+            };
+
+            let rom_path = playlist.to_string_lossy().to_string();
+            if db.rom_path_exists(&rom_path).await? {
+                summary.duplicates += 1;
+                continue;
+            }
+
+            let title = clean_title(&multidisc::without_disc_tag(&first.file_name().unwrap_or_default().to_string_lossy()));
+            let game = local_game(rom_path, title, identity.platform);
+            let game_id = game.id.clone();
+            db.add_game(game).await?;
+            summary.added += 1;
+            summary.added_game_ids.push(game_id);
+        }
+    }
+
+    // Hash new games once everything is listed, and only when a DAT could match them
+    if !summary.added_game_ids.is_empty() && db.has_dat_files().await? {
+        let count = summary.added_game_ids.len();
+        for (index, game_id) in summary.added_game_ids.iter().enumerate() {
+            if let Err(e) = dat::verify_game(db, game_id).await {
+                eprintln!("Failed to verify {}: {}", game_id, e);
+            }
+            if last_emit.elapsed() >= Duration::from_millis(120) {
+                let pct = ((index + 1) as f32 / count as f32) * 100.0;
+                emit_progress("verifying", pct, &format!("Verified {} / {} new games", index + 1, count));
+                last_emit = Instant::now();
+            }
+        }
+    }
+
+    emit_progress("completed", 100.0, &format!(
+        "Scan complete: {} added, {} already in library",
+        summary.added, summary.duplicates
+    ));
+
+    Ok(summary)
+}
+
+// Folder under `base` for files generated from `source`. Each source folder gets its
+// own, so same-named files from different folders don't collide.
+fn generated_dir(base: &Path, source: &Path) -> PathBuf {
+    let folder = source.parent().unwrap_or(Path::new(""));
+    base.join(format!("{:016x}", fnv1a64(folder.to_string_lossy().as_bytes())))
+}
+
+fn local_game(rom_path: String, title: String, platform: &str) -> Game {
+    Game {
+        id: format!("local_{:016x}", fnv1a64(rom_path.as_bytes())),
+        title,
+        platform: platform.to_string(),
+        rom_path,
+        cover_path: None,
+        emulator_id: "retroarch".to_string(),
+        description: None,
+        release_year: None,
+        genre: None,
+        developer: None,
+        is_favorite: 0,
+        play_count: 0,
+        total_playtime: 0,
+        last_played: None,
+        core_override: None,
+        crc32: None,
+        md5: None,
+        sha1: None,
+        verified: 0,
+        region: None,
+        revision: None,
+        base_game_id: None,
+        soft_patch: None,
+    }
+}
+
+// "(Disc N)" images not already covered by a sheet or playlist, grouped into sets of
+// sibling files in the same folder
+fn loose_disc_sets(files: &[PathBuf], referenced: &HashSet<PathBuf>) -> Vec<Vec<PathBuf>> {
+    let mut by_dir: std::collections::BTreeMap<&Path, Vec<PathBuf>> = std::collections::BTreeMap::new();
+    for file in files.iter().filter(|f| !referenced.contains(*f)) {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if multidisc::disc_number(&name).is_some() {
+            by_dir.entry(file.parent().unwrap_or(Path::new(""))).or_default().push(file.clone());
+        }
+    }
+    by_dir.values().flat_map(|siblings| multidisc::disc_sets(siblings)).collect()
+}
+
+// Recursively list regular files, skipping hidden files and directories
+pub fn collect_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if let Ok(read) = std::fs::read_dir(&dir) {
+            for entry in read.flatten() {
+                let path = entry.path();
+                let hidden = path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with('.'))
+                    .unwrap_or(false);
+                if hidden {
+                    continue;
+                }
+                match entry.file_type() {
+                    Ok(t) if t.is_dir() => stack.push(path),
+                    Ok(t) if t.is_file() => files.push(path),
+                    _ => {}
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+// Track files listed by every .cue and .gdi sheet in the list, and the discs (with
+// their tracks) of every .m3u playlist
+pub fn referenced_by_sheets(files: &[PathBuf]) -> HashSet<PathBuf> {
+    let mut referenced = HashSet::new();
+    for sheet in files {
+        referenced.extend(sheet_tracks(sheet));
+    }
+    for playlist in files.iter().filter(|p| extension_of(p) == "m3u") {
+        for disc in playlist_discs(playlist) {
+            referenced.extend(sheet_tracks(&disc));
+            referenced.insert(disc);
+        }
+    }
+    referenced
+}
+
+fn sheet_tracks(sheet: &Path) -> Vec<PathBuf> {
+    match extension_of(sheet).as_str() {
+        "cue" => cue_tracks(sheet),
+        "gdi" => gdi_tracks(sheet),
+        _ => Vec::new(),
+    }
+}
+
+// The data file a sheet or playlist stands for: the first track of a .cue, or the first
+// disc of an .m3u, through that disc's .cue when it is one. Resolved one level at a time
+// (a playlist may list a sheet, a sheet only raw tracks) so playlists that list themselves
+// or each other can't loop. Other files are their own image.
+pub fn first_image(path: &Path) -> Option<PathBuf> {
+    let first_track = |sheet: &Path| {
+        cue_tracks(sheet).into_iter().next()
+            .filter(|track| !matches!(extension_of(track).as_str(), "cue" | "m3u"))
+    };
+    match extension_of(path).as_str() {
+        "cue" => first_track(path),
+        "m3u" => {
+            let disc = playlist_discs(path).into_iter().next()?;
+            match extension_of(&disc).as_str() {
+                "cue" => first_track(&disc),
+                "m3u" => None,
+                _ => Some(disc),
+            }
+        }
+        _ => Some(path.to_path_buf()),
+    }
+}
+
+pub fn playlist_discs(playlist: &Path) -> Vec<PathBuf> {
+    let dir = playlist.parent().unwrap_or(Path::new(""));
+    std::fs::read_to_string(playlist)
+        .map(|content| {
+            content.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| dir.join(line))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn cue_tracks(cue: &Path) -> Vec<PathBuf> {
+    let dir = cue.parent().unwrap_or(Path::new(""));
+    std::fs::read_to_string(cue)
+        .map(|content| {
+            content.lines()
+                .filter_map(|line| {
+                    let line = line.trim();
+                    if !line.to_uppercase().starts_with("FILE ") {
+                        return None;
+                    }
+                    let start = line.find('"')?;
+                    let end = line.rfind('"').filter(|&e| e > start)?;
+                    Some(dir.join(&line[start + 1..end]))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Track lines of a .gdi: "<number> <lba> <type> <sector size> <file> <offset>",
+// the file name quoted when it holds spaces
+fn gdi_tracks(gdi: &Path) -> Vec<PathBuf> {
+    let dir = gdi.parent().unwrap_or(Path::new(""));
+    std::fs::read_to_string(gdi)
+        .map(|content| {
+            content.lines()
+                .skip(1)
+                .filter_map(|line| {
+                    let line = line.trim();
+                    let name = match (line.find('"'), line.rfind('"')) {
+                        (Some(start), Some(end)) if end > start => &line[start + 1..end],
+                        _ => line.split_whitespace().nth(4)?,
+                    };
+                    Some(dir.join(name))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn extension_of(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// "Super Mario World (USA) [!]" -> "Super Mario World"
+pub fn clean_title(name: &str) -> String {
+    let mut title = String::new();
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth > 0 => depth -= 1,
+            _ if depth == 0 => title.push(if c == '_' { ' ' } else { c }),
+            _ => {}
+        }
+    }
+
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() { name.to_string() } else { title }
+}
+
+// Stable 64-bit FNV-1a, used to derive library ids from ROM paths
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("retrolauncher-scanner-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: PathBuf, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn groups_loose_discs_per_folder() {
+        let dir = test_dir("loose-discs");
+        for disc in 1..=2 {
+            write(dir.join("psx").join(format!("Game (Disc {}).cue", disc)), &format!("FILE \"Game (Disc {}).bin\" BINARY\n", disc));
+            write(dir.join("psx").join(format!("Game (Disc {}).bin", disc)), "");
+            write(dir.join("chd").join(format!("Epic (CD{}).chd", disc)), "");
+            write(dir.join("listed").join(format!("Saga (Disc {}).chd", disc)), "");
+        }
+        write(dir.join("listed").join("Saga.m3u"), "Saga (Disc 1).chd\nSaga (Disc 2).chd\n");
+        // Same name, other folder: not part of the psx set
+        write(dir.join("other").join("Game (Disc 3).cue"), "FILE \"Game (Disc 3).bin\" BINARY\n");
+        write(dir.join("other").join("Game (Disc 3).bin"), "");
+
+        let files = collect_files(&dir);
+        let referenced = referenced_by_sheets(&files);
+        let mut sets = loose_disc_sets(&files, &referenced);
+        sets.sort();
+
+        assert_eq!(sets, [
+            vec![dir.join("chd/Epic (CD1).chd"), dir.join("chd/Epic (CD2).chd")],
+            vec![dir.join("psx/Game (Disc 1).cue"), dir.join("psx/Game (Disc 2).cue")],
+        ]);
+    }
+}
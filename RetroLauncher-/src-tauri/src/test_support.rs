@@ -0,0 +1,220 @@
+// Fixtures shared by the launch-command, CrocDB and download queue tests
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zip::write::{FileOptions, ZipWriter};
+
+// Stub emulator that writes each argv entry NUL-terminated to $STUB_OUT
+#[cfg(unix)]
+pub fn write_stub_emulator(dir: &Path) -> PathBuf {
+    let stub = dir.join("stub-emulator");
+    std::fs::write(&stub, "#!/bin/sh\nprintf '%s\\0' \"$@\" > \"$STUB_OUT\"\n").unwrap();
+    std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+    stub
+}
+
+// Run `cmd` from `dir`, so anything a shell expanded would land there, and return the
+// argv the stub received
+#[cfg(unix)]
+pub fn run_and_capture_args(mut cmd: Command, dir: &Path) -> Vec<String> {
+    let out = dir.join("args.bin");
+    let status = cmd.current_dir(dir).env("STUB_OUT", &out).status().unwrap();
+    assert!(status.success());
+    std::fs::read_to_string(&out).unwrap()
+        .split('\0')
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_string())
+        .collect()
+}
+
+pub const PLATFORMS_ETAG: &str = "\"platforms-v1\"";
+
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: String,
+    pub path: String,
+    // Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+// Minimal CrocDB API: /platforms, /regions, /search and /entry answer with fixed data,
+// /files/* serves `archive`. Setting `down` makes every request fail with a 503; while
+// `stall` is set, /files/* sends the first KB and holds the rest back.
+pub struct StubServer {
+    pub base_url: String,
+    pub requests: Arc<Mutex<Vec<StubRequest>>>,
+    pub down: Arc<AtomicBool>,
+    pub stall: Arc<AtomicBool>,
+}
+
+impl StubServer {
+    pub fn requests_to(&self, path: &str) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().iter().filter(|r| r.path == path).cloned().collect()
+    }
+}
+
+pub fn start_crocdb_stub(archive: Vec<u8>) -> StubServer {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let down = Arc::new(AtomicBool::new(false));
+    let stall = Arc::new(AtomicBool::new(false));
+    let (seen, is_down, stalled, base) = (requests.clone(), down.clone(), stall.clone(), base_url.clone());
+    let archive = Arc::new(archive);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let (seen, is_down, stalled, base, archive) =
+                (seen.clone(), is_down.clone(), stalled.clone(), base.clone(), archive.clone());
+            // One thread per connection, so a stalled download doesn't block the API
+            std::thread::spawn(move || {
+                let Some(request) = read_request(&stream) else { return };
+                seen.lock().unwrap().push(request.clone());
+                let (status, headers, body) = if is_down.load(Ordering::SeqCst) {
+                    ("503 Service Unavailable", Vec::new(), Vec::new())
+                } else {
+                    respond(&request, &base, &archive)
+                };
+                let hold = request.path.starts_with("/files/") && stalled.load(Ordering::SeqCst);
+                write_response(stream, status, &headers, &body, hold.then_some(&*stalled));
+            });
+        }
+    });
+    StubServer { base_url, requests, down, stall }
+}
+
+fn read_request(stream: &TcpStream) -> Option<StubRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers.get("content-length").and_then(|v| v.parse().ok()).unwrap_or(0);
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(StubRequest { method, path, headers, body: String::from_utf8_lossy(&body).to_string() })
+}
+
+// With `hold`, only the first KB of the body goes out until the flag is cleared
+fn write_response(mut stream: TcpStream, status: &str, headers: &[(&str, String)], body: &[u8], hold: Option<&AtomicBool>) {
+    let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes());
+    let mut sent = 0;
+    if let Some(hold) = hold {
+        sent = body.len().min(1024);
+        let _ = stream.write_all(&body[..sent]);
+        let _ = stream.flush();
+        while hold.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+    let _ = stream.write_all(&body[sent..]);
+    let _ = stream.flush();
+    let _ = stream.shutdown(std::net::Shutdown::Both);
+}
+
+fn json_ok(data: serde_json::Value) -> Vec<u8> {
+    serde_json::json!({ "info": {}, "data": data }).to_string().into_bytes()
+}
+
+// "stub-game" -> "Stub Game"
+fn title_from_slug(slug: &str) -> String {
+    slug.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn entry_json(base: &str, slug: &str, size: usize) -> serde_json::Value {
+    serde_json::json!({
+        "slug": slug,
+        "rom_id": null,
+        "title": title_from_slug(slug),
+        "platform": "gba",
+        "boxart_url": format!("{}/covers/missing.png", base),
+        "regions": ["us"],
+        "links": [{
+            "name": format!("{} (USA)", title_from_slug(slug)),
+            "type": "Game",
+            "format": "zip",
+            "url": format!("{}/files/{}.zip", base, slug),
+            "filename": format!("{}.zip", slug),
+            "host": "stub",
+            "size": size,
+            "size_str": format!("{} B", size),
+            "source_url": base,
+        }],
+    })
+}
+
+fn respond(request: &StubRequest, base: &str, archive: &[u8]) -> (&'static str, Vec<(&'static str, String)>, Vec<u8>) {
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap_or_default();
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/platforms") => {
+            if request.headers.get("if-none-match").map(|v| v.as_str()) == Some(PLATFORMS_ETAG) {
+                return ("304 Not Modified", Vec::new(), Vec::new());
+            }
+            let data = serde_json::json!({ "platforms": { "gba": { "brand": "Nintendo", "name": "Game Boy Advance" } } });
+            ("200 OK", vec![("ETag", PLATFORMS_ETAG.to_string())], json_ok(data))
+        }
+        ("GET", "/regions") => {
+            ("200 OK", Vec::new(), json_ok(serde_json::json!({ "regions": { "us": "USA", "eu": "Europe" } })))
+        }
+        ("POST", "/search") => {
+            let key = body["search_key"].as_str().unwrap_or_default().to_lowercase();
+            let data = serde_json::json!({
+                "results": [entry_json(base, &key.replace(' ', "-"), archive.len())],
+                "current_results": 1,
+                "total_results": 1,
+                "current_page": body["page"].as_i64().unwrap_or(1),
+                "total_pages": 1,
+            });
+            ("200 OK", Vec::new(), json_ok(data))
+        }
+        ("POST", "/entry") => {
+            let slug = body["slug"].as_str().unwrap_or_default();
+            ("200 OK", Vec::new(), json_ok(serde_json::json!({ "entry": entry_json(base, slug, archive.len()) })))
+        }
+        ("GET", path) if path.starts_with("/files/") => ("200 OK", Vec::new(), archive.to_vec()),
+        _ => ("404 Not Found", Vec::new(), Vec::new()),
+    }
+}
+
+// Zip holding "Stub Game.gba", stored uncompressed so a stalled download is unfinished
+pub fn rom_zip() -> Vec<u8> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file("Stub Game.gba", options).unwrap();
+    zip.write_all(&[0u8; 4096]).unwrap();
+    zip.finish().unwrap().into_inner()
+}
@@ -14,15 +14,12 @@ import BigPictureMode from './components/BigPictureMode';
 
 function App() {
   const { i18n } = useTranslation();
-  const { currentView, setGames, setEmulators, theme, layout, bigPictureMode, colorPalette, bpColorPalette, language } = useStore();
+  const { currentView, setEmulators, theme, layout, bigPictureMode, colorPalette, bpColorPalette, language } = useStore();
 
   useEffect(() => {
-    // Load initial data
+    // Load initial data; library views page their games themselves
     const loadData = async () => {
       try {
-        const games = await tauriApi.getGames();
-        setGames(games);
-
         const emulators = await tauriApi.getEmulators();
         setEmulators(emulators);
       } catch (error) {
@@ -31,7 +28,7 @@ function App() {
     };
 
     loadData();
-  }, [setGames, setEmulators]);
+  }, [setEmulators]);
 
   // Apply theme
   useEffect(() => {
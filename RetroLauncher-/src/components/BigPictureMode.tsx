@@ -4,6 +4,7 @@ import { useTranslation } from 'react-i18next';
 import { convertFileSrc } from '@tauri-apps/api/tauri';
 import { useStore, Game } from '../store/useStore';
 import { useGamepad } from '../hooks/useGamepad';
+import { useLibraryPages } from '../hooks/useLibraryPages';
 import { tauriApi, RetroArchCore, CrocDBGame, PlatformInfo } from '../utils/tauri';
 import DownloadManager from './DownloadManager';
 import { listen } from '@tauri-apps/api/event';
@@ -29,9 +30,8 @@ type View = 'main' | 'library' | 'favorites' | 'details' | 'bp_emulators' | 'bp_
 const BigPictureMode = () => {
   const { t } = useTranslation();
   const {
-    games,
     setBigPictureMode,
-    setGames,
+    refreshLibrary,
     bpKeyboardLayout,
     setBpKeyboardLayout,
     addDownload,
@@ -186,25 +186,25 @@ const BigPictureMode = () => {
   ];
   const MENU_COLS = 3;
 
-  // Filter games based on search query
-  const filterGames = (gamesList: Game[]) => {
-    if (!searchQuery.trim()) return gamesList;
-
-    const query = searchQuery.toLowerCase();
-    return gamesList.filter((game) =>
-      game.title.toLowerCase().includes(query) ||
-      game.platform.toLowerCase().includes(query) ||
-      (game.genre && game.genre.toLowerCase().includes(query)) ||
-      (game.developer && game.developer.toLowerCase().includes(query))
-    );
-  };
-
-  const displayGames = filterGames(
-    currentView === 'favorites'
-      ? games.filter((g) => g.is_favorite === 1)
-      : games
+  // Library/favorites rows, paged from the backend and searched with the full-text index
+  const listQuery = searchQuery.trim();
+  const { games: displayGames, total: displayTotal, hasMore, loadMore } = useLibraryPages(
+    (offset, limit) => {
+      const favorite = lastListView === 'favorites' ? true : undefined;
+      return listQuery
+        ? tauriApi.searchLibrary({ query: listQuery, favorite, offset, limit })
+        : tauriApi.queryGames({ favorite, offset, limit });
+    },
+    JSON.stringify([listQuery, lastListView])
   );
 
+  // Fetch the next page before the selection reaches the last rows
+  useEffect(() => {
+    if (hasMore && selectedGameIndex >= displayGames.length - COLS * 2) {
+      loadMore();
+    }
+  }, [selectedGameIndex, displayGames.length, hasMore, loadMore, COLS]);
+
   // Focus search input when search bar is shown
   useEffect(() => {
     if (showSearchBar && searchInputRef.current) {
@@ -212,20 +212,6 @@ const BigPictureMode = () => {
     }
   }, [showSearchBar]);
 
-  // Load games on mount if not present
-  useEffect(() => {
-    (async () => {
-      try {
-        if (!games || games.length === 0) {
-          const list = await tauriApi.getGames();
-          setGames(list);
-        }
-      } catch (e) {
-        console.error('Failed to load games:', e);
-      }
-    })();
-  }, []);
-
   // Load cores when entering emulators BP
   useEffect(() => {
     const load = async () => {
@@ -300,12 +286,12 @@ const BigPictureMode = () => {
   // Sync selectedGame with store when favorites change
   useEffect(() => {
     if (selectedGame) {
-      const updatedGame = games.find((g) => g.id === selectedGame.id);
+      const updatedGame = displayGames.find((g) => g.id === selectedGame.id);
       if (updatedGame && updatedGame.is_favorite !== selectedGame.is_favorite) {
         setSelectedGame(updatedGame);
       }
     }
-  }, [games, selectedGame]);
+  }, [displayGames, selectedGame]);
 
   // Load platforms/regions when entering bp_download
   useEffect(() => {
@@ -339,8 +325,7 @@ const BigPictureMode = () => {
     try {
       await tauriApi.launchGame(game.id);
       // Refresh games to update play count and last played
-      const updatedGames = await tauriApi.getGames();
-      setGames(updatedGames);
+      refreshLibrary();
     } catch (error) {
       console.error('Failed to launch game:', error);
     }
@@ -348,12 +333,10 @@ const BigPictureMode = () => {
 
   const handleToggleFavorite = async (gameId: string) => {
     try {
-      await tauriApi.toggleFavorite(gameId);
-      const updated = await tauriApi.getGames();
-      setGames(updated);
+      const isFavorite = await tauriApi.toggleFavorite(gameId);
+      refreshLibrary();
       if (selectedGame && selectedGame.id === gameId) {
-        const refreshed = updated.find(g => g.id === gameId);
-        if (refreshed) setSelectedGame(refreshed);
+        setSelectedGame({ ...selectedGame, is_favorite: isFavorite ? 1 : 0 });
       }
     } catch (e) {
       console.error('Failed to toggle favorite:', e);
@@ -408,8 +391,7 @@ const BigPictureMode = () => {
       setDownloadingRandom(true);
       addDownload({ slug: g.slug, title: g.title, stage: 'starting', progress: 0, message: 'Starting download...' });
       await tauriApi.installGameFromCrocDB(g.slug);
-      const updatedGames = await tauriApi.getGames();
-      setGames(updatedGames);
+      refreshLibrary();
     } catch (e) {
       console.error('Failed to install from search', e);
       alert(`Failed to install: ${e}`);
@@ -425,8 +407,7 @@ const BigPictureMode = () => {
       const entry = await tauriApi.getRandomCrocDBEntry();
       addDownload({ slug: entry.slug, title: entry.title, stage: 'starting', progress: 0, message: 'Starting download...' });
       await tauriApi.installGameFromCrocDB(entry.slug);
-      const updatedGames = await tauriApi.getGames();
-      setGames(updatedGames);
+      refreshLibrary();
     } catch (e) {
       console.error('Failed to install random game', e);
       alert(`Failed to install: ${e}`);
@@ -801,7 +782,7 @@ const BigPictureMode = () => {
               />
               {searchQuery && (
                 <div className="px-3 py-1 bg-purple-600 rounded-lg text-sm font-semibold">
-                  {displayGames.length} results
+                  {displayTotal} results
                 </div>
               )}
               <button
@@ -817,7 +798,7 @@ const BigPictureMode = () => {
           ) : (
             <>
               <div className="text-xl text-gray-400">
-                {displayGames.length} {t('big_picture.games')}
+                {displayTotal} {t('big_picture.games')}
               </div>
               <button
                 onClick={() => setShowSearchBar(true)}
@@ -852,7 +833,13 @@ const BigPictureMode = () => {
           <p className="text-2xl text-gray-400">{t('big_picture.no_games')}</p>
         </div>
       ) : (
-        <div className="flex-1 overflow-y-auto px-8 overflow-visible">
+        <div
+          className="flex-1 overflow-y-auto px-8 overflow-visible"
+          onScroll={(e) => {
+            const el = e.currentTarget;
+            if (el.scrollTop + el.clientHeight >= el.scrollHeight - 600) loadMore();
+          }}
+        >
           <div className={`grid ${COLS===6?'grid-cols-6':COLS===4?'grid-cols-4':'grid-cols-5'} gap-6 pt-8 pb-64`}>
             {displayGames.map((game, index) => (
               <motion.div
@@ -1013,7 +1000,7 @@ const BigPictureMode = () => {
                 <span className="text-gray-400">Playtime</span>
               </div>
               <p className="text-3xl font-bold">
-                {Math.round(selectedGame.total_playtime / 60)}h
+                {Math.round(selectedGame.total_playtime / 3600)}h
               </p>
             </div>
             <div className="bg-gray-800 rounded-xl p-6">
@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import { useTranslation } from 'react-i18next';
 import { Search, Download as DownloadIcon, Loader2, Filter, X, Package } from 'lucide-react';
 import { motion } from 'framer-motion';
-import { tauriApi, CrocDBGame, PlatformInfo } from '../utils/tauri';
+import { tauriApi, CrocDBGame, LinkSelector, PlatformInfo } from '../utils/tauri';
 import { useStore } from '../store/useStore';
 import { listen } from '@tauri-apps/api/event';
 
@@ -11,7 +11,6 @@ const Download = () => {
   const [searchQuery, setSearchQuery] = useState('');
   const [searchResults, setSearchResults] = useState<CrocDBGame[]>([]);
   const [isSearching, setIsSearching] = useState(false);
-  const [installing, setInstalling] = useState<string | null>(null);
   const [selectedGame, setSelectedGame] = useState<CrocDBGame | null>(null);
   const [boxartCache, setBoxartCache] = useState<Record<string, string>>({});
 
@@ -23,7 +22,14 @@ const Download = () => {
   const [availableRegions, setAvailableRegions] = useState<Record<string, string>>({});
   const [hideGamesWithoutImage, setHideGamesWithoutImage] = useState(false);
 
-  const { games, setGames, addDownload, updateDownloadProgress, removeDownload } = useStore();
+  const { games, setGames, libraryRevision, refreshLibrary, downloads, addDownload, updateDownloadProgress, removeDownload } = useStore();
+
+  // Installed games, used to mark results that are already in the library
+  useEffect(() => {
+    tauriApi.getGames()
+      .then(setGames)
+      .catch((error) => console.error('Failed to load installed games:', error));
+  }, [libraryRevision, setGames]);
 
   // Load platforms and regions on mount
   useEffect(() => {
@@ -63,15 +69,9 @@ const Download = () => {
           setTimeout(() => {
             console.log('🧹 Removing download and refreshing library...');
             removeDownload(slug);
-            setInstalling(null);
 
             // Refresh games library
-            tauriApi.getGames().then(games => {
-              setGames(games);
-              console.log('📚 Library refreshed successfully:', games.length, 'games');
-            }).catch(err => {
-              console.error('❌ Failed to refresh library:', err);
-            });
+            refreshLibrary();
           }, 3000);
         }
       });
@@ -174,25 +174,24 @@ const Download = () => {
     );
   };
 
-  const handleInstallClick = async (game: CrocDBGame) => {
-    setInstalling(game.slug);
-
+  // Without a link selector the backend picks the best link using the link preferences
+  const handleInstallClick = async (game: CrocDBGame, link?: LinkSelector) => {
     // Add to download manager
     addDownload({
       slug: game.slug,
       title: game.title,
-      stage: 'starting',
+      stage: 'queued',
       progress: 0,
-      message: 'Starting download...'
+      message: 'Waiting for a free download slot...',
+      queued: true,
     });
 
     try {
-      await tauriApi.installGameFromCrocDB(game.slug);
-      // Success is handled by the progress event listener
+      await tauriApi.installGameFromCrocDB(game.slug, link);
+      // Progress and completion are handled by the progress event listener
     } catch (error) {
-      console.error('Failed to install game:', error);
+      console.error('Failed to queue game:', error);
       removeDownload(game.slug);
-      setInstalling(null);
       alert(`Failed to install game: ${error}`);
     }
   };
@@ -433,19 +432,20 @@ const Download = () => {
                     {/* Install Button */}
                     {(() => {
                       const isInstalled = games.some(g => g.id === `crocdb_${game.slug.replace(/-/g, '_')}`);
+                      const installing = downloads.some(d => d.slug === game.slug && d.stage !== 'failed');
                       return (
                         <motion.button
                           whileHover={{ scale: 1.02 }}
                           whileTap={{ scale: 0.98 }}
                           onClick={() => handleInstallClick(game)}
-                          disabled={isInstalled || installing === game.slug || !game.download_links || game.download_links.length === 0}
+                          disabled={isInstalled || installing || !game.download_links || game.download_links.length === 0}
                           className="w-full px-4 py-2 bg-primary-600 hover:bg-primary-700 rounded-lg font-medium transition-colors disabled:opacity-50 flex items-center justify-center gap-2"
                         >
                           {isInstalled ? (
                             <>
                               ✓ Installed
                             </>
-                          ) : installing === game.slug ? (
+                          ) : installing ? (
                             <>
                               <Loader2 className="w-4 h-4 animate-spin" />
                               Installing...
@@ -524,7 +524,20 @@ const Download = () => {
                             Format: {link.format} • Host: {link.host}
                           </p>
                         </div>
-                        <span className="text-sm text-primary-400">{link.size_str}</span>
+                        <div className="flex items-center gap-3">
+                          <span className="text-sm text-primary-400">{link.size_str}</span>
+                          <button
+                            onClick={() => {
+                              setSelectedGame(null);
+                              handleInstallClick(selectedGame, { index: idx });
+                            }}
+                            className="px-3 py-1 bg-primary-600 hover:bg-primary-700 rounded-lg text-sm font-medium transition-colors flex items-center gap-1"
+                            title={`Install ${link.filename}`}
+                          >
+                            <DownloadIcon className="w-3 h-3" />
+                            Install
+                          </button>
+                        </div>
                       </div>
                     </div>
                   ))}
@@ -1,9 +1,56 @@
+import { useEffect } from 'react';
+import { useTranslation } from 'react-i18next';
 import { motion, AnimatePresence } from 'framer-motion';
-import { Download, CheckCircle, X } from 'lucide-react';
+import { Download, CheckCircle, X, Pause, Play, RotateCcw, AlertCircle } from 'lucide-react';
 import { useStore } from '../store/useStore';
+import { tauriApi } from '../utils/tauri';
 
 const DownloadManager = () => {
-  const { downloads, removeDownload } = useStore();
+  const { t } = useTranslation();
+  const { downloads, addDownload, removeDownload } = useStore();
+
+  // Restore queued, paused and failed downloads from the previous session
+  useEffect(() => {
+    tauriApi.listDownloads().then(items => {
+      items
+        .filter(item => item.status !== 'completed')
+        .forEach(item => addDownload({
+          slug: item.slug,
+          title: item.title,
+          stage: item.status,
+          progress: item.progress,
+          message: item.error || t(`download_manager.${item.status}`),
+          bytesReceived: item.bytes_received,
+          totalBytes: item.total_bytes,
+          queued: true,
+        }));
+    }).catch(err => console.error('Failed to load download queue:', err));
+  }, []);
+
+  const handlePause = async (slug: string) => {
+    try {
+      await tauriApi.pauseDownload(slug);
+    } catch (error) {
+      console.error('Failed to pause download:', error);
+    }
+  };
+
+  const handleResume = async (slug: string) => {
+    try {
+      await tauriApi.resumeDownload(slug);
+    } catch (error) {
+      console.error('Failed to resume download:', error);
+    }
+  };
+
+  const handleCancel = async (slug: string) => {
+    try {
+      await tauriApi.cancelDownload(slug);
+      removeDownload(slug);
+    } catch (error) {
+      console.error('Failed to cancel download:', error);
+    }
+  };
 
   const formatBytes = (n?: number) => {
     if (!n || n <= 0) return '0 B';
@@ -47,6 +94,10 @@ const DownloadManager = () => {
               <div className="flex items-center gap-3 flex-1 min-w-0">
                 {download.progress === 100 ? (
                   <CheckCircle className="w-5 h-5 text-green-400 flex-shrink-0" />
+                ) : download.stage === 'failed' ? (
+                  <AlertCircle className="w-5 h-5 text-red-400 flex-shrink-0" />
+                ) : download.stage === 'paused' ? (
+                  <Pause className="w-5 h-5 text-dark-400 flex-shrink-0" />
                 ) : (
                   <Download className="w-5 h-5 text-primary-400 animate-bounce flex-shrink-0" />
                 )}
@@ -56,6 +107,35 @@ const DownloadManager = () => {
                 </div>
               </div>
 
+              {download.queued && download.progress < 100 && (
+                <div className="flex items-center gap-1 flex-shrink-0">
+                  {download.stage === 'paused' || download.stage === 'failed' ? (
+                    <button
+                      onClick={() => handleResume(download.slug)}
+                      title={t(download.stage === 'failed' ? 'download_manager.retry' : 'download_manager.resume')}
+                      className="text-dark-400 hover:text-white transition-colors p-1"
+                    >
+                      {download.stage === 'failed' ? <RotateCcw className="w-4 h-4" /> : <Play className="w-4 h-4" />}
+                    </button>
+                  ) : (
+                    <button
+                      onClick={() => handlePause(download.slug)}
+                      title={t('download_manager.pause')}
+                      className="text-dark-400 hover:text-white transition-colors p-1"
+                    >
+                      <Pause className="w-4 h-4" />
+                    </button>
+                  )}
+                  <button
+                    onClick={() => handleCancel(download.slug)}
+                    title={t('download_manager.cancel')}
+                    className="text-dark-400 hover:text-white transition-colors p-1"
+                  >
+                    <X className="w-4 h-4" />
+                  </button>
+                </div>
+              )}
+
               {download.progress === 100 && (
                 <button
                   onClick={() => removeDownload(download.slug)}
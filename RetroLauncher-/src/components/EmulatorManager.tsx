@@ -511,7 +511,7 @@ const EmulatorManager = () => {
                     value={newEmulator.arguments}
                     onChange={(e) => setNewEmulator({ ...newEmulator, arguments: e.target.value })}
                     className="w-full px-4 py-2 bg-dark-800 border border-dark-700 rounded-lg focus:outline-none focus:border-primary-500"
-                    placeholder="-L cores/core.dll"
+                    placeholder="{fullscreen:-f} {rom}"
                   />
                 </div>
                 <div className="flex gap-3 pt-2">
@@ -1,14 +1,24 @@
 import { useTranslation } from 'react-i18next';
-import { useStore } from '../store/useStore';
 import GameCard from './GameCard';
 import { motion } from 'framer-motion';
 import { Star } from 'lucide-react';
+import { tauriApi } from '../utils/tauri';
+import { useLibraryPages } from '../hooks/useLibraryPages';
 
 const Favorites = () => {
   const { t } = useTranslation();
-  const { games } = useStore();
 
-  const favoriteGames = games.filter((game) => game.is_favorite === 1);
+  const { games: favoriteGames, total, loading, loadMore } = useLibraryPages(
+    (offset, limit) => tauriApi.queryGames({ favorite: true, offset, limit }),
+    'favorites'
+  );
+
+  const handleScroll = (e: React.UIEvent<HTMLDivElement>) => {
+    const el = e.currentTarget;
+    if (el.scrollTop + el.clientHeight >= el.scrollHeight - 600) {
+      loadMore();
+    }
+  };
 
   return (
     <div className="h-full flex flex-col">
@@ -23,15 +33,15 @@ const Favorites = () => {
           <div>
             <h2 className="text-3xl font-bold bg-gradient-to-r from-[var(--brand-from)] to-[var(--brand-to)] bg-clip-text text-transparent">{t('nav.favorites')}</h2>
             <p className="text-dark-400 mt-1">
-              {t(favoriteGames.length === 1 ? 'library.games_count' : 'library.games_count_plural', { count: favoriteGames.length })}
+              {t(total === 1 ? 'library.games_count' : 'library.games_count_plural', { count: total })}
             </p>
           </div>
         </div>
       </motion.div>
 
       {/* Content */}
-      <div className="flex-1 overflow-y-auto p-6">
-        {favoriteGames.length === 0 ? (
+      <div className="flex-1 overflow-y-auto p-6" onScroll={handleScroll}>
+        {favoriteGames.length === 0 && !loading ? (
           <motion.div
             initial={{ opacity: 0 }}
             animate={{ opacity: 1 }}
@@ -43,7 +53,7 @@ const Favorites = () => {
               {t('favorites.empty_desc')}
             </p>
           </motion.div>
-        ) : (
+        ) : favoriteGames.length === 0 ? null : (
           <motion.div
             initial={{ opacity: 0 }}
             animate={{ opacity: 1 }}
@@ -18,7 +18,7 @@ const GameCard = ({ game, onGameUpdate, viewMode = 'grid' }: GameCardProps) => {
   const [isLaunching, setIsLaunching] = useState(false);
   const [showContextMenu, setShowContextMenu] = useState(false);
   const [contextMenuPosition, setContextMenuPosition] = useState({ x: 0, y: 0 });
-  const { setGames } = useStore();
+  const { refreshLibrary } = useStore();
 
   const handlePlay = async (e: React.MouseEvent) => {
     e.stopPropagation();
@@ -26,8 +26,7 @@ const GameCard = ({ game, onGameUpdate, viewMode = 'grid' }: GameCardProps) => {
     try {
       await tauriApi.launchGame(game.id);
       // Refresh games to update play count
-      const games = await tauriApi.getGames();
-      setGames(games);
+      refreshLibrary();
     } catch (error) {
       console.error('Failed to launch game:', error);
       alert(`Failed to launch game: ${error}`);
@@ -42,8 +41,7 @@ const GameCard = ({ game, onGameUpdate, viewMode = 'grid' }: GameCardProps) => {
     try {
       await tauriApi.toggleFavorite(game.id);
       // Refresh games from database to sync favorite status
-      const games = await tauriApi.getGames();
-      setGames(games);
+      refreshLibrary();
       onGameUpdate?.();
     } catch (error) {
       console.error('Failed to toggle favorite:', error);
@@ -65,8 +63,7 @@ const GameCard = ({ game, onGameUpdate, viewMode = 'grid' }: GameCardProps) => {
 
     try {
       await tauriApi.deleteGame(game.id);
-      const games = await tauriApi.getGames();
-      setGames(games);
+      refreshLibrary();
       onGameUpdate?.();
       setShowContextMenu(false);
     } catch (error) {
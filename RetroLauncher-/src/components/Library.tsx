@@ -3,52 +3,40 @@ import { Search, Grid3x3, List, Plus } from 'lucide-react';
 import { useStore } from '../store/useStore';
 import GameCard from './GameCard';
 import { motion } from 'framer-motion';
-import { useMemo, useState } from 'react';
+import { useEffect, useState } from 'react';
+import { tauriApi } from '../utils/tauri';
+import { useLibraryPages } from '../hooks/useLibraryPages';
 
 const Library = () => {
   const { t } = useTranslation();
-  const { games, viewMode, setViewMode, searchQuery, setSearchQuery, sortBy, setSortBy, setCurrentView } = useStore();
+  const { viewMode, setViewMode, searchQuery, setSearchQuery, sortBy, setSortBy, setCurrentView, libraryRevision } = useStore();
   const [selectedPlatform, setSelectedPlatform] = useState<string | null>(null);
+  const [platforms, setPlatforms] = useState<string[]>([]);
+  const query = searchQuery.trim();
+
+  // Rows come from the backend a page at a time, already filtered and sorted
+  const { games, total, loading, loadMore } = useLibraryPages(
+    (offset, limit) => {
+      const platform = selectedPlatform ?? undefined;
+      return query
+        ? tauriApi.searchLibrary({ query, platform, sort: sortBy, offset, limit })
+        : tauriApi.queryGames({ platform, sort: sortBy, offset, limit });
+    },
+    JSON.stringify([query, selectedPlatform, sortBy])
+  );
 
-  const filteredGames = useMemo(() => {
-    let filtered = [...games];
-
-    // Platform filter
-    if (selectedPlatform) {
-      filtered = filtered.filter((game) => game.platform === selectedPlatform);
-    }
+  useEffect(() => {
+    tauriApi.getLibraryPlatforms()
+      .then((list) => setPlatforms(list.map((p) => p.platform)))
+      .catch((error) => console.error('Failed to load platforms:', error));
+  }, [libraryRevision]);
 
-    // Search filter
-    if (searchQuery) {
-      filtered = filtered.filter((game) =>
-        game.title.toLowerCase().includes(searchQuery.toLowerCase()) ||
-        game.platform.toLowerCase().includes(searchQuery.toLowerCase())
-      );
+  const handleScroll = (e: React.UIEvent<HTMLDivElement>) => {
+    const el = e.currentTarget;
+    if (el.scrollTop + el.clientHeight >= el.scrollHeight - 600) {
+      loadMore();
     }
-
-    // Sort
-    filtered.sort((a, b) => {
-      switch (sortBy) {
-        case 'title':
-          return a.title.localeCompare(b.title);
-        case 'platform':
-          return a.platform.localeCompare(b.platform);
-        case 'play_count':
-          return b.play_count - a.play_count;
-        case 'last_played':
-          return (b.last_played || '').localeCompare(a.last_played || '');
-        default:
-          return 0;
-      }
-    });
-
-    return filtered;
-  }, [games, searchQuery, sortBy, selectedPlatform]);
-
-  const platforms = useMemo(() => {
-    const uniquePlatforms = new Set(games.map(g => g.platform));
-    return Array.from(uniquePlatforms);
-  }, [games]);
+  };
 
   const handlePlatformFilter = (platform: string | null) => {
     setSelectedPlatform(platform);
@@ -66,7 +54,7 @@ const Library = () => {
           <div>
             <h2 className="text-3xl font-bold bg-gradient-to-r from-[var(--brand-from)] to-[var(--brand-to)] bg-clip-text text-transparent">{t('library.title')}</h2>
             <p className="text-dark-400 mt-1">
-              {t(filteredGames.length === 1 ? 'library.games_count' : 'library.games_count_plural', { count: filteredGames.length })}
+              {t(total === 1 ? 'library.games_count' : 'library.games_count_plural', { count: total })}
             </p>
           </div>
 
@@ -163,8 +151,8 @@ const Library = () => {
       </motion.div>
 
       {/* Game Grid */}
-      <div className="flex-1 overflow-y-auto p-6">
-        {filteredGames.length === 0 ? (
+      <div className="flex-1 overflow-y-auto p-6" onScroll={handleScroll}>
+        {games.length === 0 && !loading ? (
           <motion.div
             initial={{ opacity: 0 }}
             animate={{ opacity: 1 }}
@@ -185,7 +173,7 @@ const Library = () => {
               {t('library.add_game')}
             </motion.button>
           </motion.div>
-        ) : (
+        ) : games.length === 0 ? null : (
           <motion.div
             initial={{ opacity: 0 }}
             animate={{ opacity: 1 }}
@@ -198,7 +186,7 @@ const Library = () => {
               }
             `}
           >
-            {filteredGames.map((game) => (
+            {games.map((game) => (
               <GameCard key={game.id} game={game} viewMode={viewMode} />
             ))}
           </motion.div>
@@ -1,15 +1,76 @@
+import { useState, useEffect } from 'react';
 import { useTranslation } from 'react-i18next';
 import { motion } from 'framer-motion';
-import { Globe, Palette, Layout, Info, FolderOpen, Shield, Gamepad2 } from 'lucide-react';
+import { Globe, Palette, Layout, Info, FolderOpen, Shield, Gamepad2, Download } from 'lucide-react';
 import { useStore } from '../store/useStore';
 import { appDataDir, join } from '@tauri-apps/api/path';
 import { message } from '@tauri-apps/api/dialog';
-import { tauriApi } from '../utils/tauri';
+import { tauriApi, LinkPreferences } from '../utils/tauri';
 
 const Settings = () => {
   const { t, i18n } = useTranslation();
   const { theme, setTheme, layout, setLayout, bigPictureMode, setBigPictureMode, colorPalette, setColorPalette, bpColorPalette, setBpColorPalette, setLanguage, windowedFullscreen, setWindowedFullscreen } = useStore();
 
+  // Stored in the backend settings table; read by the download queue
+  const [maxConcurrentDownloads, setMaxConcurrentDownloads] = useState(2);
+  const [linkPreferences, setLinkPreferences] = useState<LinkPreferences>({
+    preferred_hosts: [],
+    preferred_formats: [],
+    prefer_smallest: false,
+  });
+  const [preferredHostsText, setPreferredHostsText] = useState('');
+  const [preferredFormatsText, setPreferredFormatsText] = useState('');
+  const [normalizeN64, setNormalizeN64] = useState(false);
+
+  useEffect(() => {
+    tauriApi.getSettings().then(settings => {
+      const value = Number(settings?.max_concurrent_downloads);
+      if (value > 0) setMaxConcurrentDownloads(value);
+      setNormalizeN64(!!settings?.normalize_n64_roms);
+      const prefs = settings?.link_preferences;
+      if (prefs) {
+        const loaded: LinkPreferences = {
+          preferred_hosts: prefs.preferred_hosts ?? [],
+          preferred_formats: prefs.preferred_formats ?? [],
+          prefer_smallest: !!prefs.prefer_smallest,
+        };
+        setLinkPreferences(loaded);
+        setPreferredHostsText(loaded.preferred_hosts.join(', '));
+        setPreferredFormatsText(loaded.preferred_formats.join(', '));
+      }
+    }).catch(err => console.error('Failed to load settings:', err));
+  }, []);
+
+  const saveLinkPreferences = async (prefs: LinkPreferences) => {
+    setLinkPreferences(prefs);
+    try {
+      await tauriApi.updateSettings({ link_preferences: prefs });
+    } catch (error) {
+      console.error('Failed to save download settings:', error);
+    }
+  };
+
+  const handleNormalizeN64Change = async (value: boolean) => {
+    setNormalizeN64(value);
+    try {
+      await tauriApi.updateSettings({ normalize_n64_roms: value });
+    } catch (error) {
+      console.error('Failed to save download settings:', error);
+    }
+  };
+
+  // "chd, bin/cue" -> ["chd", "bin/cue"]
+  const splitList = (text: string) => text.split(',').map(s => s.trim()).filter(Boolean);
+
+  const handleMaxConcurrentDownloadsChange = async (value: number) => {
+    setMaxConcurrentDownloads(value);
+    try {
+      await tauriApi.updateSettings({ max_concurrent_downloads: value });
+    } catch (error) {
+      console.error('Failed to save download settings:', error);
+    }
+  };
+
   const languages = [
     { code: 'fr', name: 'Français', flag: '🇫🇷' },
     { code: 'en', name: 'English', flag: '🇬🇧' },
@@ -337,6 +398,91 @@ const Settings = () => {
             </div>
           </motion.div>
 
+          {/* Downloads */}
+          <motion.div
+            initial={{ opacity: 0, y: 20 }}
+            animate={{ opacity: 1, y: 0 }}
+            transition={{ delay: 0.22 }}
+            className="glass-effect rounded-xl p-6"
+          >
+            <div className="flex items-center gap-3 mb-4">
+              <div className="w-10 h-10 bg-primary-600 rounded-lg flex items-center justify-center">
+                <Download className="w-5 h-5" />
+              </div>
+              <div>
+                <h3 className="text-xl font-bold">{t('settings.downloads.title')}</h3>
+                <p className="text-sm text-dark-400">{t('settings.downloads.concurrent_desc')}</p>
+              </div>
+            </div>
+
+            <label className="block text-sm font-medium mb-2">{t('settings.downloads.concurrent')}</label>
+            <div className="grid grid-cols-4 gap-3">
+              {[1, 2, 3, 4].map((value) => (
+                <button
+                  key={value}
+                  onClick={() => handleMaxConcurrentDownloadsChange(value)}
+                  className={`px-4 py-3 rounded-lg border-2 transition-all ${
+                    maxConcurrentDownloads === value
+                      ? 'border-primary-500 bg-primary-500/10'
+                      : 'border-dark-700 hover:border-dark-600'
+                  }`}
+                >
+                  {value}
+                </button>
+              ))}
+            </div>
+
+            <div className="mt-6 space-y-4">
+              <div>
+                <label className="block text-sm font-medium mb-1">{t('settings.downloads.preferred_formats')}</label>
+                <p className="text-sm text-dark-400 mb-2">{t('settings.downloads.preferred_formats_desc')}</p>
+                <input
+                  type="text"
+                  placeholder="chd, bin/cue, iso"
+                  value={preferredFormatsText}
+                  onChange={(e) => setPreferredFormatsText(e.target.value)}
+                  onBlur={() => saveLinkPreferences({ ...linkPreferences, preferred_formats: splitList(preferredFormatsText) })}
+                  className="w-full px-4 py-2 bg-dark-800 border border-dark-700 rounded-lg focus:outline-none focus:border-primary-500 transition-colors"
+                />
+              </div>
+              <div>
+                <label className="block text-sm font-medium mb-1">{t('settings.downloads.preferred_hosts')}</label>
+                <p className="text-sm text-dark-400 mb-2">{t('settings.downloads.preferred_hosts_desc')}</p>
+                <input
+                  type="text"
+                  placeholder="myrient"
+                  value={preferredHostsText}
+                  onChange={(e) => setPreferredHostsText(e.target.value)}
+                  onBlur={() => saveLinkPreferences({ ...linkPreferences, preferred_hosts: splitList(preferredHostsText) })}
+                  className="w-full px-4 py-2 bg-dark-800 border border-dark-700 rounded-lg focus:outline-none focus:border-primary-500 transition-colors"
+                />
+              </div>
+              <div className="flex items-center justify-between p-3 rounded-lg border-2 border-dark-700">
+                <span className="text-sm text-dark-300">{t('settings.downloads.prefer_smallest')}</span>
+                <button
+                  onClick={() => saveLinkPreferences({ ...linkPreferences, prefer_smallest: !linkPreferences.prefer_smallest })}
+                  className={`relative inline-flex h-6 w-11 items-center rounded-full transition-colors ${linkPreferences.prefer_smallest ? 'bg-primary-600' : 'bg-dark-700'}`}
+                  aria-pressed={linkPreferences.prefer_smallest}
+                >
+                  <span className={`inline-block h-5 w-5 transform rounded-full bg-white transition-transform ${linkPreferences.prefer_smallest ? 'translate-x-5' : 'translate-x-1'}`} />
+                </button>
+              </div>
+              <div>
+                <label className="block text-sm font-medium mb-2">{t('settings.downloads.normalize_n64')}</label>
+                <div className="flex items-center justify-between gap-4 p-3 rounded-lg border-2 border-dark-700">
+                  <span className="text-sm text-dark-300">{t('settings.downloads.normalize_n64_desc')}</span>
+                  <button
+                    onClick={() => handleNormalizeN64Change(!normalizeN64)}
+                    className={`relative inline-flex h-6 w-11 shrink-0 items-center rounded-full transition-colors ${normalizeN64 ? 'bg-primary-600' : 'bg-dark-700'}`}
+                    aria-pressed={normalizeN64}
+                  >
+                    <span className={`inline-block h-5 w-5 transform rounded-full bg-white transition-transform ${normalizeN64 ? 'translate-x-5' : 'translate-x-1'}`} />
+                  </button>
+                </div>
+              </div>
+            </div>
+          </motion.div>
+
           {/* Storage / Paths */}
           <motion.div
             initial={{ opacity: 0, y: 20 }}
@@ -0,0 +1,72 @@
+import { useCallback, useEffect, useRef, useState } from 'react';
+import { Game, useStore } from '../store/useStore';
+import { LibraryPage } from '../utils/tauri';
+
+export const LIBRARY_PAGE_SIZE = 60;
+const MAX_PAGE_SIZE = 500; // Backend cap per request
+
+// Loads library rows a page at a time. Starts over from the first page whenever `key`
+// (filters, sort, search text) changes, and reloads the rows already shown when the
+// store's libraryRevision is bumped after a launch, favorite toggle, delete or install.
+export const useLibraryPages = (
+  fetchPage: (offset: number, limit: number) => Promise<LibraryPage>,
+  key: string
+) => {
+  const { libraryRevision } = useStore();
+  const [games, setGames] = useState<Game[]>([]);
+  const [total, setTotal] = useState(0);
+  const [loading, setLoading] = useState(true);
+  const fetchRef = useRef(fetchPage);
+  const requestId = useRef(0);
+  const loadedKey = useRef<string | null>(null);
+  const loadedCount = useRef(0);
+  fetchRef.current = fetchPage;
+
+  useEffect(() => {
+    const id = ++requestId.current;
+    const sameKey = loadedKey.current === key;
+    const limit = sameKey
+      ? Math.min(MAX_PAGE_SIZE, Math.max(LIBRARY_PAGE_SIZE, loadedCount.current))
+      : LIBRARY_PAGE_SIZE;
+
+    setLoading(true);
+    fetchRef.current(0, limit)
+      .then((page) => {
+        if (id !== requestId.current) return;
+        loadedKey.current = key;
+        loadedCount.current = page.games.length;
+        setGames(page.games);
+        setTotal(page.total);
+      })
+      .catch((error) => console.error('Failed to load library page:', error))
+      .finally(() => {
+        if (id === requestId.current) setLoading(false);
+      });
+  }, [key, libraryRevision]);
+
+  const hasMore = games.length < total;
+
+  const loadMore = useCallback(() => {
+    if (loading || !hasMore) return;
+    const id = ++requestId.current;
+
+    setLoading(true);
+    fetchRef.current(games.length, LIBRARY_PAGE_SIZE)
+      .then((page) => {
+        if (id !== requestId.current) return;
+        setGames((prev) => {
+          const seen = new Set(prev.map((g) => g.id));
+          const next = [...prev, ...page.games.filter((g) => !seen.has(g.id))];
+          loadedCount.current = next.length;
+          return next;
+        });
+        setTotal(page.total);
+      })
+      .catch((error) => console.error('Failed to load library page:', error))
+      .finally(() => {
+        if (id === requestId.current) setLoading(false);
+      });
+  }, [loading, hasMore, games.length]);
+
+  return { games, total, loading, hasMore, loadMore };
+};
@@ -16,6 +16,16 @@ export interface Game {
   play_count: number;
   total_playtime: number;
   last_played?: string;
+  core_override?: string | null;
+  crc32?: string | null;
+  md5?: string | null;
+  sha1?: string | null;
+  verified?: number;
+  region?: string | null;
+  revision?: string | null;
+  // Set on patched copies (translations, hacks) of another library game
+  base_game_id?: string | null;
+  soft_patch?: string | null;
 }
 
 export interface Emulator {
@@ -36,10 +46,12 @@ export interface DownloadProgress {
   bytesReceived?: number;
   totalBytes?: number;
   speedBps?: number;
+  queued?: boolean; // Managed by the download queue (can be paused, resumed, cancelled)
 }
 
 interface AppStore {
   games: Game[];
+  libraryRevision: number; // Bumped when library rows change; paged views re-query on it
   emulators: Emulator[];
   downloads: DownloadProgress[];
   selectedGame: Game | null;
@@ -62,6 +74,7 @@ interface AppStore {
   windowedFullscreen: boolean; // borderless fullscreen
 
   setGames: (games: Game[]) => void;
+  refreshLibrary: () => void;
   setEmulators: (emulators: Emulator[]) => void;
   setSelectedGame: (game: Game | null) => void;
   setViewMode: (mode: 'grid' | 'list') => void;
@@ -91,6 +104,7 @@ export const useStore = create<AppStore>()(
   persist(
     (set) => ({
       games: [],
+      libraryRevision: 0,
       emulators: [],
       downloads: [],
       selectedGame: null,
@@ -113,6 +127,7 @@ export const useStore = create<AppStore>()(
       windowedFullscreen: false,
 
       setGames: (games) => set({ games }),
+      refreshLibrary: () => set((state) => ({ libraryRevision: state.libraryRevision + 1 })),
       setEmulators: (emulators) => set({ emulators }),
       setSelectedGame: (selectedGame) => set({ selectedGame }),
       setViewMode: (viewMode) => set({ viewMode }),
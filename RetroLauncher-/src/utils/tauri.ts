@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/tauri';
+import { invoke as tauriInvoke } from '@tauri-apps/api/tauri';
 import { Game, Emulator } from '../store/useStore';
 
 // CrocDB Types
@@ -24,6 +24,20 @@ export interface DownloadLink {
   host: string;
 }
 
+// Picks one of an entry's download links; set one field
+export interface LinkSelector {
+  index?: number;
+  filename?: string;
+  format?: string;
+}
+
+// Stored as the 'link_preferences' setting; ranks links when no selector is given
+export interface LinkPreferences {
+  preferred_hosts: string[];
+  preferred_formats: string[];
+  prefer_smallest: boolean;
+}
+
 export interface PlatformInfo {
   brand: string;
   name: string;
@@ -57,6 +71,8 @@ export interface RecommendedEmulator {
   executable_name_linux: string | null;
   logo_url: string | null;
   install_instructions: string;
+  // Supports {rom}, {rom_dir}, {rom_stem}, {fullscreen} and {fullscreen:FLAG}
+  default_arguments: string | null;
 }
 
 // RetroArch Types
@@ -77,12 +93,284 @@ export interface RetroArchCore {
   recommended: boolean;
 }
 
+export type CoreRule = 'game_override' | 'platform_default' | 'recommended' | 'first_installed';
+
+export interface CoreResolution {
+  core: RetroArchCore;
+  rule: CoreRule;
+}
+
+// Process supervisor types
+export interface RunningGame {
+  game_id: string;
+  title: string;
+  pid: number | null;
+  started_at: string;
+}
+
+// Play history types
+export interface PlaySession {
+  id: number;
+  game_id: string;
+  started_at: string;
+  ended_at: string;
+  duration: number;
+  core: string | null;
+  exit_status: string;
+  exit_code: number | null;
+}
+
+export interface PeriodPlaytime {
+  period: string;
+  total_seconds: number;
+  sessions: number;
+}
+
+export interface PlatformPlaytime {
+  platform: string;
+  total_seconds: number;
+  sessions: number;
+}
+
+export interface GamePlaytime {
+  game_id: string;
+  title: string;
+  platform: string;
+  total_seconds: number;
+  sessions: number;
+}
+
+export interface ScanSummary {
+  files_scanned: number;
+  added: number;
+  duplicates: number;
+  unrecognized: number;
+  added_game_ids: string[];
+}
+
+export type RuleField =
+  | 'title'
+  | 'platform'
+  | 'genre'
+  | 'developer'
+  | 'region'
+  | 'release_year'
+  | 'playtime'
+  | 'play_count'
+  | 'last_played'
+  | 'favorite'
+  | 'verified'
+  | 'tag';
+
+export type RuleOp = 'eq' | 'ne' | 'contains' | 'not_contains' | 'lt' | 'lte' | 'gt' | 'gte';
+
+// e.g. { field: 'playtime', op: 'lt', value: '1h' }; playtime takes seconds or "1h", "90m", "1h30m"
+export interface SmartRule {
+  field: RuleField;
+  op: RuleOp;
+  value: string | number | boolean;
+}
+
+export interface SmartRules {
+  match?: 'all' | 'any';
+  rules: SmartRule[];
+}
+
+export interface Collection {
+  id: number;
+  name: string;
+  description?: string | null;
+  // Set for smart collections
+  rules?: SmartRules | null;
+  game_count: number;
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface Tag {
+  id: number;
+  name: string;
+  color?: string | null;
+  game_count: number;
+}
+
+export type LibrarySort = 'relevance' | 'title' | 'platform' | 'last_played' | 'playtime' | 'play_count' | 'added';
+
+// Title and platform sort ascending by default, the rest descending; limit defaults to 50 (max 500)
+export interface GameQuery {
+  platform?: string;
+  favorite?: boolean;
+  sort?: LibrarySort;
+  descending?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export interface LibraryPlatform {
+  platform: string;
+  game_count: number;
+}
+
+// Everything is optional; sort defaults to relevance with a query and title without
+export interface LibrarySearch {
+  query?: string;
+  platform?: string;
+  genre?: string;
+  tag?: string;
+  favorite?: boolean;
+  verified?: boolean;
+  sort?: LibrarySort;
+  descending?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export interface LibraryPage {
+  games: Game[];
+  total: number;
+  offset: number;
+  limit: number;
+}
+
+export interface DatFile {
+  id: number;
+  name: string;
+  description?: string;
+  version?: string;
+  entry_count: number;
+  imported_at?: string;
+}
+
+export interface RomHashes {
+  size: number;
+  crc32: string;
+  md5: string;
+  sha1: string;
+}
+
+export interface Verification {
+  game_id: string;
+  verified: boolean;
+  hashes: RomHashes;
+  matched: {
+    dat_name: string;
+    game_name: string;
+    rom_name: string;
+    region?: string;
+    revision?: string;
+  } | null;
+}
+
+// BIOS files in RetroArch's system directory
+export interface BiosFileStatus {
+  filename: string;
+  expected_md5: string;
+  present: boolean;
+  verified: boolean;
+}
+
+export interface BiosStatus {
+  platform: string;
+  description: string;
+  // 'cores' lists the core ids that can't start without it
+  need: { kind: 'always' } | { kind: 'cores'; cores: string[] } | { kind: 'optional' };
+  content: string[];
+  files: BiosFileStatus[];
+  present: boolean;
+  verified: boolean;
+}
+
+export interface BiosImportSummary {
+  files_scanned: number;
+  imported: string[];
+  already_present: number;
+}
+
+export interface DownloadItem {
+  slug: string;
+  title: string;
+  platform: string;
+  status: 'queued' | 'active' | 'paused' | 'failed' | 'completed';
+  progress: number;
+  bytes_received: number;
+  total_bytes: number;
+  error?: string | null;
+  created_at?: string;
+  updated_at?: string;
+}
+
+// Shape of every error returned by a backend command
+export interface AppError {
+  kind:
+    | 'rate_limited'
+    | 'not_found'
+    | 'http'
+    | 'network'
+    | 'parse'
+    | 'disk_full'
+    | 'io'
+    | 'database'
+    | 'retroarch'
+    | 'install'
+    | 'other';
+  message: string;
+  retryable: boolean;
+  details: Record<string, unknown> | null;
+}
+
+// Thrown by tauriApi calls; stringifies to the plain message so existing
+// `${error}` alerts keep reading the same
+export class CommandError extends Error {
+  kind: AppError['kind'];
+  retryable: boolean;
+  details: AppError['details'];
+
+  constructor(error: AppError) {
+    super(error.message);
+    this.name = 'CommandError';
+    this.kind = error.kind;
+    this.retryable = error.retryable;
+    this.details = error.details;
+  }
+
+  toString(): string {
+    return this.message;
+  }
+}
+
+const isAppError = (error: unknown): error is AppError =>
+  typeof error === 'object' &&
+  error !== null &&
+  typeof (error as AppError).kind === 'string' &&
+  typeof (error as AppError).message === 'string';
+
+async function invoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  try {
+    return await tauriInvoke<T>(command, args);
+  } catch (error) {
+    throw isAppError(error) ? new CommandError(error) : error;
+  }
+}
+
 export const tauriApi = {
   // Game operations
   async getGames(): Promise<Game[]> {
     return await invoke('get_games');
   },
 
+  // Prefer these over getGames in views: they return one page plus the total
+  async queryGames(query: GameQuery = {}): Promise<LibraryPage> {
+    return await invoke('query_games', { query });
+  },
+
+  async getLibraryPlatforms(): Promise<LibraryPlatform[]> {
+    return await invoke('get_library_platforms');
+  },
+
+  async searchLibrary(search: LibrarySearch = {}): Promise<LibraryPage> {
+    return await invoke('search_library', { search });
+  },
+
   async addGame(game: Game): Promise<string> {
     return await invoke('add_game', { game });
   },
@@ -91,6 +379,30 @@ export const tauriApi = {
     return await invoke('launch_game', { gameId });
   },
 
+  async resolveGameCore(gameId: string): Promise<CoreResolution> {
+    return await invoke('resolve_game_core', { gameId });
+  },
+
+  async setGameCoreOverride(gameId: string, coreId: string | null): Promise<void> {
+    return await invoke('set_game_core_override', { gameId, coreId });
+  },
+
+  async setPlatformDefaultCore(platform: string, coreId: string | null): Promise<void> {
+    return await invoke('set_platform_default_core', { platform, coreId });
+  },
+
+  async setGameEmulator(gameId: string, emulatorId: string): Promise<void> {
+    return await invoke('set_game_emulator', { gameId, emulatorId });
+  },
+
+  async getRunningGames(): Promise<RunningGame[]> {
+    return await invoke('get_running_games');
+  },
+
+  async stopGame(gameId: string): Promise<void> {
+    return await invoke('stop_game', { gameId });
+  },
+
   async deleteGame(gameId: string): Promise<void> {
     return await invoke('delete_game', { gameId });
   },
@@ -103,6 +415,89 @@ export const tauriApi = {
     return await invoke('get_game_stats', { gameId });
   },
 
+  // Collections; pass rules to make a smart collection
+  async getCollections(): Promise<Collection[]> {
+    return await invoke('get_collections');
+  },
+
+  async createCollection(name: string, description?: string, rules?: SmartRules): Promise<Collection> {
+    return await invoke('create_collection', { name, description: description || null, rules: rules ?? null });
+  },
+
+  async updateCollection(
+    collectionId: number,
+    name: string,
+    description?: string,
+    rules?: SmartRules
+  ): Promise<Collection> {
+    return await invoke('update_collection', {
+      collectionId,
+      name,
+      description: description || null,
+      rules: rules ?? null,
+    });
+  },
+
+  async deleteCollection(collectionId: number): Promise<void> {
+    return await invoke('delete_collection', { collectionId });
+  },
+
+  async addGameToCollection(collectionId: number, gameId: string): Promise<void> {
+    return await invoke('add_game_to_collection', { collectionId, gameId });
+  },
+
+  async removeGameFromCollection(collectionId: number, gameId: string): Promise<void> {
+    return await invoke('remove_game_from_collection', { collectionId, gameId });
+  },
+
+  async getCollectionGames(collectionId: number): Promise<Game[]> {
+    return await invoke('get_collection_games', { collectionId });
+  },
+
+  // Tags
+  async getTags(): Promise<Tag[]> {
+    return await invoke('get_tags');
+  },
+
+  async getGameTags(gameId: string): Promise<Tag[]> {
+    return await invoke('get_game_tags', { gameId });
+  },
+
+  // Replaces the game's tags; unknown names become new tags
+  async setGameTags(gameId: string, tags: string[]): Promise<Tag[]> {
+    return await invoke('set_game_tags', { gameId, tags });
+  },
+
+  async updateTag(tagId: number, name: string, color?: string): Promise<void> {
+    return await invoke('update_tag', { tagId, name, color: color || null });
+  },
+
+  async deleteTag(tagId: number): Promise<void> {
+    return await invoke('delete_tag', { tagId });
+  },
+
+  // Play history
+  async getPlaySessions(gameId?: string, from?: string, to?: string, limit?: number): Promise<PlaySession[]> {
+    return await invoke('get_play_sessions', {
+      gameId: gameId || null,
+      from: from || null,
+      to: to || null,
+      limit: limit ?? null,
+    });
+  },
+
+  async getPlaytimeByPeriod(period: 'day' | 'week' | 'month', from?: string, to?: string): Promise<PeriodPlaytime[]> {
+    return await invoke('get_playtime_by_period', { period, from: from || null, to: to || null });
+  },
+
+  async getPlaytimeByPlatform(from?: string, to?: string): Promise<PlatformPlaytime[]> {
+    return await invoke('get_playtime_by_platform', { from: from || null, to: to || null });
+  },
+
+  async getTopGames(from?: string, to?: string, limit?: number): Promise<GamePlaytime[]> {
+    return await invoke('get_top_games', { from: from || null, to: to || null, limit: limit ?? null });
+  },
+
   // Emulator operations
   async getEmulators(): Promise<Emulator[]> {
     return await invoke('get_emulators');
@@ -161,12 +556,75 @@ export const tauriApi = {
     return await invoke('get_crocdb_info');
   },
 
-  async downloadFromCrocDB(slug: string, destination: string): Promise<string> {
-    return await invoke('download_from_crocdb', { slug, destination });
+  // Platforms, regions, entries and searches are cached; returns the number of responses removed
+  async clearCrocDBCache(): Promise<number> {
+    return await invoke('clear_crocdb_cache');
+  },
+
+  async downloadFromCrocDB(slug: string, destination: string, link?: LinkSelector): Promise<string> {
+    return await invoke('download_from_crocdb', { slug, destination, link: link ?? null });
+  },
+
+  // Queues the install; progress arrives on 'download-progress'
+  async installGameFromCrocDB(slug: string, link?: LinkSelector): Promise<string> {
+    return await invoke('install_game_from_crocdb', { slug, link: link ?? null });
+  },
+
+  // Download queue
+  async listDownloads(): Promise<DownloadItem[]> {
+    return await invoke('list_downloads');
+  },
+
+  async pauseDownload(slug: string): Promise<void> {
+    return await invoke('pause_download', { slug });
+  },
+
+  async resumeDownload(slug: string): Promise<void> {
+    return await invoke('resume_download', { slug });
+  },
+
+  async cancelDownload(slug: string): Promise<void> {
+    return await invoke('cancel_download', { slug });
+  },
+
+  // Import ROMs from a local folder (recursive)
+  async scanDirectory(path: string): Promise<ScanSummary> {
+    return await invoke('scan_directory', { path });
+  },
+
+  // No-Intro / Redump DAT verification
+  async importDatFile(path: string): Promise<DatFile> {
+    return await invoke('import_dat_file', { path });
+  },
+
+  async getDatFiles(): Promise<DatFile[]> {
+    return await invoke('get_dat_files');
+  },
+
+  async deleteDatFile(datId: number): Promise<void> {
+    return await invoke('delete_dat_file', { datId });
+  },
+
+  async verifyGame(gameId: string): Promise<Verification> {
+    return await invoke('verify_game', { gameId });
+  },
+
+  async verifyLibrary(): Promise<Verification[]> {
+    return await invoke('verify_library');
+  },
+
+  // Adds a patched copy of the game from an IPS, UPS or BPS file. With soft, RetroArch
+  // applies the patch at launch instead.
+  async patchGame(gameId: string, patchPath: string, soft = false): Promise<Game> {
+    return await invoke('patch_game', { gameId, patchPath, soft });
+  },
+
+  async verifyBios(platform?: string): Promise<BiosStatus[]> {
+    return await invoke('verify_bios', { platform: platform ?? null });
   },
 
-  async installGameFromCrocDB(slug: string): Promise<string> {
-    return await invoke('install_game_from_crocdb', { slug });
+  async importBiosFiles(folder: string): Promise<BiosImportSummary> {
+    return await invoke('import_bios_files', { folder });
   },
 
   // Settings operations